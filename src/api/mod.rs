@@ -1,7 +1,11 @@
+pub mod source;
+pub mod torznab;
 pub mod x1337;
 pub mod yts;
 pub mod piratebay;
 
+pub use source::{SearchRequest, SourceCapabilities, SourceError, SourceResult, TorrentDetails, TorrentFileEntry, TorrentSource};
+pub use torznab::TorznabClient;
 pub use x1337::X1337Client;
 pub use yts::YtsClient;
-pub use piratebay::PirateBayClient;
\ No newline at end of file
+pub use piratebay::PirateBayClient;
@@ -1,50 +1,113 @@
+use crate::api::source::{SearchRequest, SourceCapabilities, SourceResult, TorrentDetails, TorrentFileEntry, TorrentSource};
+use crate::magnet::{normalize_info_hash, tracker_params};
 use crate::torrent_search::TorrentSearchResult;
+use crate::types::Category;
+use async_trait::async_trait;
+use chrono::DateTime;
 use reqwest::Client;
 use serde::Deserialize;
 use std::time::Duration;
 
+/// apibay-compatible API hosts tried in order when no list is configured
+pub const DEFAULT_MIRRORS: &[&str] = &["https://apibay.org"];
+
+/// Trackers added to magnet links built from apibay info-hashes
+pub const DEFAULT_TRACKERS: &[&str] = &[
+    "udp://tracker.coppersurfer.tk:6969/announce",
+    "udp://9.rarbg.to:2920/announce",
+    "udp://tracker.opentrackr.org:1337",
+    "udp://tracker.internetwarriors.net:1337/announce",
+    "udp://tracker.leechers-paradise.org:6969/announce",
+    "udp://tracker.pirateparty.gr:6969/announce",
+    "udp://tracker.cyberia.is:6969/announce",
+];
+
 #[derive(Debug, Clone)]
 pub struct PirateBayClient {
     client: Client,
+    mirrors: Vec<String>,
+    trackers: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct PbSearchResult {
+    #[serde(default)]
+    id: String,
     name: String,
     info_hash: String,
     leechers: String,
     seeders: String,
-    #[allow(dead_code)]
     num_files: String,
     size: String,
-    #[allow(dead_code)]
     username: String,
-    #[allow(dead_code)]
     added: String,
     #[allow(dead_code)]
     status: String,
-    #[allow(dead_code)]
     category: String,
     #[allow(dead_code)]
     imdb: String,
 }
 
+/// One entry of apibay's `f.php` file list; name and size come wrapped in single-element arrays
+#[derive(Debug, Deserialize)]
+struct PbFile {
+    name: Vec<String>,
+    size: Vec<u64>,
+}
+
 impl PirateBayClient {
     pub fn new() -> Self {
+        Self::with_mirrors(DEFAULT_MIRRORS.iter().map(|m| m.to_string()).collect())
+    }
+
+    /// Client trying the given apibay-compatible hosts in order
+    pub fn with_mirrors(mirrors: Vec<String>) -> Self {
         let client = Client::builder()
             .user_agent("TUI-Torrent/1.0")
             .timeout(Duration::from_secs(30))
             .build()
             .expect("Failed to create HTTP client");
 
-    Self { client }
+        Self {
+            client,
+            mirrors: mirrors
+                .into_iter()
+                .map(|m| m.trim_end_matches('/').to_string())
+                .collect(),
+            trackers: DEFAULT_TRACKERS.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    /// Trackers to put into the magnet links this client builds
+    pub fn with_trackers(mut self, trackers: Vec<String>) -> Self {
+        self.trackers = trackers;
+        self
+    }
+
+    pub fn mirrors(&self) -> &[String] {
+        &self.mirrors
     }
 
-    pub async fn search(&self, query: &str, _category: Option<&str>) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
-        // Use apibay.org API (unofficial but more reliable)
+    pub async fn search(&self, query: &str, category: Option<Category>) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
+        // Fall back to the next mirror when one is blocked or down
+        let mut last_error: Box<dyn std::error::Error + Send + Sync> = "no PirateBay mirrors configured".into();
+        for mirror in &self.mirrors {
+            match self.fetch_results(mirror, query, category).await {
+                Ok(pb_results) => return Ok(self.build_results(mirror, pb_results, category)),
+                Err(e) => last_error = e,
+            }
+        }
+
+        Err(last_error)
+    }
+
+    async fn fetch_results(&self, mirror: &str, query: &str, category: Option<Category>) -> Result<Vec<PbSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
+        // Use the apibay API (unofficial but more reliable)
         let search_url = format!(
-            "https://apibay.org/q.php?q={}&cat=0",
-            urlencoding::encode(query)
+            "{}/q.php?q={}&cat={}",
+            mirror,
+            urlencoding::encode(query),
+            category.and_then(category_code).unwrap_or("0")
         );
 
         let response = self.client
@@ -56,10 +119,18 @@ impl PirateBayClient {
             return Err(format!("HTTP error: {}", response.status()).into());
         }
 
-        let pb_results: Vec<PbSearchResult> = response.json().await?;
+        Ok(response.json().await?)
+    }
+
+    fn build_results(&self, mirror: &str, pb_results: Vec<PbSearchResult>, category: Option<Category>) -> Vec<TorrentSearchResult> {
         let mut results = Vec::new();
 
-        for pb_result in pb_results.into_iter().take(20) {
+        // Top-level codes cover neighbouring categories too (Video holds both movies and TV)
+        let pb_results = pb_results
+            .into_iter()
+            .filter(|pb_result| category.is_none_or(|c| category_from_code(&pb_result.category) == Some(c)));
+
+        for pb_result in pb_results.take(20) {
             // Skip if no seeders
             let seeders = pb_result.seeders.parse::<u32>().unwrap_or(0);
             let leechers = pb_result.leechers.parse::<u32>().unwrap_or(0);
@@ -69,25 +140,66 @@ impl PirateBayClient {
             }
 
             let magnet_link = format!(
-                "magnet:?xt=urn:btih:{}&dn={}&tr=udp://tracker.coppersurfer.tk:6969/announce&tr=udp://9.rarbg.to:2920/announce&tr=udp://tracker.opentrackr.org:1337&tr=udp://tracker.internetwarriors.net:1337/announce&tr=udp://tracker.leechers-paradise.org:6969/announce&tr=udp://tracker.coppersurfer.tk:6969/announce&tr=udp://tracker.pirateparty.gr:6969/announce&tr=udp://tracker.cyberia.is:6969/announce",
+                "magnet:?xt=urn:btih:{}&dn={}{}",
                 pb_result.info_hash,
-                urlencoding::encode(&pb_result.name)
+                urlencoding::encode(&pb_result.name),
+                tracker_params(&self.trackers)
             );
 
             results.push(TorrentSearchResult {
-                name: pb_result.name,
                 size: self.format_size(&pb_result.size),
+                size_bytes: pb_result.size.parse().ok(),
+                uploaded_at: pb_result
+                    .added
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+                category: category_from_code(&pb_result.category),
+                uploader: Some(pb_result.username).filter(|name| !name.is_empty()),
+                file_count: pb_result.num_files.parse().ok(),
+                details_url: Some(&pb_result.id)
+                    .filter(|id| !id.is_empty())
+                    .map(|id| format!("{}/f.php?id={}", mirror, id)),
+                name: pb_result.name,
                 seeders,
                 leechers,
                 magnet_link,
                 source: "PirateBay".to_string(),
+                info_hash: normalize_info_hash(&pb_result.info_hash),
+                ..Default::default()
             });
         }
 
         // Sort by seeders (descending)
-        results.sort_by(|a, b| b.seeders.cmp(&a.seeders));
+        results.sort_by_key(|r| std::cmp::Reverse(r.seeders));
 
-        Ok(results)
+        results
+    }
+
+    /// File list of a result from apibay's `f.php`, on the mirror that returned the result
+    pub async fn files(&self, details_url: &str) -> Result<Vec<TorrentFileEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self.client.get(details_url).send().await?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP error: {}", response.status()).into());
+        }
+
+        let files: Vec<PbFile> = response.json().await?;
+        let files: Vec<TorrentFileEntry> = files
+            .into_iter()
+            .filter_map(|file| {
+                Some(TorrentFileEntry {
+                    path: file.name.into_iter().next()?,
+                    size_bytes: file.size.into_iter().next(),
+                })
+            })
+            .collect();
+
+        // apibay answers with a single placeholder entry when it has no list
+        match files.as_slice() {
+            [] => Err("PirateBay has no file list for this torrent".into()),
+            [only] if only.path == "Filelist not found" => Err("PirateBay has no file list for this torrent".into()),
+            _ => Ok(files),
+        }
     }
 
     fn format_size(&self, size_bytes: &str) -> String {
@@ -107,6 +219,64 @@ impl PirateBayClient {
     }
 }
 
+/// apibay `cat` code for a category; the site has no anime section.
+/// Top-level codes so HD and UHD subcategories are searched as well
+fn category_code(category: Category) -> Option<&'static str> {
+    match category {
+        Category::Movies | Category::TV => Some("200"),
+        Category::Music => Some("100"),
+        Category::Games => Some("400"),
+        Category::Software => Some("300"),
+        Category::Anime => None,
+        Category::Books => Some("601"),
+        Category::Other => Some("600"),
+    }
+}
+
+/// Map an apibay category code such as "207" (HD movies) back to a category
+fn category_from_code(code: &str) -> Option<Category> {
+    let code: u32 = code.parse().ok()?;
+    Some(match code {
+        100..=199 => Category::Music,
+        205 | 208 | 212 => Category::TV,
+        200..=299 => Category::Movies,
+        300..=399 => Category::Software,
+        400..=499 => Category::Games,
+        601 => Category::Books,
+        _ => Category::Other,
+    })
+}
+
+#[async_trait]
+impl TorrentSource for PirateBayClient {
+    fn name(&self) -> &str {
+        "PirateBay"
+    }
+
+    fn capabilities(&self) -> SourceCapabilities {
+        SourceCapabilities {
+            categories: true,
+            paging: false,
+            details: true,
+        }
+    }
+
+    async fn search(&self, request: &SearchRequest) -> SourceResult<Vec<TorrentSearchResult>> {
+        self.search(&request.query, request.category).await
+    }
+
+    async fn details(&self, result: &TorrentSearchResult) -> SourceResult<TorrentDetails> {
+        let url = result.details_url.as_deref().ok_or("result has no PirateBay id")?;
+        Ok(TorrentDetails {
+            files: self.files(url).await?,
+        })
+    }
+
+    fn supports_category(&self, category: Category) -> bool {
+        category_code(category).is_some()
+    }
+}
+
 impl Default for PirateBayClient {
     fn default() -> Self {
         Self::new()
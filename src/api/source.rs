@@ -0,0 +1,93 @@
+use crate::torrent_search::TorrentSearchResult;
+use crate::types::Category;
+use async_trait::async_trait;
+
+pub type SourceError = Box<dyn std::error::Error + Send + Sync>;
+pub type SourceResult<T> = Result<T, SourceError>;
+
+/// What a search provider is able to do beyond a plain keyword search
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceCapabilities {
+    /// Provider can restrict a search to a category
+    pub categories: bool,
+    /// Provider can return further result pages
+    pub paging: bool,
+    /// Provider can look up details (file list etc.) for a single result
+    pub details: bool,
+}
+
+/// A single search request handed to every registered source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchRequest {
+    pub query: String,
+    pub category: Option<Category>,
+    /// 1-based page number, only honoured by sources with `paging`
+    pub page: u32,
+    /// Names of the sources to query; `None` queries every registered source
+    pub sources: Option<Vec<String>>,
+}
+
+impl SearchRequest {
+    pub fn new(query: &str) -> Self {
+        Self {
+            query: query.to_string(),
+            category: None,
+            page: 1,
+            sources: None,
+        }
+    }
+
+    pub fn with_category(mut self, category: Option<Category>) -> Self {
+        self.category = category;
+        self
+    }
+
+    pub fn with_page(mut self, page: u32) -> Self {
+        self.page = page.max(1);
+        self
+    }
+
+    pub fn with_sources(mut self, sources: Option<Vec<String>>) -> Self {
+        self.sources = sources;
+        self
+    }
+}
+
+/// A single file inside a torrent, as reported by a provider detail page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TorrentFileEntry {
+    pub path: String,
+    pub size_bytes: Option<u64>,
+}
+
+/// Extra information about one result, fetched on demand
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TorrentDetails {
+    pub files: Vec<TorrentFileEntry>,
+}
+
+/// A torrent search provider that can be registered with `TorrentSearchEngine`
+#[async_trait]
+pub trait TorrentSource: Send + Sync {
+    /// Display name, also used to attribute results
+    fn name(&self) -> &str;
+
+    fn capabilities(&self) -> SourceCapabilities;
+
+    /// Whether this source can restrict a search to the given category
+    fn supports_category(&self, _category: Category) -> bool {
+        self.capabilities().categories
+    }
+
+    /// One-off setup before the first search, e.g. fetching capabilities
+    async fn prepare(&self) -> SourceResult<()> {
+        Ok(())
+    }
+
+    async fn search(&self, request: &SearchRequest) -> SourceResult<Vec<TorrentSearchResult>>;
+
+    /// Look up details for a result previously returned by this source
+    async fn details(&self, _result: &TorrentSearchResult) -> SourceResult<TorrentDetails> {
+        Err(format!("{} does not support detail lookup", self.name()).into())
+    }
+}
@@ -0,0 +1,322 @@
+use crate::api::source::{SearchRequest, SourceCapabilities, SourceResult, TorrentSource};
+use crate::magnet::normalize_info_hash;
+use crate::torrent_search::TorrentSearchResult;
+use crate::types::Category;
+use crate::utils::format_bytes;
+use async_trait::async_trait;
+use chrono::DateTime;
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+const TORZNAB_NS: &str = "http://torznab.com/schemas/2015/feed";
+
+/// Items requested per page, lowered to the indexer's maximum when that is smaller
+const PAGE_SIZE: u32 = 50;
+
+/// Client for a Torznab endpoint as exposed by Jackett or Prowlarr
+///
+/// `base_url` is the full API endpoint, e.g.
+/// `http://localhost:9117/api/v2.0/indexers/all/results/torznab/api` (Jackett) or
+/// `http://localhost:9696/1/api` (Prowlarr).
+#[derive(Debug, Clone)]
+pub struct TorznabClient {
+    client: Client,
+    name: String,
+    base_url: String,
+    api_key: Option<String>,
+    caps: Arc<OnceCell<TorznabCaps>>,
+}
+
+/// Parsed `t=caps` response
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TorznabCaps {
+    /// Indexer category ids for each of our categories
+    pub categories: HashMap<Category, Vec<u32>>,
+    /// Maximum number of items per page
+    pub limit: Option<u32>,
+}
+
+impl TorznabCaps {
+    pub fn parse(xml: &str) -> SourceResult<Self> {
+        let document = roxmltree::Document::parse(xml)?;
+        check_error(&document)?;
+
+        let mut caps = TorznabCaps::default();
+
+        if let Some(limits) = document.descendants().find(|n| n.has_tag_name("limits")) {
+            caps.limit = limits
+                .attribute("max")
+                .or_else(|| limits.attribute("default"))
+                .and_then(|v| v.parse().ok());
+        }
+
+        for node in document
+            .descendants()
+            .filter(|n| n.has_tag_name("category") || n.has_tag_name("subcat"))
+        {
+            let (Some(id), Some(name)) = (
+                node.attribute("id").and_then(|id| id.parse::<u32>().ok()),
+                node.attribute("name"),
+            ) else {
+                continue;
+            };
+            if let Some(category) = category_for(id, name) {
+                caps.categories.entry(category).or_default().push(id);
+            }
+        }
+
+        Ok(caps)
+    }
+}
+
+impl TorznabClient {
+    pub fn new(base_url: &str, api_key: Option<&str>) -> Self {
+        let client = Client::builder()
+            .user_agent("TUI-Torrent/1.0")
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            client,
+            name: "Torznab".to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.map(|key| key.to_string()),
+            caps: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// Display name used to attribute results, e.g. "Jackett"
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Fetch and cache the endpoint's capabilities
+    pub async fn fetch_caps(&self) -> SourceResult<&TorznabCaps> {
+        self.caps
+            .get_or_try_init(|| async {
+                let xml = self.get(&[("t", "caps".to_string())]).await?;
+                TorznabCaps::parse(&xml)
+            })
+            .await
+    }
+
+    pub async fn search(&self, request: &SearchRequest) -> SourceResult<Vec<TorrentSearchResult>> {
+        let mut params = vec![
+            ("t", "search".to_string()),
+            ("q", request.query.clone()),
+        ];
+
+        // Category ids come from the caps when available, otherwise the standard Newznab ranges
+        let caps = self.fetch_caps().await.ok();
+
+        if let Some(category) = request.category {
+            let ids = match caps.and_then(|caps| caps.categories.get(&category)) {
+                Some(ids) => ids.clone(),
+                None => standard_ids(category).to_vec(),
+            };
+            params.push(("cat", ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",")));
+        }
+
+        // The same explicit page size on every page, so offsets line up with what earlier pages returned
+        let limit = caps.and_then(|caps| caps.limit).filter(|&max| max > 0).map_or(PAGE_SIZE, |max| max.min(PAGE_SIZE));
+        params.push(("limit", limit.to_string()));
+        params.push(("offset", (request.page.saturating_sub(1) * limit).to_string()));
+
+        let xml = self.get(&params).await?;
+        let mut results = parse_results(&xml, &self.name)?;
+        results.sort_by_key(|r| std::cmp::Reverse(r.seeders));
+        Ok(results)
+    }
+
+    async fn get(&self, params: &[(&str, String)]) -> SourceResult<String> {
+        let mut query: Vec<(&str, &str)> = params.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        if let Some(key) = &self.api_key {
+            query.push(("apikey", key));
+        }
+
+        let response = self.client
+            .get(&self.base_url)
+            .query(&query)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("HTTP error: {}", response.status()).into());
+        }
+
+        Ok(response.text().await?)
+    }
+}
+
+/// Parse a Torznab RSS search response into results attributed to `source`
+pub fn parse_results(xml: &str, source: &str) -> SourceResult<Vec<TorrentSearchResult>> {
+    let document = roxmltree::Document::parse(xml)?;
+    check_error(&document)?;
+
+    let mut results = Vec::new();
+
+    for item in document.descendants().filter(|n| n.has_tag_name("item")) {
+        let child_text = |tag: &str| {
+            item.children()
+                .find(|n| n.has_tag_name(tag))
+                .and_then(|n| n.text())
+                .map(|t| t.trim().to_string())
+        };
+        let attrs: HashMap<&str, &str> = item
+            .children()
+            .filter(|n| n.has_tag_name((TORZNAB_NS, "attr")))
+            .filter_map(|n| Some((n.attribute("name")?, n.attribute("value")?)))
+            .collect();
+
+        let Some(name) = child_text("title") else {
+            continue;
+        };
+
+        let info_hash = attrs.get("infohash").and_then(|hash| normalize_info_hash(hash));
+        let link = child_text("link");
+        let magnet_link = match (attrs.get("magneturl"), &link, &info_hash) {
+            (Some(magnet), _, _) => magnet.to_string(),
+            (None, Some(link), _) if link.starts_with("magnet:") => link.clone(),
+            (None, _, Some(hash)) => format!("magnet:?xt=urn:btih:{}&dn={}", hash, urlencoding::encode(&name)),
+            // Only a .torrent download link; aria2 accepts those as well
+            (None, Some(link), None) => link.clone(),
+            (None, None, None) => continue,
+        };
+
+        let size_bytes = attrs
+            .get("size")
+            .map(|s| s.to_string())
+            .or_else(|| child_text("size"))
+            .and_then(|s| s.parse::<u64>().ok());
+        let seeders = attrs.get("seeders").and_then(|s| s.parse::<u32>().ok()).unwrap_or(0);
+        // Torznab "peers" counts seeders and leechers together
+        let leechers = attrs
+            .get("leechers")
+            .and_then(|s| s.parse::<u32>().ok())
+            .or_else(|| {
+                attrs.get("peers")
+                    .and_then(|s| s.parse::<u32>().ok())
+                    .map(|peers| peers.saturating_sub(seeders))
+            })
+            .unwrap_or(0);
+
+        results.push(TorrentSearchResult {
+            name,
+            size: size_bytes.map(format_bytes).unwrap_or_default(),
+            seeders,
+            leechers,
+            magnet_link,
+            source: source.to_string(),
+            info_hash,
+            size_bytes,
+            uploaded_at: child_text("pubDate")
+                .and_then(|date| DateTime::parse_from_rfc2822(&date).ok())
+                .map(|date| date.to_utc()),
+            category: attrs
+                .get("category")
+                .and_then(|id| id.parse::<u32>().ok())
+                .and_then(|id| category_for(id, "")),
+            uploader: attrs.get("poster").map(|s| s.to_string()),
+            file_count: attrs.get("files").and_then(|s| s.parse().ok()),
+            ..Default::default()
+        });
+    }
+
+    Ok(results)
+}
+
+/// Torznab reports failures as `<error code=".." description=".."/>` with a 200 status
+fn check_error(document: &roxmltree::Document) -> SourceResult<()> {
+    let root = document.root_element();
+    if root.has_tag_name("error") {
+        return Err(format!(
+            "Torznab error {}: {}",
+            root.attribute("code").unwrap_or("?"),
+            root.attribute("description").unwrap_or("unknown error")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Standard Newznab category ids for each category
+fn standard_ids(category: Category) -> &'static [u32] {
+    match category {
+        Category::Movies => &[2000],
+        Category::TV => &[5000],
+        Category::Music => &[3000],
+        Category::Games => &[1000, 4050],
+        Category::Software => &[4000],
+        Category::Anime => &[5070],
+        Category::Books => &[7000],
+        Category::Other => &[8000],
+    }
+}
+
+/// Map a Newznab category id to a category; indexer specific ids (100000+) go by name
+fn category_for(id: u32, name: &str) -> Option<Category> {
+    match id {
+        5070 => Some(Category::Anime),
+        4050 => Some(Category::Games),
+        1000..=1999 => Some(Category::Games),
+        2000..=2999 => Some(Category::Movies),
+        3000..=3999 => Some(Category::Music),
+        4000..=4999 => Some(Category::Software),
+        5000..=5999 => Some(Category::TV),
+        7000..=7999 => Some(Category::Books),
+        8000..=8999 => Some(Category::Other),
+        _ => {
+            let name = name.to_lowercase();
+            [
+                ("anime", Category::Anime),
+                ("movie", Category::Movies),
+                ("tv", Category::TV),
+                ("music", Category::Music),
+                ("audio", Category::Music),
+                ("game", Category::Games),
+                ("book", Category::Books),
+                ("software", Category::Software),
+                ("app", Category::Software),
+            ]
+            .into_iter()
+            .find(|(keyword, _)| name.contains(keyword))
+            .map(|(_, category)| category)
+        }
+    }
+}
+
+#[async_trait]
+impl TorrentSource for TorznabClient {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> SourceCapabilities {
+        SourceCapabilities {
+            categories: true,
+            paging: true,
+            details: false,
+        }
+    }
+
+    /// Before the caps are known every category is assumed to be served
+    fn supports_category(&self, category: Category) -> bool {
+        match self.caps.get() {
+            Some(caps) => caps.categories.contains_key(&category),
+            None => true,
+        }
+    }
+
+    async fn prepare(&self) -> SourceResult<()> {
+        self.fetch_caps().await.map(|_| ())
+    }
+
+    async fn search(&self, request: &SearchRequest) -> SourceResult<Vec<TorrentSearchResult>> {
+        TorznabClient::search(self, request).await
+    }
+}
@@ -1,33 +1,62 @@
+use crate::api::source::{SearchRequest, SourceCapabilities, SourceResult, TorrentDetails, TorrentFileEntry, TorrentSource};
+use crate::magnet;
 use crate::torrent_search::TorrentSearchResult;
+use crate::types::Category;
+use crate::utils::parse_size;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use regex::Regex;
 use reqwest::Client;
-use scraper::{Html, Selector};
-// No additional imports needed
+use scraper::{ElementRef, Html, Selector};
+use std::sync::LazyLock;
+use std::time::Duration;
 
+/// Mirrors tried in order when no list is configured
+pub const DEFAULT_MIRRORS: &[&str] = &[
+    "https://1337x.to",
+    "https://1337x.st",
+    "https://x1337x.ws",
+    "https://x1337x.eu",
+];
+
+/// A file in the "Files" tab, e.g. "Movie.mkv (1.4 GB)"
+static FILE_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.+?)\s*\(([\d.,]+\s*[KMGT]?i?B)\)$").unwrap());
+
+/// Listing date such as "Jan. 5th '24", matched once per result row
+static LISTING_DATE_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([A-Za-z]{3})\.?\s+(\d{1,2})(?:st|nd|rd|th)\s+'(\d{2})$").unwrap());
 
 #[derive(Debug, Clone)]
 pub struct X1337Client {
     client: Client,
-    base_url: String,
+    mirrors: Vec<String>,
+    /// Pause before each listing request to avoid rate limiting
+    request_delay: Duration,
 }
 
-// (Removed unused TorrentInfo struct)
+/// One row of a 1337x listing page, before its magnet link is resolved
+struct ListingRow {
+    name: String,
+    href: String,
+    seeders: u32,
+    leechers: u32,
+    size: String,
+    uploaded_at: Option<DateTime<Utc>>,
+    uploader: Option<String>,
+}
 
 impl X1337Client {
     pub fn new() -> Self {
-        let client = Client::builder()
-            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-            .timeout(std::time::Duration::from_secs(30))
-            .cookie_store(true)
-            .build()
-            .expect("Failed to create HTTP client");
-
-        Self {
-            client,
-            base_url: "https://1337x.to".to_string(),
-        }
+        Self::with_mirrors(DEFAULT_MIRRORS.iter().map(|m| m.to_string()).collect())
     }
 
     pub fn with_mirror(mirror_url: &str) -> Self {
+        Self::with_mirrors(vec![mirror_url.to_string()])
+    }
+
+    /// Client trying the given mirrors in order; magnet pages are fetched from whichever mirror answered
+    pub fn with_mirrors(mirrors: Vec<String>) -> Self {
         let client = Client::builder()
             .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
             .timeout(std::time::Duration::from_secs(30))
@@ -37,40 +66,51 @@ impl X1337Client {
 
         Self {
             client,
-            base_url: mirror_url.to_string(),
+            mirrors: mirrors
+                .into_iter()
+                .map(|m| m.trim_end_matches('/').to_string())
+                .collect(),
+            request_delay: Duration::from_millis(1000),
         }
     }
 
-    pub async fn search(&self, query: &str, category: Option<&str>) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
+    pub fn with_request_delay(mut self, delay: Duration) -> Self {
+        self.request_delay = delay;
+        self
+    }
+
+    pub fn mirrors(&self) -> &[String] {
+        &self.mirrors
+    }
+
+    pub async fn search(&self, query: &str, category: Option<Category>) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
+        self.search_page(query, category, 1).await
+    }
+
+    pub async fn search_page(&self, query: &str, category: Option<Category>, page: u32) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
         // Try multiple mirrors if the main one fails
-        let mirrors = vec![
-            "https://1337x.to",
-            "https://1337x.st",
-            "https://x1337x.ws",
-            "https://x1337x.eu",
-        ];
-
-        for mirror in mirrors {
-            match self.try_search_with_mirror(mirror, query, category).await {
+        let mut last_error = None;
+        for mirror in &self.mirrors {
+            match self.try_search_with_mirror(mirror, query, category, page).await {
                 Ok(results) => return Ok(results),
-                Err(_) => {
-                    // Silently try next mirror
-                    continue;
-                }
+                Err(e) => last_error = Some(e),
             }
         }
 
-        // If all mirrors fail, return empty results
-        Ok(Vec::new())
+        Err(match last_error {
+            Some(e) => format!("all 1337x mirrors failed, last error: {}", e).into(),
+            None => "no 1337x mirrors configured".into(),
+        })
     }
 
-    async fn try_search_with_mirror(&self, mirror: &str, query: &str, category: Option<&str>) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
+    async fn try_search_with_mirror(&self, mirror: &str, query: &str, category: Option<Category>, page: u32) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
         // Add delay to avoid rate limiting
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        tokio::time::sleep(self.request_delay).await;
         
-        let search_url = match category {
-            Some(cat) => format!("{}/category-search/{}/{}/1/", mirror, query, cat),
-            None => format!("{}/search/{}/1/", mirror, query),
+        let query = urlencoding::encode(query);
+        let search_url = match category.and_then(category_slug) {
+            Some(slug) => format!("{}/category-search/{}/{}/{}/", mirror, query, slug, page),
+            None => format!("{}/search/{}/{}/", mirror, query, page),
         };
 
         // Trying to search mirror silently
@@ -92,20 +132,60 @@ impl X1337Client {
         }
 
         let html = response.text().await?;
-        self.parse_search_results(&html).await
+        let mut results = self.parse_search_results(mirror, &html).await?;
+        for result in &mut results {
+            result.category = category;
+        }
+        Ok(results)
     }
 
 
 
-    async fn parse_search_results(&self, html: &str) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
+    async fn parse_search_results(&self, mirror: &str, html: &str) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
+        // The parsed document is not `Send`, so collect the rows before awaiting anything
+        let rows = Self::parse_listing_rows(html);
+        let mut results = Vec::new();
+
+        for row in rows {
+            // Get magnet link by visiting the torrent page
+            let torrent_url = torrent_url(mirror, &row.href);
+            if let Ok(magnet_link) = self.get_magnet_link(&torrent_url).await {
+                results.push(TorrentSearchResult {
+                    name: row.name,
+                    size_bytes: parse_size(&row.size),
+                    size: row.size,
+                    uploaded_at: row.uploaded_at,
+                    uploader: row.uploader,
+                    seeders: row.seeders,
+                    leechers: row.leechers,
+                    info_hash: magnet::info_hash(&magnet_link),
+                    magnet_link,
+                    source: "1337x".to_string(),
+                    details_url: Some(torrent_url),
+                    ..Default::default()
+                });
+            }
+
+            // Limit results to avoid too many requests
+            if results.len() >= 10 {
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn parse_listing_rows(html: &str) -> Vec<ListingRow> {
         let document = Html::parse_document(html);
         let row_selector = Selector::parse("tbody tr").unwrap();
         let name_selector = Selector::parse("td.coll-1 a:nth-child(2)").unwrap();
         let seeders_selector = Selector::parse("td.coll-2").unwrap();
         let leechers_selector = Selector::parse("td.coll-3").unwrap();
         let size_selector = Selector::parse("td.coll-4").unwrap();
+        let date_selector = Selector::parse("td.coll-date").unwrap();
+        let uploader_selector = Selector::parse("td.coll-5").unwrap();
 
-        let mut results = Vec::new();
+        let mut rows = Vec::new();
 
         for row in document.select(&row_selector) {
             if let (Some(name_elem), Some(seeders_elem), Some(leechers_elem), Some(size_elem)) = (
@@ -114,41 +194,33 @@ impl X1337Client {
                 row.select(&leechers_selector).next(),
                 row.select(&size_selector).next(),
             ) {
-                let name = name_elem.inner_html().trim().to_string();
-                let href = name_elem.value().attr("href").unwrap_or("");
-                
-                // Parse seeders and leechers
-                let seeders = seeders_elem.inner_html().trim().parse::<u32>().unwrap_or(0);
-                let leechers = leechers_elem.inner_html().trim().parse::<u32>().unwrap_or(0);
-                let size = size_elem.inner_html().trim().to_string();
-
-                // Get magnet link by visiting the torrent page
-                if let Ok(magnet_link) = self.get_magnet_link(href).await {
-                    results.push(TorrentSearchResult {
-                        name,
-                        size,
-                        seeders,
-                        leechers,
-                        magnet_link,
-                        source: "1337x".to_string(),
-                    });
-                }
-
-                // Limit results to avoid too many requests
-                if results.len() >= 10 {
-                    break;
-                }
+                rows.push(ListingRow {
+                    name: name_elem.inner_html().trim().to_string(),
+                    href: name_elem.value().attr("href").unwrap_or("").to_string(),
+                    seeders: seeders_elem.inner_html().trim().parse::<u32>().unwrap_or(0),
+                    leechers: leechers_elem.inner_html().trim().parse::<u32>().unwrap_or(0),
+                    // The size cell also holds a hidden seeders span; only the leading text is the size
+                    size: size_elem.text().next().unwrap_or("").trim().to_string(),
+                    uploaded_at: row
+                        .select(&date_selector)
+                        .next()
+                        .and_then(|elem| parse_listing_date(&elem.text().collect::<String>())),
+                    uploader: row
+                        .select(&uploader_selector)
+                        .next()
+                        .map(|elem| elem.text().collect::<String>().trim().to_string())
+                        .filter(|name| !name.is_empty()),
+                });
             }
         }
 
-        Ok(results)
+        rows
     }
 
-    async fn get_magnet_link(&self, torrent_path: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        let torrent_url = format!("{}{}", self.base_url, torrent_path);
-        
+    /// Fetch the magnet link from the torrent page on the mirror that served the listing
+    async fn get_magnet_link(&self, torrent_url: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         let response = self.client
-            .get(&torrent_url)
+            .get(torrent_url)
             .send()
             .await?;
 
@@ -158,14 +230,139 @@ impl X1337Client {
         // Look for magnet link
         let magnet_selector = Selector::parse("a[href^='magnet:']").unwrap();
         
-        if let Some(magnet_elem) = document.select(&magnet_selector).next() {
-            if let Some(href) = magnet_elem.value().attr("href") {
-                return Ok(href.to_string());
-            }
+        if let Some(href) = document
+            .select(&magnet_selector)
+            .next()
+            .and_then(|magnet_elem| magnet_elem.value().attr("href"))
+        {
+            return Ok(href.to_string());
         }
 
         Err("Magnet link not found".into())
     }
+
+    /// File list shown on a torrent page
+    pub async fn files(&self, torrent_url: &str) -> Result<Vec<TorrentFileEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self.client.get(torrent_url).send().await?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP error: {}", response.status()).into());
+        }
+
+        let files = parse_file_list(&response.text().await?);
+        if files.is_empty() {
+            return Err("no file list on the 1337x torrent page".into());
+        }
+        Ok(files)
+    }
+}
+
+fn torrent_url(mirror: &str, torrent_path: &str) -> String {
+    if torrent_path.starts_with("http") {
+        torrent_path.to_string()
+    } else {
+        format!("{}{}", mirror, torrent_path)
+    }
+}
+
+/// Parse the "Files" tab of a torrent page, where each file reads like "Movie.mkv (1.4 GB)"
+/// and folders are list items holding a nested list
+fn parse_file_list(html: &str) -> Vec<TorrentFileEntry> {
+    let document = Html::parse_document(html);
+    let item_selector = Selector::parse("#files li, .file-content li").unwrap();
+
+    // Text of the item itself, without any nested list
+    let own_text = |item: ElementRef| -> String {
+        item.children()
+            .filter_map(|child| match child.value() {
+                scraper::Node::Text(text) => Some(text.to_string()),
+                scraper::Node::Element(element) if element.name() != "ul" => {
+                    ElementRef::wrap(child).map(|e| e.text().collect::<String>())
+                }
+                _ => None,
+            })
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let mut files = Vec::new();
+    for item in document.select(&item_selector) {
+        let text = own_text(item);
+        let Some(captures) = FILE_PATTERN.captures(&text) else {
+            continue;
+        };
+        let mut parts: Vec<String> = item
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .filter(|ancestor| ancestor.value().name() == "li")
+            .map(own_text)
+            .filter(|folder| !folder.is_empty())
+            .collect();
+        parts.reverse();
+        parts.push(captures[1].to_string());
+        files.push(TorrentFileEntry {
+            path: parts.join("/"),
+            size_bytes: parse_size(&captures[2]),
+        });
+    }
+
+    files
+}
+
+/// 1337x `category-search` slug; books are only reachable through the generic "Other" section
+fn category_slug(category: Category) -> Option<&'static str> {
+    match category {
+        Category::Movies => Some("Movies"),
+        Category::TV => Some("TV"),
+        Category::Music => Some("Music"),
+        Category::Games => Some("Games"),
+        Category::Software => Some("Apps"),
+        Category::Anime => Some("Anime"),
+        Category::Books => None,
+        Category::Other => Some("Other"),
+    }
+}
+
+/// Parse listing dates such as "Jan. 5th '24"; same-day uploads only show a time and are skipped
+fn parse_listing_date(text: &str) -> Option<DateTime<Utc>> {
+    let captures = LISTING_DATE_PATTERN.captures(text.trim())?;
+    let date = NaiveDate::parse_from_str(
+        &format!("{} {} 20{}", &captures[1], &captures[2], &captures[3]),
+        "%b %d %Y",
+    )
+    .ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+#[async_trait]
+impl TorrentSource for X1337Client {
+    fn name(&self) -> &str {
+        "1337x"
+    }
+
+    fn capabilities(&self) -> SourceCapabilities {
+        SourceCapabilities {
+            categories: true,
+            paging: true,
+            details: true,
+        }
+    }
+
+    async fn search(&self, request: &SearchRequest) -> SourceResult<Vec<TorrentSearchResult>> {
+        self.search_page(&request.query, request.category, request.page).await
+    }
+
+    async fn details(&self, result: &TorrentSearchResult) -> SourceResult<TorrentDetails> {
+        let url = result.details_url.as_deref().ok_or("result has no 1337x torrent page")?;
+        Ok(TorrentDetails {
+            files: self.files(url).await?,
+        })
+    }
+
+    fn supports_category(&self, category: Category) -> bool {
+        category_slug(category).is_some()
+    }
 }
 
 impl Default for X1337Client {
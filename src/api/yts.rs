@@ -1,12 +1,34 @@
+use crate::api::source::{SearchRequest, SourceCapabilities, SourceResult, TorrentSource};
+use crate::magnet::{normalize_info_hash, tracker_params};
 use crate::torrent_search::TorrentSearchResult;
+use crate::types::Category;
+use crate::utils::parse_size;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
 use reqwest::Client;
 use serde::Deserialize;
 use std::time::Duration;
 
+/// API base URLs tried in order when no list is configured
+pub const DEFAULT_MIRRORS: &[&str] = &["https://yts.mx/api/v2"];
+
+/// Trackers added to magnet links built from YTS hashes
+pub const DEFAULT_TRACKERS: &[&str] = &[
+    "udp://open.demonii.com:1337/announce",
+    "udp://tracker.openbittorrent.com:80",
+    "udp://tracker.coppersurfer.tk:6969",
+    "udp://glotorrents.pw:6969/announce",
+    "udp://tracker.opentrackr.org:1337/announce",
+    "udp://torrent.gresille.org:80/announce",
+    "udp://p4p.arenabg.com:1337",
+    "udp://tracker.leechers-paradise.org:6969",
+];
+
 #[derive(Debug, Clone)]
 pub struct YtsClient {
     client: Client,
-    base_url: String,
+    mirrors: Vec<String>,
+    trackers: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -17,6 +39,8 @@ struct YtsResponse {
 
 #[derive(Debug, Deserialize)]
 struct YtsData {
+    // Omitted by the API when nothing matched
+    #[serde(default)]
     movies: Vec<YtsMovie>,
 }
 
@@ -44,13 +68,19 @@ struct YtsTorrent {
     seeds: u32,
     peers: u32,
     size: String,
-    #[allow(dead_code)]
+    #[serde(default)]
+    size_bytes: Option<u64>,
     #[serde(rename = "date_uploaded")]
     date_uploaded: String,
 }
 
 impl YtsClient {
     pub fn new() -> Self {
+        Self::with_mirrors(DEFAULT_MIRRORS.iter().map(|m| m.to_string()).collect())
+    }
+
+    /// Client trying the given API base URLs (e.g. `https://yts.mx/api/v2`) in order
+    pub fn with_mirrors(mirrors: Vec<String>) -> Self {
         let client = Client::builder()
             .user_agent("TUI-Torrent/1.0")
             .timeout(Duration::from_secs(30))
@@ -59,17 +89,50 @@ impl YtsClient {
 
         Self {
             client,
-            base_url: "https://yts.mx/api/v2".to_string(),
+            mirrors: mirrors
+                .into_iter()
+                .map(|m| m.trim_end_matches('/').to_string())
+                .collect(),
+            trackers: DEFAULT_TRACKERS.iter().map(|t| t.to_string()).collect(),
         }
     }
 
+    /// Trackers to put into the magnet links this client builds
+    pub fn with_trackers(mut self, trackers: Vec<String>) -> Self {
+        self.trackers = trackers;
+        self
+    }
+
+    pub fn mirrors(&self) -> &[String] {
+        &self.mirrors
+    }
+
     pub async fn search(&self, query: &str, limit: Option<u32>) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
+        self.search_page(query, limit, 1).await
+    }
+
+    pub async fn search_page(&self, query: &str, limit: Option<u32>, page: u32) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
         let limit = limit.unwrap_or(20);
+
+        // Fall back to the next mirror when one is blocked or down
+        let mut last_error: Box<dyn std::error::Error + Send + Sync> = "no YTS mirrors configured".into();
+        for mirror in &self.mirrors {
+            match self.fetch_movies(mirror, query, limit, page).await {
+                Ok(yts_response) => return Ok(self.build_results(yts_response)),
+                Err(e) => last_error = e,
+            }
+        }
+
+        Err(last_error)
+    }
+
+    async fn fetch_movies(&self, mirror: &str, query: &str, limit: u32, page: u32) -> Result<YtsResponse, Box<dyn std::error::Error + Send + Sync>> {
         let search_url = format!(
-            "{}/list_movies.json?query_term={}&limit={}",
-            self.base_url, 
+            "{}/list_movies.json?query_term={}&limit={}&page={}",
+            mirror, 
             urlencoding::encode(query),
-            limit
+            limit,
+            page
         );
 
         let response = self.client
@@ -87,31 +150,67 @@ impl YtsClient {
             return Err("YTS API returned error status".into());
         }
 
+        Ok(yts_response)
+    }
+
+    fn build_results(&self, yts_response: YtsResponse) -> Vec<TorrentSearchResult> {
         let mut results = Vec::new();
 
         for movie in yts_response.data.movies {
             for torrent in movie.torrents {
                 let magnet_link = format!(
-                    "magnet:?xt=urn:btih:{}&dn={}&tr=udp://open.demonii.com:1337/announce&tr=udp://tracker.openbittorrent.com:80&tr=udp://tracker.coppersurfer.tk:6969&tr=udp://glotorrents.pw:6969/announce&tr=udp://tracker.opentrackr.org:1337/announce&tr=udp://torrent.gresille.org:80/announce&tr=udp://p4p.arenabg.com:1337&tr=udp://tracker.leechers-paradise.org:6969",
+                    "magnet:?xt=urn:btih:{}&dn={}{}",
                     torrent.hash,
-                    urlencoding::encode(&format!("{} ({}) [{}] [{}]", movie.title, movie.year, torrent.quality, torrent.codec))
+                    urlencoding::encode(&format!("{} ({}) [{}] [{}]", movie.title, movie.year, torrent.quality, torrent.codec)),
+                    tracker_params(&self.trackers)
                 );
 
                 results.push(TorrentSearchResult {
                     name: format!("{} ({}) [{}] [{}]", movie.title, movie.year, torrent.quality, torrent.codec),
+                    size_bytes: torrent.size_bytes.or_else(|| parse_size(&torrent.size)),
                     size: torrent.size,
                     seeders: torrent.seeds,
                     leechers: torrent.peers,
                     magnet_link,
                     source: "YTS".to_string(),
+                    info_hash: normalize_info_hash(&torrent.hash),
+                    uploaded_at: NaiveDateTime::parse_from_str(&torrent.date_uploaded, "%Y-%m-%d %H:%M:%S")
+                        .ok()
+                        .map(|date| date.and_utc()),
+                    category: Some(Category::Movies),
+                    ..Default::default()
                 });
             }
         }
 
         // Sort by seeders (descending)
-        results.sort_by(|a, b| b.seeders.cmp(&a.seeders));
+        results.sort_by_key(|r| std::cmp::Reverse(r.seeders));
+
+        results
+    }
+}
+
+#[async_trait]
+impl TorrentSource for YtsClient {
+    fn name(&self) -> &str {
+        "YTS"
+    }
+
+    fn capabilities(&self) -> SourceCapabilities {
+        SourceCapabilities {
+            categories: true,
+            paging: true,
+            details: false,
+        }
+    }
+
+    async fn search(&self, request: &SearchRequest) -> SourceResult<Vec<TorrentSearchResult>> {
+        self.search_page(&request.query, Some(20), request.page).await
+    }
 
-        Ok(results)
+    /// YTS only carries movies
+    fn supports_category(&self, category: Category) -> bool {
+        category == Category::Movies
     }
 }
 
@@ -1,5 +1,10 @@
-use crate::aria2_client::TorrentStatus;
-use crate::torrent_search::TorrentSearchResult;
+use crate::aria2_client::{DownloadAction, DownloadDetails, QueueMove, TorrentStatus};
+use crate::bandwidth::{to_aria2_speed, SpeedLimits};
+use crate::file_picker::{FilePicker, PickerTarget};
+use crate::log_buffer::{LogBuffer, LogEntry, LogLevel, LogSource};
+use crate::query::{parse_query, SearchFilter};
+use crate::torrent_search::{merge_duplicates, SourceState, SourceUpdate, TorrentSearchResult, MAX_RESULTS};
+use crate::types::{Category, DownloadState};
 use crossterm::event::{self, Event, KeyCode, KeyEvent};
 use std::io;
 
@@ -9,22 +14,146 @@ pub enum AppMode {
     Search,
     Results,
     Searching,
+    /// Waiting for y/n on `confirm_action`
+    Confirm,
+    /// Peers, files and trackers of one download
+    Detail,
+    /// Choosing which files of `file_picker` to download
+    FilePicker,
+    /// Typing speed limits for aria2 as a whole or for `limit_gid`
+    LimitInput,
+    /// Reordering waiting downloads
+    Queue,
+    /// aria2's output and our own messages
+    Log,
+}
+
+/// Section shown in the lower half of the detail screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetailTab {
+    Files,
+    Peers,
+    Trackers,
+}
+
+impl DetailTab {
+    pub const ALL: [DetailTab; 3] = [DetailTab::Files, DetailTab::Peers, DetailTab::Trackers];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DetailTab::Files => "Files",
+            DetailTab::Peers => "Peers",
+            DetailTab::Trackers => "Trackers",
+        }
+    }
+
+    fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&t| t == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+/// Search result the main loop should hand to aria2
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddRequest {
+    pub name: String,
+    pub uri: String,
+    /// Per-download aria2 options such as `select-file`
+    pub options: Vec<(String, String)>,
+    /// Files to tick in the picker once the torrent's metadata is in, when the preview's indexes were not exact
+    pub wanted_files: Option<Vec<String>>,
 }
 
 #[derive(Debug)]
 pub struct App {
     pub mode: AppMode,
     pub search_query: String,
+    /// Free text part of `search_query`, sent to the providers
+    pub search_terms: String,
+    /// Inline filters from `search_query`, applied to merged results
+    pub search_filter: SearchFilter,
+    /// Merged results of the current search before `search_filter` is applied
+    pub unfiltered_results: Vec<TorrentSearchResult>,
     pub search_results: Vec<TorrentSearchResult>,
-    pub active_downloads: Vec<TorrentStatus>,
+    /// Every download aria2 knows about, grouped by state
+    pub downloads: Vec<TorrentStatus>,
+    /// State picked with 'f' in normal mode; `None` lists everything
+    pub download_filter: Option<DownloadState>,
     pub selected_index: usize,
     pub should_quit: bool,
     pub search_in_progress: bool,
     pub status_message: String,
-    pub selected_category: Option<String>,
+    /// Category picked with Tab in search mode; `None` searches everything
+    pub selected_category: Option<Category>,
     pub download_requested: bool,
+    /// The requested download should stop after fetching metadata so its files can be picked
+    pub choose_files: bool,
+    /// Set by `start_search`, cleared once the main loop has launched the search
+    pub search_requested: bool,
+    /// Live state of every source taking part in the current search
+    pub source_states: Vec<(String, SourceState)>,
     pub loading_frame: usize,
     pub search_progress: String,
+    /// Number of merged results kept for display
+    pub max_results: usize,
+    /// Whether aria2's WebSocket notifications are connected
+    pub live_updates: bool,
+    /// Action for the main loop to run against aria2
+    pub pending_action: Option<DownloadAction>,
+    /// Destructive action waiting for confirmation
+    pub confirm_action: Option<DownloadAction>,
+    /// Download shown on the detail screen; the main loop keeps `detail` fresh for it
+    pub detail_gid: Option<String>,
+    pub detail: Option<DownloadDetails>,
+    pub detail_tab: DetailTab,
+    pub detail_scroll: usize,
+    /// Metadata downloads whose torrent opens in the file picker once resolved
+    pub awaiting_metadata: Vec<String>,
+    /// Download whose files the main loop should load into the picker, and whether to resume it afterwards
+    pub picker_request: Option<(String, bool)>,
+    pub file_picker: Option<FilePicker>,
+    /// Set with 'i' on a search result; the main loop fetches its file list
+    pub preview_requested: bool,
+    /// Download picked from a preview, for the main loop to add
+    pub pending_add: Option<AddRequest>,
+    /// Files chosen in a preview, keyed by the gid whose picker should start with them ticked
+    pub wanted_files: Vec<(String, Vec<String>)>,
+    /// Overall limits in force, as last read from or sent to aria2
+    pub global_limits: SpeedLimits,
+    /// Overall limits typed by the user, for the main loop to send
+    pub pending_global_limits: Option<SpeedLimits>,
+    pub limit_input: String,
+    /// Download the limit being typed applies to; `None` means all of aria2
+    pub limit_gid: Option<String>,
+    /// Waiting and paused downloads in aria2's queue order
+    pub queue: Vec<String>,
+    pub queue_index: usize,
+    /// aria2's `max-concurrent-downloads`, once known
+    pub max_concurrent: Option<u32>,
+    /// Concurrency changed in the queue view, for the main loop to send
+    pub pending_concurrency: Option<u32>,
+    /// Downloads started with 'f' in the queue view, marked until they stop
+    pub forced: Vec<String>,
+    /// Why aria2 cannot be reached; `None` while it answers
+    pub aria2_offline: Option<String>,
+    /// Shared with the aria2 manager, which copies aria2c's output into it
+    pub log: LogBuffer,
+    /// Status message last copied into `log`
+    logged_status: String,
+    /// Only show lines from this source; `None` shows both
+    pub log_source: Option<LogSource>,
+    /// Text the log lines must contain
+    pub log_filter: String,
+    /// Whether keys go into `log_filter`
+    pub log_filter_typing: bool,
+    /// Lines scrolled back from the newest; 0 follows new output
+    pub log_scroll: usize,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl App {
@@ -32,25 +161,328 @@ impl App {
         App {
             mode: AppMode::Normal,
             search_query: String::new(),
+            search_terms: String::new(),
+            search_filter: SearchFilter::default(),
+            unfiltered_results: Vec::new(),
             search_results: Vec::new(),
-            active_downloads: Vec::new(),
+            downloads: Vec::new(),
+            download_filter: None,
             selected_index: 0,
             should_quit: false,
             search_in_progress: false,
             status_message: "Starting up...".to_string(),
             selected_category: None,
             download_requested: false,
+            choose_files: false,
+            search_requested: false,
+            source_states: Vec::new(),
             loading_frame: 0,
             search_progress: String::new(),
+            max_results: MAX_RESULTS,
+            live_updates: false,
+            pending_action: None,
+            confirm_action: None,
+            detail_gid: None,
+            detail: None,
+            detail_tab: DetailTab::Files,
+            detail_scroll: 0,
+            awaiting_metadata: Vec::new(),
+            picker_request: None,
+            file_picker: None,
+            preview_requested: false,
+            pending_add: None,
+            wanted_files: Vec::new(),
+            global_limits: SpeedLimits::default(),
+            pending_global_limits: None,
+            limit_input: String::new(),
+            limit_gid: None,
+            queue: Vec::new(),
+            queue_index: 0,
+            max_concurrent: None,
+            pending_concurrency: None,
+            forced: Vec::new(),
+            aria2_offline: None,
+            log: LogBuffer::default(),
+            logged_status: String::new(),
+            log_source: None,
+            log_filter: String::new(),
+            log_filter_typing: false,
+            log_scroll: 0,
+        }
+    }
+
+    /// Copy the status message into the log when it changed since the last call
+    pub fn record_status(&mut self) {
+        if self.status_message != self.logged_status {
+            self.logged_status = self.status_message.clone();
+            self.log
+                .push(LogSource::App, LogLevel::of_status(&self.status_message), self.status_message.clone());
+        }
+    }
+
+    /// Log lines passing the source and text filters, oldest first
+    pub fn visible_log(&self) -> Vec<LogEntry> {
+        self.log
+            .entries()
+            .into_iter()
+            .filter(|entry| self.log_source.is_none_or(|source| entry.source == source))
+            .filter(|entry| entry.matches(&self.log_filter))
+            .collect()
+    }
+
+    /// aria2 stopped answering; the last known downloads stay listed until it is back
+    pub fn aria2_disconnected(&mut self, reason: String) {
+        self.live_updates = false;
+        self.status_message = format!("aria2 disconnected: {} - reconnecting...", reason);
+        self.aria2_offline = Some(reason);
+    }
+
+    pub fn aria2_reconnected(&mut self) {
+        self.aria2_offline = None;
+        self.status_message = "Reconnected to aria2".to_string();
+    }
+
+    /// Replace the download list with a full refresh from aria2
+    pub fn set_downloads(&mut self, downloads: Vec<TorrentStatus>) {
+        // aria2 lists waiting downloads in queue order, which grouping by state would lose
+        self.queue = downloads
+            .iter()
+            .filter(|d| is_queued(d.state()))
+            .map(|d| d.gid.clone())
+            .collect();
+        self.downloads = downloads;
+        self.track_queue();
+        self.sort_downloads();
+        self.check_metadata();
+    }
+
+    /// Apply a fresh status for one download after aria2 reported a change
+    pub fn update_download(&mut self, status: TorrentStatus) {
+        match self.downloads.iter_mut().find(|d| d.gid == status.gid) {
+            Some(existing) => *existing = status,
+            None => self.downloads.push(status),
+        }
+        self.track_queue();
+        self.sort_downloads();
+        self.check_metadata();
+    }
+
+    /// Drop a download aria2 no longer knows about
+    pub fn remove_download(&mut self, gid: &str) {
+        self.downloads.retain(|d| d.gid != gid);
+        self.track_queue();
+        self.clamp_download_selection();
+    }
+
+    /// Downloads shown with the current filter applied
+    pub fn visible_downloads(&self) -> Vec<&TorrentStatus> {
+        self.downloads
+            .iter()
+            .filter(|d| self.download_filter.is_none_or(|state| d.state() == state))
+            .collect()
+    }
+
+    pub fn selected_download(&self) -> Option<&TorrentStatus> {
+        self.visible_downloads().get(self.selected_index).copied()
+    }
+
+    /// Waiting and paused downloads in the order aria2 will start them
+    pub fn queued_downloads(&self) -> Vec<&TorrentStatus> {
+        self.queue
+            .iter()
+            .filter_map(|gid| self.downloads.iter().find(|d| d.gid == *gid))
+            .collect()
+    }
+
+    /// Keep `queue` in step with single updates; newly queued downloads join at the end like in aria2
+    fn track_queue(&mut self) {
+        let downloads = &self.downloads;
+        self.queue
+            .retain(|gid| downloads.iter().any(|d| d.gid == *gid && is_queued(d.state())));
+        for download in downloads {
+            if is_queued(download.state()) && !self.queue.contains(&download.gid) {
+                self.queue.push(download.gid.clone());
+            }
+        }
+        self.forced
+            .retain(|gid| downloads.iter().any(|d| d.gid == *gid && !d.state().is_stopped()));
+        self.queue_index = self.queue_index.min(self.queue.len().saturating_sub(1));
+    }
+
+    /// Number of downloads in each state, for the pane title
+    pub fn download_counts(&self) -> Vec<(DownloadState, usize)> {
+        DownloadState::ALL
+            .iter()
+            .map(|&state| (state, self.downloads.iter().filter(|d| d.state() == state).count()))
+            .filter(|&(_, count)| count > 0)
+            .collect()
+    }
+
+    /// Queue an action for the main loop, asking first if it is destructive
+    pub fn request_action(&mut self, action: DownloadAction) {
+        if action.is_destructive() {
+            let name = self.downloads.iter().find(|d| d.gid == action.gid()).map(|d| d.name());
+            self.status_message = match name {
+                Some(name) => format!("{} ({})? Press y to confirm, n to cancel", action.describe(), name),
+                None => format!("{}? Press y to confirm, n to cancel", action.describe()),
+            };
+            self.confirm_action = Some(action);
+            self.mode = AppMode::Confirm;
+        } else {
+            self.pending_action = Some(action);
+        }
+    }
+
+    /// Pause or resume, remove or retry the selected download
+    fn download_action_for(&self, key: KeyCode) -> Option<DownloadAction> {
+        let download = self.selected_download()?;
+        let gid = download.gid.clone();
+        let state = download.state();
+        match key {
+            KeyCode::Char('p') => match state {
+                DownloadState::Active | DownloadState::Waiting => Some(DownloadAction::Pause { gid }),
+                DownloadState::Paused => Some(DownloadAction::Resume { gid }),
+                _ => None,
+            },
+            // Stopped downloads only have a result left to clear, nothing to delete
+            KeyCode::Char('x') if state.is_stopped() => Some(DownloadAction::RemoveResult { gid }),
+            KeyCode::Char('x') => Some(DownloadAction::Remove { gid, delete_files: false }),
+            KeyCode::Char('X') => Some(DownloadAction::Remove { gid, delete_files: true }),
+            KeyCode::Char('r') if state == DownloadState::Error => {
+                let magnet = download.magnet_link()?;
+                Some(DownloadAction::Retry { gid, magnet })
+            }
+            _ => None,
+        }
+    }
+
+    /// Hand torrents whose metadata arrived to the file picker; aria2 holds them paused meanwhile
+    fn check_metadata(&mut self) {
+        let mut resolved = None;
+        self.awaiting_metadata.retain(|gid| {
+            let Some(download) = self.downloads.iter().find(|d| d.gid == *gid) else {
+                return true;
+            };
+            if let Some(next) = download.followed_by.first() {
+                // A selection made in a preview carries over to the real download
+                for (wanted_gid, _) in self.wanted_files.iter_mut().filter(|(g, _)| g == gid) {
+                    *wanted_gid = next.clone();
+                }
+                resolved.get_or_insert_with(|| next.clone());
+                false
+            } else {
+                // Failed or removed before the metadata came in
+                !download.state().is_stopped()
+            }
+        });
+        if let Some(gid) = resolved {
+            self.picker_request = Some((gid, true));
+        }
+    }
+
+    /// Ask the main loop to load the selected download's files into the picker
+    pub fn request_file_picker(&mut self) {
+        let Some(download) = self.selected_download() else {
+            return;
+        };
+        if download.has_metadata() {
+            self.picker_request = Some((download.gid.clone(), false));
+        } else {
+            self.status_message = "No file list for this download yet".to_string();
+        }
+    }
+
+    /// Show the picker, leaving whatever screen was open underneath
+    pub fn open_file_picker(&mut self, mut picker: FilePicker) {
+        if let PickerTarget::Download { gid, .. } = &picker.target
+            && let Some(i) = self.wanted_files.iter().position(|(g, _)| g == gid)
+        {
+            picker.preselect(&self.wanted_files.remove(i).1);
+        }
+        self.status_message = format!("Choose files to download ({} files)", picker.files.len());
+        self.file_picker = Some(picker);
+        self.mode = AppMode::FilePicker;
+    }
+
+    pub fn close_file_picker(&mut self) {
+        let from_preview = matches!(self.file_picker.take().map(|p| p.target), Some(PickerTarget::Preview { .. }));
+        self.mode = if self.detail_gid.is_some() {
+            AppMode::Detail
+        } else if from_preview && !self.search_results.is_empty() {
+            AppMode::Results
+        } else {
+            AppMode::Normal
+        };
+    }
+
+    /// What applying the picker's selection should do
+    fn apply_file_picker(&mut self, picker: &FilePicker, files: String) {
+        match &picker.target {
+            PickerTarget::Download { gid, resume_after } => {
+                self.pending_action = Some(DownloadAction::SelectFiles {
+                    gid: gid.clone(),
+                    files,
+                    resume: *resume_after,
+                });
+            }
+            PickerTarget::Preview { name, uri, exact, .. } => {
+                let (options, wanted_files) = if picker.all_selected() {
+                    (Vec::new(), None)
+                } else if *exact {
+                    (vec![("select-file".to_string(), files)], None)
+                } else {
+                    // The provider's list may not follow the torrent's order; pick again on the real list
+                    let wanted = picker.files.iter().filter(|f| f.selected).map(|f| f.path.clone()).collect();
+                    (vec![("pause-metadata".to_string(), "true".to_string())], Some(wanted))
+                };
+                self.pending_add = Some(AddRequest {
+                    name: name.clone(),
+                    uri: uri.clone(),
+                    options,
+                    wanted_files,
+                });
+            }
+        }
+    }
+
+    /// Whether a picker may pop up now without interrupting typing, a search or a prompt
+    pub fn can_open_file_picker(&self) -> bool {
+        matches!(self.mode, AppMode::Normal | AppMode::Results | AppMode::Detail)
+    }
+
+    // Group by state; the sort is stable so aria2's queue order is kept within a group
+    fn sort_downloads(&mut self) {
+        self.downloads.sort_by_key(TorrentStatus::state);
+        self.clamp_download_selection();
+    }
+
+    fn clamp_download_selection(&mut self) {
+        let visible = self.visible_downloads().len();
+        if self.mode == AppMode::Normal && self.selected_index >= visible {
+            self.selected_index = visible.saturating_sub(1);
         }
     }
 
     pub fn start_search(&mut self) {
+        // A malformed filter keeps the user in the search box with the reason in the status bar
+        let parsed = match parse_query(&self.search_query) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                self.status_message = format!("Query error: {}", e);
+                return;
+            }
+        };
+        self.search_terms = parsed.text;
+        self.search_filter = parsed.filter;
+
         self.mode = AppMode::Searching;
         self.search_in_progress = true;
+        self.search_requested = true;
         self.status_message = "Initializing search across multiple sources...".to_string();
         self.search_progress = "Starting search...".to_string();
         self.search_results.clear();
+        self.unfiltered_results.clear();
+        self.source_states.clear();
         self.selected_index = 0;
         self.loading_frame = 0;
     }
@@ -63,6 +495,119 @@ impl App {
         self.selected_index = 0;
     }
 
+    /// Mark the given sources as pending for the search that was just launched, in the order
+    /// of `sources_for` so updates find their row by index
+    pub fn track_sources<I: IntoIterator<Item = String>>(&mut self, sources: I) {
+        self.source_states = sources
+            .into_iter()
+            .map(|name| (name, SourceState::Pending))
+            .collect();
+        if self.source_states.is_empty() {
+            self.finish_search(Vec::new());
+        }
+    }
+
+    /// Merge one source's outcome into the results; finishes the search once every source answered
+    pub fn apply_source_update(&mut self, update: SourceUpdate) {
+        if !self.search_in_progress {
+            return;
+        }
+
+        let state = update.state();
+        match self.source_states.get_mut(update.index) {
+            Some(entry) => entry.1 = state,
+            None => self.source_states.push((update.source.clone(), state)),
+        }
+
+        if let Ok(results) = update.outcome {
+            let selected_name = self.search_results.get(self.selected_index).map(|r| r.name.clone());
+            self.unfiltered_results.extend(results);
+            self.unfiltered_results = merge_duplicates(std::mem::take(&mut self.unfiltered_results));
+            // Filter after merging so counts combined across sources are what gets compared
+            self.search_results = self
+                .unfiltered_results
+                .iter()
+                .filter(|r| self.search_filter.matches(r))
+                .cloned()
+                .collect();
+            self.search_results.sort_by_key(|r| std::cmp::Reverse(r.seeders));
+            self.search_results.truncate(self.max_results);
+            // Keep the cursor on the same entry while results move around
+            self.selected_index = selected_name
+                .and_then(|name| self.search_results.iter().position(|r| r.name == name))
+                .unwrap_or(0);
+        }
+
+        if self.pending_sources() == 0 {
+            let results = std::mem::take(&mut self.search_results);
+            let selected = self.selected_index;
+            self.finish_search(results);
+            self.selected_index = selected;
+            let hidden = self
+                .unfiltered_results
+                .iter()
+                .filter(|r| !self.search_filter.matches(r))
+                .count();
+            if !self.search_filter.is_empty() && hidden > 0 {
+                self.status_message = format!("{} ({} hidden by filters)", self.status_message, hidden);
+            }
+            if !self.failed_sources().is_empty() {
+                self.status_message = format!(
+                    "{} (unavailable: {})",
+                    self.status_message,
+                    self.failed_sources().join(", ")
+                );
+            }
+        } else {
+            self.update_search_progress();
+        }
+    }
+
+    pub fn pending_sources(&self) -> usize {
+        self.source_states
+            .iter()
+            .filter(|(_, state)| *state == SourceState::Pending)
+            .count()
+    }
+
+    fn failed_sources(&self) -> Vec<String> {
+        self.source_states
+            .iter()
+            .filter(|(_, state)| matches!(state, SourceState::Failed(_) | SourceState::TimedOut))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    fn update_search_progress(&mut self) {
+        if self.source_states.is_empty() {
+            return;
+        }
+        let waiting: Vec<&str> = self
+            .source_states
+            .iter()
+            .filter(|(_, state)| *state == SourceState::Pending)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let done = self.source_states.len() - waiting.len();
+        self.search_progress = format!(
+            "Waiting for {} ({}/{} sources done, {} results so far)",
+            waiting.join(", "),
+            done,
+            self.source_states.len(),
+            self.search_results.len()
+        );
+    }
+
+    /// Stop the running search; the main loop aborts its outstanding requests
+    pub fn cancel_search(&mut self) {
+        self.search_in_progress = false;
+        self.search_requested = false;
+        self.mode = AppMode::Normal;
+        self.search_results.clear();
+        self.selected_index = 0;
+        self.status_message = "Search cancelled".to_string();
+    }
+
     pub fn search_error(&mut self, error: String) {
         self.search_in_progress = false;
         self.mode = AppMode::Normal;
@@ -72,21 +617,7 @@ impl App {
     pub fn update_loading_animation(&mut self) {
         if self.search_in_progress {
             self.loading_frame = (self.loading_frame + 1) % 8;
-            
-            // Update search progress message with different states
-            let progress_messages = [
-                "Connecting to YTS movie database...",
-                "Searching YTS for movies...",
-                "Connecting to PirateBay API...",
-                "Searching PirateBay torrents...",
-                "Checking 1337x mirrors...",
-                "Searching 1337x database...",
-                "Sorting results by seeders...",
-                "Finalizing search results...",
-            ];
-            
-            let message_index = (self.loading_frame / 4) % progress_messages.len();
-            self.search_progress = progress_messages[message_index].to_string();
+            self.update_search_progress();
         }
     }
 
@@ -98,36 +629,300 @@ impl App {
     }
 
     pub fn handle_input(&mut self) -> io::Result<()> {
-        if event::poll(std::time::Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                match self.mode {
-                    AppMode::Normal => self.handle_normal_mode(key),
-                    AppMode::Search => self.handle_search_mode(key),
-                    AppMode::Results => self.handle_results_mode(key),
-                    AppMode::Searching => self.handle_searching_mode(key),
+        if event::poll(std::time::Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+        {
+            self.handle_key(key);
+        }
+        Ok(())
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        match self.mode {
+            AppMode::Normal => self.handle_normal_mode(key),
+            AppMode::Search => self.handle_search_mode(key),
+            AppMode::Results => self.handle_results_mode(key),
+            AppMode::Searching => self.handle_searching_mode(key),
+            AppMode::Confirm => self.handle_confirm_mode(key),
+            AppMode::Detail => self.handle_detail_mode(key),
+            AppMode::FilePicker => self.handle_file_picker_mode(key),
+            AppMode::LimitInput => self.handle_limit_mode(key),
+            AppMode::Queue => self.handle_queue_mode(key),
+            AppMode::Log => self.handle_log_mode(key),
+        }
+    }
+
+    /// Open the detail screen for the selected download
+    pub fn open_detail(&mut self) {
+        if let Some(download) = self.selected_download() {
+            self.detail_gid = Some(download.gid.clone());
+            self.detail = None;
+            self.detail_tab = DetailTab::Files;
+            self.detail_scroll = 0;
+            self.mode = AppMode::Detail;
+        }
+    }
+
+    pub fn close_detail(&mut self) {
+        self.detail_gid = None;
+        self.detail = None;
+        self.mode = AppMode::Normal;
+    }
+
+    /// Number of rows in the current detail tab, for scrolling
+    fn detail_rows(&self) -> usize {
+        let Some(detail) = &self.detail else {
+            return 0;
+        };
+        match self.detail_tab {
+            DetailTab::Files => detail.files.len(),
+            DetailTab::Peers => detail.peers.len(),
+            DetailTab::Trackers => {
+                let trackers: usize = detail
+                    .status
+                    .bittorrent
+                    .as_ref()
+                    .map(|bt| bt.announce_list.iter().map(Vec::len).sum())
+                    .unwrap_or(0);
+                trackers + detail.uris.len()
+            }
+        }
+    }
+
+    fn handle_detail_mode(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.close_detail(),
+            KeyCode::Tab => {
+                self.detail_tab = self.detail_tab.next();
+                self.detail_scroll = 0;
+            }
+            KeyCode::Char('o') => match self.detail.as_ref().map(|d| &d.status) {
+                Some(status) if status.has_metadata() => self.picker_request = Some((status.gid.clone(), false)),
+                Some(_) => self.status_message = "No file list for this download yet".to_string(),
+                None => {}
+            },
+            KeyCode::Down | KeyCode::Char('j') if self.detail_scroll + 1 < self.detail_rows() => {
+                self.detail_scroll += 1;
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.detail_scroll = self.detail_scroll.saturating_sub(1);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_file_picker_mode(&mut self, key: KeyEvent) {
+        let Some(picker) = self.file_picker.as_mut() else {
+            self.close_file_picker();
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.status_message = match &picker.target {
+                    PickerTarget::Download { gid, resume_after: true } => {
+                        format!("Left {} paused; press p to start it with all files", gid)
+                    }
+                    PickerTarget::Download { .. } => "File selection unchanged".to_string(),
+                    PickerTarget::Preview { .. } => "Nothing downloaded".to_string(),
+                };
+                self.close_file_picker();
+            }
+            KeyCode::Enter => match picker.select_file_option() {
+                Some(files) => {
+                    let picker = picker.clone();
+                    self.apply_file_picker(&picker, files);
+                    self.close_file_picker();
                 }
+                None => self.status_message = "Select at least one file".to_string(),
+            },
+            KeyCode::Char(' ') => picker.toggle(),
+            KeyCode::Char('a') => picker.toggle_all(),
+            KeyCode::Down | KeyCode::Char('j') => picker.move_cursor(true),
+            KeyCode::Up | KeyCode::Char('k') => picker.move_cursor(false),
+            _ => {}
+        }
+    }
+
+    /// Prompt for overall limits, or for the selected download's with `per_download`
+    pub fn start_limit_input(&mut self, per_download: bool) {
+        self.limit_gid = None;
+        if per_download {
+            match self.selected_download() {
+                Some(download) => self.limit_gid = Some(download.gid.clone()),
+                None => return,
             }
         }
-        Ok(())
+        // Overall limits start from what is in force so a single value is easy to change
+        self.limit_input = if per_download {
+            String::new()
+        } else {
+            let show = |limit: Option<u64>| limit.map(to_aria2_speed).unwrap_or_else(|| "-".to_string());
+            format!("{} {}", show(self.global_limits.download), show(self.global_limits.upload))
+        };
+        self.mode = AppMode::LimitInput;
+    }
+
+    fn handle_limit_mode(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.limit_gid = None;
+                self.mode = AppMode::Normal;
+            }
+            KeyCode::Enter => match SpeedLimits::parse(&self.limit_input) {
+                Ok(limits) => {
+                    match self.limit_gid.take() {
+                        Some(gid) => self.pending_action = Some(DownloadAction::SetLimits { gid, limits }),
+                        None => self.pending_global_limits = Some(limits),
+                    }
+                    self.mode = AppMode::Normal;
+                }
+                Err(e) => self.status_message = format!("Invalid limit: {}", e),
+            },
+            KeyCode::Backspace => {
+                self.limit_input.pop();
+            }
+            KeyCode::Char(c) => self.limit_input.push(c),
+            _ => {}
+        }
+    }
+
+    fn handle_queue_mode(&mut self, key: KeyEvent) {
+        let len = self.queue.len();
+        let selected = self.queue.get(self.queue_index).cloned();
+        match (key.code, selected) {
+            (KeyCode::Esc | KeyCode::Char('q'), _) => self.mode = AppMode::Normal,
+            (KeyCode::Down | KeyCode::Char('j'), Some(_)) => self.queue_index = (self.queue_index + 1) % len,
+            (KeyCode::Up | KeyCode::Char('k'), Some(_)) => {
+                self.queue_index = self.queue_index.checked_sub(1).unwrap_or(len - 1);
+            }
+            // The cursor follows the download to where it is moved
+            (KeyCode::Char(c @ ('K' | 'J' | 't' | 'b')), Some(gid)) => {
+                let (to, index) = match c {
+                    'K' => (QueueMove::Up, self.queue_index.saturating_sub(1)),
+                    'J' => (QueueMove::Down, (self.queue_index + 1).min(len - 1)),
+                    't' => (QueueMove::Top, 0),
+                    _ => (QueueMove::Bottom, len - 1),
+                };
+                self.queue_index = index;
+                self.pending_action = Some(DownloadAction::Move { gid, to });
+            }
+            (KeyCode::Char('f'), Some(gid)) => {
+                self.forced.push(gid.clone());
+                self.pending_action = Some(DownloadAction::ForceStart { gid });
+            }
+            (KeyCode::Char('p'), Some(gid)) => {
+                let paused = self.downloads.iter().any(|d| d.gid == gid && d.state() == DownloadState::Paused);
+                self.pending_action = Some(if paused {
+                    DownloadAction::Resume { gid }
+                } else {
+                    DownloadAction::Pause { gid }
+                });
+            }
+            (KeyCode::Char(c @ ('+' | '-')), _) => match self.max_concurrent {
+                Some(max) => {
+                    let max = if c == '+' { max + 1 } else { max.saturating_sub(1).max(1) };
+                    self.pending_concurrency = Some(max);
+                }
+                None => self.status_message = "The concurrency limit is not known yet".to_string(),
+            },
+            _ => {}
+        }
+    }
+
+    fn handle_log_mode(&mut self, key: KeyEvent) {
+        if self.log_filter_typing {
+            match key.code {
+                KeyCode::Enter => self.log_filter_typing = false,
+                KeyCode::Esc => {
+                    self.log_filter.clear();
+                    self.log_filter_typing = false;
+                }
+                KeyCode::Backspace => {
+                    self.log_filter.pop();
+                }
+                KeyCode::Char(c) => self.log_filter.push(c),
+                _ => {}
+            }
+            self.log_scroll = 0;
+            return;
+        }
+        let oldest = self.visible_log().len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.mode = AppMode::Normal,
+            KeyCode::Char('/') => self.log_filter_typing = true,
+            KeyCode::Tab => {
+                self.log_source = match self.log_source {
+                    None => Some(LogSource::App),
+                    Some(LogSource::App) => Some(LogSource::Aria2),
+                    Some(LogSource::Aria2) => None,
+                };
+                self.log_scroll = 0;
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.log_scroll = (self.log_scroll + 1).min(oldest),
+            KeyCode::Down | KeyCode::Char('j') => self.log_scroll = self.log_scroll.saturating_sub(1),
+            KeyCode::PageUp => self.log_scroll = (self.log_scroll + 10).min(oldest),
+            KeyCode::PageDown => self.log_scroll = self.log_scroll.saturating_sub(10),
+            KeyCode::End | KeyCode::Char('G') => self.log_scroll = 0,
+            _ => {}
+        }
+    }
+
+    fn handle_confirm_mode(&mut self, key: KeyEvent) {
+        match key.code {
+            // Only an explicit yes; Enter is too easily pressed out of habit
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                self.pending_action = self.confirm_action.take();
+                self.mode = AppMode::Normal;
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.confirm_action = None;
+                self.status_message = "Cancelled".to_string();
+                self.mode = AppMode::Normal;
+            }
+            _ => {}
+        }
     }
 
     fn handle_normal_mode(&mut self, key: KeyEvent) {
         match key.code {
             KeyCode::Char('q') => self.should_quit = true,
             KeyCode::Char('s') => self.mode = AppMode::Search,
-            KeyCode::Down | KeyCode::Char('j') => {
-                if !self.active_downloads.is_empty() {
-                    self.selected_index = (self.selected_index + 1) % self.active_downloads.len();
-                }
+            KeyCode::Enter => self.open_detail(),
+            KeyCode::Char('o') => self.request_file_picker(),
+            KeyCode::Char('l') => self.start_limit_input(false),
+            KeyCode::Char('L') => self.start_limit_input(true),
+            KeyCode::Char('w') => {
+                self.queue_index = 0;
+                self.mode = AppMode::Queue;
             }
-            KeyCode::Up | KeyCode::Char('k') => {
-                if !self.active_downloads.is_empty() {
-                    self.selected_index = if self.selected_index == 0 {
-                        self.active_downloads.len() - 1
-                    } else {
-                        self.selected_index - 1
-                    };
+            KeyCode::Char('v') => {
+                self.log_scroll = 0;
+                self.mode = AppMode::Log;
+            }
+            KeyCode::Char('f') => {
+                self.download_filter = DownloadState::cycle(self.download_filter, true);
+                self.selected_index = 0;
+            }
+            KeyCode::Char('F') => {
+                self.download_filter = DownloadState::cycle(self.download_filter, false);
+                self.selected_index = 0;
+            }
+            KeyCode::Char('p' | 'x' | 'X' | 'r') => match self.download_action_for(key.code) {
+                Some(action) => self.request_action(action),
+                None if self.selected_download().is_some() => {
+                    self.status_message = "Not available for this download".to_string();
                 }
+                None => {}
+            },
+            KeyCode::Down | KeyCode::Char('j') if !self.visible_downloads().is_empty() => {
+                self.selected_index = (self.selected_index + 1) % self.visible_downloads().len();
+            }
+            KeyCode::Up | KeyCode::Char('k') if !self.visible_downloads().is_empty() => {
+                self.selected_index = if self.selected_index == 0 {
+                    self.visible_downloads().len() - 1
+                } else {
+                    self.selected_index - 1
+                };
             }
             _ => {}
         }
@@ -139,14 +934,18 @@ impl App {
                 self.mode = AppMode::Normal;
                 self.search_query.clear();
             }
-            KeyCode::Enter => {
-                if !self.search_query.is_empty() {
-                    self.start_search();
-                }
+            KeyCode::Enter if !self.search_query.is_empty() => {
+                self.start_search();
             }
             KeyCode::Backspace => {
                 self.search_query.pop();
             }
+            KeyCode::Tab => {
+                self.selected_category = Category::cycle(self.selected_category, true);
+            }
+            KeyCode::BackTab => {
+                self.selected_category = Category::cycle(self.selected_category, false);
+            }
             KeyCode::Char(c) => {
                 self.search_query.push(c);
             }
@@ -161,24 +960,25 @@ impl App {
                 self.search_results.clear();
                 self.selected_index = 0;
             }
-            KeyCode::Enter => {
-                if !self.search_results.is_empty() {
-                    self.download_requested = true;
-                }
+            KeyCode::Enter if !self.search_results.is_empty() => {
+                self.download_requested = true;
             }
-            KeyCode::Down | KeyCode::Char('j') => {
-                if !self.search_results.is_empty() {
-                    self.selected_index = (self.selected_index + 1) % self.search_results.len();
-                }
+            KeyCode::Char('o') if !self.search_results.is_empty() => {
+                self.download_requested = true;
+                self.choose_files = true;
             }
-            KeyCode::Up | KeyCode::Char('k') => {
-                if !self.search_results.is_empty() {
-                    self.selected_index = if self.selected_index == 0 {
-                        self.search_results.len() - 1
-                    } else {
-                        self.selected_index - 1
-                    };
-                }
+            KeyCode::Char('i') if !self.search_results.is_empty() => {
+                self.preview_requested = true;
+            }
+            KeyCode::Down | KeyCode::Char('j') if !self.search_results.is_empty() => {
+                self.selected_index = (self.selected_index + 1) % self.search_results.len();
+            }
+            KeyCode::Up | KeyCode::Char('k') if !self.search_results.is_empty() => {
+                self.selected_index = if self.selected_index == 0 {
+                    self.search_results.len() - 1
+                } else {
+                    self.selected_index - 1
+                };
             }
             _ => {}
         }
@@ -186,14 +986,20 @@ impl App {
 
     fn handle_searching_mode(&mut self, key: KeyEvent) {
         match key.code {
-            KeyCode::Esc => {
-                self.search_in_progress = false;
-                self.mode = AppMode::Normal;
-                self.status_message = "Search cancelled".to_string();
-            }
+            KeyCode::Esc => self.cancel_search(),
+            // Results that already arrived can be browsed
+            KeyCode::Down
+            | KeyCode::Char('j')
+            | KeyCode::Up
+            | KeyCode::Char('k') => self.handle_results_mode(key),
             _ => {
                 // Ignore other keys while searching
             }
         }
     }
 }
+
+/// Whether a download sits in aria2's waiting queue
+fn is_queued(state: DownloadState) -> bool {
+    matches!(state, DownloadState::Waiting | DownloadState::Paused)
+}
@@ -1,7 +1,46 @@
+use crate::error::{Aria2Error, Result, TorrentError};
+use crate::bandwidth::{parse_speed, SpeedLimits};
+use crate::magnet::tracker_params;
+use crate::torrent_file::TorrentMetadata;
+use crate::types::DownloadState;
+use crate::utils::delete_download_files;
+use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
-use serde_json::json;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
-#[derive(Debug, Deserialize)]
+/// Downloads fetched per `tellWaiting` / `tellStopped` request
+const PAGE_SIZE: usize = 100;
+
+/// How long aria2 gets to finish removing a download, or to start a force started one
+const STATE_CHANGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Status fields requested when listing downloads; keeps polling cheap by leaving out
+/// per-file details and peer lists
+pub const STATUS_KEYS: &[&str] = &[
+    "gid",
+    "status",
+    "totalLength",
+    "completedLength",
+    "downloadSpeed",
+    "uploadSpeed",
+    "uploadLength",
+    "connections",
+    "numSeeders",
+    "infoHash",
+    "bittorrent",
+    "dir",
+    "errorCode",
+    "errorMessage",
+    "followedBy",
+];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct TorrentStatus {
     pub gid: String,
     pub status: String,
@@ -11,25 +50,813 @@ pub struct TorrentStatus {
     pub completed_length: String,
     #[serde(rename = "downloadSpeed")]
     pub download_speed: String,
+    #[serde(rename = "uploadSpeed")]
+    pub upload_speed: String,
+    #[serde(rename = "uploadLength")]
+    pub upload_length: String,
+    /// Peers we are connected to
+    pub connections: String,
+    /// Connected peers that have the whole torrent
+    #[serde(rename = "numSeeders")]
+    pub num_seeders: String,
     #[serde(rename = "infoHash")]
     pub info_hash: Option<String>,
+    pub bittorrent: Option<BittorrentInfo>,
+    pub dir: Option<String>,
+    /// Only filled by `tellStatus`, not by the list calls
+    pub files: Vec<DownloadFile>,
+    /// Last error code reported by aria2; "0" when the download succeeded
+    #[serde(rename = "errorCode")]
+    pub error_code: Option<String>,
+    #[serde(rename = "errorMessage")]
+    pub error_message: Option<String>,
+    /// Downloads started by this one, e.g. the actual torrent after a magnet's metadata arrived
+    #[serde(rename = "followedBy")]
+    pub followed_by: Vec<String>,
+    /// Only filled by `tellStatus`
+    #[serde(rename = "numPieces")]
+    pub num_pieces: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct BittorrentInfo {
+    /// Trackers, grouped in tiers
+    #[serde(rename = "announceList")]
+    pub announce_list: Vec<Vec<String>>,
+    /// Missing until a magnet's metadata has been fetched
+    pub info: Option<BittorrentName>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BittorrentName {
+    pub name: String,
+}
+
+impl TorrentStatus {
+    /// Unknown states are treated as queued
+    pub fn state(&self) -> DownloadState {
+        DownloadState::from_aria2(&self.status).unwrap_or(DownloadState::Waiting)
+    }
+
+    /// Torrent name, falling back to the first file for plain downloads
+    pub fn name(&self) -> String {
+        if let Some(info) = self.bittorrent.as_ref().and_then(|bt| bt.info.as_ref()) {
+            return info.name.clone();
+        }
+        match self.files.first().map(|f| f.path.as_str()).filter(|p| !p.is_empty()) {
+            Some(path) if !path.starts_with("[METADATA]") => {
+                path.rsplit(['/', '\\']).next().unwrap_or(path).to_string()
+            }
+            _ if self.bittorrent.is_some() || self.info_hash.is_some() => "Fetching metadata…".to_string(),
+            _ => self.gid.clone(),
+        }
+    }
+
+    /// aria2's control file of a torrent, `<dir>/<torrent name>.aria2`; for a multi-file torrent
+    /// it is not next to any of the files
+    pub fn control_file(&self) -> Option<std::path::PathBuf> {
+        let name = &self.bittorrent.as_ref()?.info.as_ref()?.name;
+        if name.is_empty() || name.contains(['/', '\\']) || name == ".." {
+            return None;
+        }
+        Some(std::path::Path::new(self.dir.as_deref()?).join(format!("{}.aria2", name)))
+    }
+
+    /// Whether the torrent's file list is known, i.e. its metadata has been fetched
+    pub fn has_metadata(&self) -> bool {
+        self.bittorrent.as_ref().is_some_and(|bt| bt.info.is_some())
+    }
+
+    /// Magnet link rebuilt from the info-hash, name and trackers, for re-adding a torrent
+    pub fn magnet_link(&self) -> Option<String> {
+        let hash = self.info_hash.as_ref()?;
+        let mut magnet = format!("magnet:?xt=urn:btih:{}", hash);
+        if let Some(info) = self.bittorrent.as_ref().and_then(|bt| bt.info.as_ref()) {
+            magnet.push_str(&format!("&dn={}", urlencoding::encode(&info.name)));
+        }
+        if let Some(bt) = &self.bittorrent {
+            let trackers: Vec<String> = bt.announce_list.iter().flatten().cloned().collect();
+            magnet.push_str(&tracker_params(&trackers));
+        }
+        Some(magnet)
+    }
+
+    pub fn completed_bytes(&self) -> u64 {
+        self.completed_length.parse().unwrap_or(0)
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.total_length.parse().unwrap_or(0)
+    }
+
+    pub fn download_speed_bps(&self) -> u64 {
+        self.download_speed.parse().unwrap_or(0)
+    }
+
+    pub fn upload_speed_bps(&self) -> u64 {
+        self.upload_speed.parse().unwrap_or(0)
+    }
+
+    /// Uploaded divided by downloaded
+    pub fn ratio(&self) -> f64 {
+        let completed = self.completed_bytes();
+        if completed == 0 {
+            0.0
+        } else {
+            self.upload_length.parse::<u64>().unwrap_or(0) as f64 / completed as f64
+        }
+    }
+
+    /// Time left at the current download speed
+    pub fn eta(&self) -> Option<Duration> {
+        let speed = self.download_speed_bps();
+        let remaining = self.total_bytes().checked_sub(self.completed_bytes())?;
+        if speed == 0 || remaining == 0 {
+            return None;
+        }
+        Some(Duration::from_secs(remaining.div_ceil(speed)))
+    }
+
+    /// aria2's error code and message for failed downloads
+    pub fn error(&self) -> Option<String> {
+        if self.state() != DownloadState::Error {
+            return None;
+        }
+        let code = self.error_code.as_deref().unwrap_or("?");
+        Some(match self.error_message.as_deref().filter(|m| !m.is_empty()) {
+            Some(message) => format!("error {}: {}", code, message),
+            None => format!("error {}", code),
+        })
+    }
+}
+
+/// One file of a download, from `aria2.getFiles`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DownloadFile {
+    pub index: String,
+    pub path: String,
+    pub length: String,
+    #[serde(rename = "completedLength")]
+    pub completed_length: String,
+    pub selected: String,
+}
+
+impl DownloadFile {
+    /// Fraction downloaded, between 0 and 1
+    pub fn progress(&self) -> f64 {
+        let length: u64 = self.length.parse().unwrap_or(0);
+        if length == 0 {
+            return 0.0;
+        }
+        self.completed_length.parse::<u64>().unwrap_or(0) as f64 / length as f64
+    }
 }
 
-pub async fn get_active_downloads() -> Result<Vec<TorrentStatus>, reqwest::Error> {
-    let client = reqwest::Client::new();
-    let payload = json!({
-        "jsonrpc": "2.0",
-        "method": "aria2.tellActive",
-        "id": "1"
-    });
+/// A source URI of a download, from `aria2.getUris`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UriInfo {
+    pub uri: String,
+    /// "used" or "waiting"
+    pub status: String,
+}
+
+/// A connected BitTorrent peer, from `aria2.getPeers`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PeerInfo {
+    /// Percent-encoded peer id
+    #[serde(rename = "peerId")]
+    pub peer_id: String,
+    pub ip: String,
+    pub port: String,
+    /// Hex bitfield of the pieces the peer has
+    pub bitfield: String,
+    #[serde(rename = "downloadSpeed")]
+    pub download_speed: String,
+    #[serde(rename = "uploadSpeed")]
+    pub upload_speed: String,
+    pub seeder: String,
+}
+
+/// Azureus-style client codes, as in `-qB4630-`
+const PEER_CLIENTS: &[(&str, &str)] = &[
+    ("A2", "aria2"),
+    ("AZ", "Vuze"),
+    ("BI", "BiglyBT"),
+    ("BT", "BitTorrent"),
+    ("DE", "Deluge"),
+    ("FD", "Free Download Manager"),
+    ("KT", "KTorrent"),
+    ("LT", "libtorrent"),
+    ("lt", "rTorrent"),
+    ("qB", "qBittorrent"),
+    ("TR", "Transmission"),
+    ("UM", "µTorrent Mac"),
+    ("UT", "µTorrent"),
+    ("WW", "WebTorrent"),
+];
+
+impl PeerInfo {
+    /// Client name and version decoded from the peer id, e.g. "qBittorrent 4.6.3"
+    pub fn client(&self) -> String {
+        let id = urlencoding::decode_binary(self.peer_id.as_bytes());
+        let id = String::from_utf8_lossy(&id);
+        // aria2 uses its own "A2-1-37-0-" format
+        if let Some(version) = id.strip_prefix("A2-") {
+            let parts: Vec<&str> = version.split('-').take(3).collect();
+            return format!("aria2 {}", parts.join("."));
+        }
+        if id.starts_with('-')
+            && id.get(7..8) == Some("-")
+            && let (Some(code), Some(version)) = (id.get(1..3), id.get(3..7))
+        {
+            let version: Vec<String> = version.chars().map(|c| c.to_string()).collect();
+            // Known clients put major.minor.patch first and a build number last
+            return match PEER_CLIENTS.iter().find(|(c, _)| *c == code) {
+                Some((_, name)) => format!("{} {}", name, version.iter().take(3).cloned().collect::<Vec<_>>().join(".")),
+                None => format!("{} {}", code, version.join(".")),
+            };
+        }
+        "unknown".to_string()
+    }
+
+    /// Fraction of the torrent the peer has, from its bitfield
+    pub fn progress(&self, num_pieces: u64) -> Option<f64> {
+        if num_pieces == 0 {
+            return None;
+        }
+        let have: u32 = self
+            .bitfield
+            .chars()
+            .filter_map(|c| c.to_digit(16))
+            .map(|nibble| nibble.count_ones())
+            .sum();
+        Some((have as f64 / num_pieces as f64).min(1.0))
+    }
+}
+
+/// Everything shown on the download detail screen
+#[derive(Debug, Clone, Default)]
+pub struct DownloadDetails {
+    pub status: TorrentStatus,
+    pub peers: Vec<PeerInfo>,
+    pub files: Vec<DownloadFile>,
+    pub uris: Vec<UriInfo>,
+}
+
+/// Where `aria2.changePosition` moves a queued download
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueMove {
+    Up,
+    Down,
+    Top,
+    Bottom,
+}
 
-    let res = client
-        .post("http://localhost:6800/jsonrpc")
-        .json(&payload)
-        .send()
-        .await?;
+impl QueueMove {
+    /// `pos` and `how` parameters of `aria2.changePosition`
+    fn position(self) -> (i64, &'static str) {
+        match self {
+            QueueMove::Up => (-1, "POS_CUR"),
+            QueueMove::Down => (1, "POS_CUR"),
+            QueueMove::Top => (0, "POS_SET"),
+            QueueMove::Bottom => (0, "POS_END"),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            QueueMove::Up => "up",
+            QueueMove::Down => "down",
+            QueueMove::Top => "to the top",
+            QueueMove::Bottom => "to the bottom",
+        }
+    }
+}
+
+/// A user-requested change to one download, executed by `Aria2Client::perform`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadAction {
+    Pause { gid: String },
+    Resume { gid: String },
+    /// Stop the download; `delete_files` also removes what was downloaded so far
+    Remove { gid: String, delete_files: bool },
+    /// Forget a stopped download
+    RemoveResult { gid: String },
+    /// Re-add a failed torrent from its magnet link and forget the failed entry
+    Retry { gid: String, magnet: String },
+    /// Download only the given files (aria2 `select-file` syntax, e.g. "1,3-5"); `resume` unpauses afterwards
+    SelectFiles { gid: String, files: String, resume: bool },
+    /// Cap this download's speeds
+    SetLimits { gid: String, limits: SpeedLimits },
+    /// Change the position of a waiting download in aria2's queue
+    Move { gid: String, to: QueueMove },
+    /// Start a waiting download now instead of when its turn comes
+    ForceStart { gid: String },
+}
+
+impl DownloadAction {
+    pub fn gid(&self) -> &str {
+        match self {
+            DownloadAction::Pause { gid }
+            | DownloadAction::Resume { gid }
+            | DownloadAction::Remove { gid, .. }
+            | DownloadAction::RemoveResult { gid }
+            | DownloadAction::Retry { gid, .. }
+            | DownloadAction::SelectFiles { gid, .. }
+            | DownloadAction::SetLimits { gid, .. }
+            | DownloadAction::Move { gid, .. }
+            | DownloadAction::ForceStart { gid } => gid,
+        }
+    }
+
+    /// Actions that lose data are confirmed first
+    pub fn is_destructive(&self) -> bool {
+        matches!(self, DownloadAction::Remove { .. })
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            DownloadAction::Pause { gid } => format!("Pause {}", gid),
+            DownloadAction::Resume { gid } => format!("Resume {}", gid),
+            DownloadAction::Remove { gid, delete_files: false } => format!("Remove {}", gid),
+            DownloadAction::Remove { gid, delete_files: true } => format!("Remove {} and delete its files", gid),
+            DownloadAction::RemoveResult { gid } => format!("Clear {}", gid),
+            DownloadAction::Retry { gid, .. } => format!("Retry {}", gid),
+            DownloadAction::SelectFiles { gid, .. } => format!("Select files of {}", gid),
+            DownloadAction::SetLimits { gid, limits } => format!("Limit {} to {}", gid, limits),
+            DownloadAction::Move { gid, to } => format!("Move {} {}", gid, to.label()),
+            DownloadAction::ForceStart { gid } => format!("Force start {}", gid),
+        }
+    }
+}
+
+/// Result of `aria2.getVersion`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Aria2Version {
+    pub version: String,
+    #[serde(rename = "enabledFeatures", default)]
+    pub enabled_features: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<Aria2Error>,
+}
+
+/// JSON-RPC client for a running aria2 instance
+///
+/// Cheap to clone; clones share the connection pool and the request id counter.
+#[derive(Debug, Clone)]
+pub struct Aria2Client {
+    http: Client,
+    endpoint: String,
+    secret: Option<String>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Aria2Client {
+    /// Client for a JSON-RPC endpoint such as `http://localhost:6800/jsonrpc`
+    pub fn new(endpoint: &str) -> Self {
+        let http = Client::builder()
+            .user_agent("TUI-Torrent/1.0")
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            http,
+            endpoint: endpoint.to_string(),
+            secret: None,
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// RPC secret (`--rpc-secret`), sent as the `token:` parameter of every call
+    pub fn with_secret(mut self, secret: Option<String>) -> Self {
+        self.secret = secret;
+        self
+    }
+
+    pub fn secret(&self) -> Option<&str> {
+        self.secret.as_deref()
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// WebSocket endpoint for notifications, e.g. `ws://localhost:6800/jsonrpc`
+    pub fn ws_url(&self) -> String {
+        match self.endpoint.split_once("://") {
+            Some(("https", rest)) => format!("wss://{}", rest),
+            Some((_, rest)) => format!("ws://{}", rest),
+            None => format!("ws://{}", self.endpoint),
+        }
+    }
+
+    /// Call `method` with positional `params` and decode its `result`
+    pub async fn call<T: DeserializeOwned>(&self, method: &str, mut params: Vec<Value>) -> Result<T> {
+        if let Some(secret) = &self.secret {
+            params.insert(0, json!(format!("token:{}", secret)));
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": id.to_string(),
+            "method": method,
+            "params": params,
+        });
+
+        let response = self.http.post(&self.endpoint).json(&payload).send().await?;
+        // aria2 answers errors with a 4xx status and a JSON-RPC error body, so the body is read either way
+        let status = response.status();
+        let body = response.text().await?;
+        let decoded: RpcResponse<T> = serde_json::from_str(&body)
+            .map_err(|e| TorrentError::Parse(format!("{} response (HTTP {}): {}", method, status, e)))?;
+
+        match (decoded.result, decoded.error) {
+            (_, Some(error)) => Err(error.into()),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(TorrentError::Parse(format!("{} response has neither result nor error", method))),
+        }
+    }
+
+    pub async fn get_version(&self) -> Result<Aria2Version> {
+        self.call("aria2.getVersion", vec![]).await
+    }
+
+    /// Write unfinished downloads to the file given by aria2's `--save-session`
+    pub async fn save_session(&self) -> Result<String> {
+        self.call("aria2.saveSession", vec![]).await
+    }
+
+    /// Ask aria2 to finish up (e.g. tell trackers it stopped) and exit
+    pub async fn shutdown(&self) -> Result<String> {
+        self.call("aria2.shutdown", vec![]).await
+    }
+
+    /// Add a magnet link or URI; returns the GID of the new download
+    pub async fn add_uri(&self, uri: &str) -> Result<String> {
+        self.call("aria2.addUri", vec![json!([uri])]).await
+    }
+
+    /// Add a download with per-download options such as `pause-metadata`
+    pub async fn add_uri_with_options(&self, uri: &str, options: &[(&str, &str)]) -> Result<String> {
+        self.call("aria2.addUri", vec![json!([uri]), option_map(options)]).await
+    }
+
+    /// Fetch a magnet's metadata without downloading it and read the file list from the saved `.torrent`
+    ///
+    /// aria2 writes `<info-hash>.torrent` into the download's directory, so this only works when that
+    /// directory is reachable from here. The temporary download and the file are cleaned up afterwards.
+    pub async fn fetch_metadata(&self, magnet: &str, timeout: Duration) -> Result<TorrentMetadata> {
+        let gid = self
+            .add_uri_with_options(magnet, &[("bt-metadata-only", "true"), ("bt-save-metadata", "true")])
+            .await?;
+        let mut guard = MetadataGuard {
+            client: self.clone(),
+            gid: Some(gid.clone()),
+        };
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        let status = loop {
+            let status = self.tell_status(&gid).await?;
+            match status.state() {
+                DownloadState::Complete => break status,
+                DownloadState::Error | DownloadState::Removed => {
+                    guard.disarm();
+                    let _ = self.remove_download_result(&gid).await;
+                    let reason = status.error().unwrap_or_else(|| "download was removed".to_string());
+                    return Err(TorrentError::NotFound(format!("metadata could not be fetched: {}", reason)));
+                }
+                _ if tokio::time::Instant::now() >= deadline => {
+                    guard.disarm();
+                    let _ = self.remove(&gid).await;
+                    let _ = self.remove_download_result(&gid).await;
+                    return Err(TorrentError::NotFound(format!(
+                        "no peer sent the metadata within {}s",
+                        timeout.as_secs()
+                    )));
+                }
+                _ => tokio::time::sleep(Duration::from_millis(500)).await,
+            }
+        };
+        guard.disarm();
+        let _ = self.remove_download_result(&gid).await;
+
+        let (Some(dir), Some(hash)) = (status.dir.as_deref(), status.info_hash.as_deref()) else {
+            return Err(TorrentError::Parse("aria2 did not report where the metadata was saved".to_string()));
+        };
+        let path = std::path::Path::new(dir).join(format!("{}.torrent", hash));
+        let data = tokio::fs::read(&path)
+            .await
+            .map_err(|e| TorrentError::NotFound(format!("could not read {}: {}", path.display(), e)))?;
+        let _ = tokio::fs::remove_file(&path).await;
+        TorrentMetadata::from_bytes(&data).map_err(TorrentError::Parse)
+    }
+
+    /// Change options of a download; aria2 restarts active downloads for most options
+    pub async fn change_option(&self, gid: &str, options: &[(&str, &str)]) -> Result<String> {
+        self.call("aria2.changeOption", vec![json!(gid), option_map(options)]).await
+    }
+
+    /// Change options that apply to aria2 as a whole
+    pub async fn change_global_option(&self, options: &[(&str, &str)]) -> Result<String> {
+        self.call("aria2.changeGlobalOption", vec![option_map(options)]).await
+    }
+
+    /// Current global options, e.g. `max-overall-download-limit`
+    pub async fn get_global_option(&self) -> Result<HashMap<String, String>> {
+        self.call("aria2.getGlobalOption", Vec::new()).await
+    }
+
+    /// Overall download and upload limits currently in force
+    pub async fn global_limits(&self) -> Result<SpeedLimits> {
+        let options = self.get_global_option().await?;
+        let limit = |key: &str| options.get(key).and_then(|v| parse_speed(v));
+        Ok(SpeedLimits {
+            download: limit("max-overall-download-limit"),
+            upload: limit("max-overall-upload-limit"),
+        })
+    }
+
+    pub async fn set_global_limits(&self, limits: &SpeedLimits) -> Result<String> {
+        let options = limits.global_options();
+        let options: Vec<(&str, &str)> = options.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        self.change_global_option(&options).await
+    }
+
+    /// Move a waiting download; `how` is `POS_SET`, `POS_CUR` or `POS_END`. Returns the new 0-based position
+    pub async fn change_position(&self, gid: &str, pos: i64, how: &str) -> Result<i64> {
+        self.call("aria2.changePosition", vec![json!(gid), json!(pos), json!(how)]).await
+    }
+
+    /// Number of downloads aria2 runs at once; the rest wait in the queue
+    pub async fn max_concurrent_downloads(&self) -> Result<u32> {
+        let options = self.get_global_option().await?;
+        options
+            .get("max-concurrent-downloads")
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| TorrentError::Parse("aria2 did not report max-concurrent-downloads".to_string()))
+    }
+
+    pub async fn set_max_concurrent_downloads(&self, max: u32) -> Result<String> {
+        self.change_global_option(&[("max-concurrent-downloads", &max.to_string())]).await
+    }
+
+    /// Start a queued download right away
+    ///
+    /// aria2 has no such switch: the download goes to the front of the queue, and when every
+    /// slot is taken `max-concurrent-downloads` is raised by one until it has started. Lowering
+    /// the limit again does not stop running downloads, so the user's limit is kept.
+    pub async fn force_start(&self, gid: &str) -> Result<String> {
+        let status = self.tell_status(gid).await?;
+        self.change_position(gid, 0, "POS_SET").await?;
+        if status.state() == DownloadState::Paused {
+            self.unpause(gid).await?;
+        }
+        let active = self.tell_active().await?.len() as u32;
+        let max = self.max_concurrent_downloads().await?;
+        if active < max {
+            return Ok(format!("Force started {}", gid));
+        }
+        self.set_max_concurrent_downloads(active + 1).await?;
+        let started = self.wait_for_state(gid, DownloadState::Active, STATE_CHANGE_TIMEOUT).await;
+        self.set_max_concurrent_downloads(max).await?;
+        if started? {
+            Ok(format!("Force started {} beyond the limit of {} concurrent downloads", gid, max))
+        } else {
+            Ok(format!("Moved {} to the front of the queue; it did not start within {}s", gid, STATE_CHANGE_TIMEOUT.as_secs()))
+        }
+    }
+
+    /// Full status of one download, including its files
+    pub async fn tell_status(&self, gid: &str) -> Result<TorrentStatus> {
+        self.call("aria2.tellStatus", vec![json!(gid)]).await
+    }
+
+    pub async fn tell_active(&self) -> Result<Vec<TorrentStatus>> {
+        self.call("aria2.tellActive", vec![json!(STATUS_KEYS)]).await
+    }
+
+    pub async fn get_files(&self, gid: &str) -> Result<Vec<DownloadFile>> {
+        self.call("aria2.getFiles", vec![json!(gid)]).await
+    }
+
+    /// Peers of a BitTorrent download
+    pub async fn get_peers(&self, gid: &str) -> Result<Vec<PeerInfo>> {
+        self.call("aria2.getPeers", vec![json!(gid)]).await
+    }
+
+    /// Source URIs of a plain HTTP/FTP download
+    pub async fn get_uris(&self, gid: &str) -> Result<Vec<UriInfo>> {
+        self.call("aria2.getUris", vec![json!(gid)]).await
+    }
+
+    /// Status, peers, files and URIs of one download, fetched concurrently
+    ///
+    /// Peers and URIs only exist for some kinds of download, so failures there leave the lists empty.
+    pub async fn details(&self, gid: &str) -> Result<DownloadDetails> {
+        let (status, files, peers, uris) = tokio::join!(
+            self.tell_status(gid),
+            self.get_files(gid),
+            self.get_peers(gid),
+            self.get_uris(gid)
+        );
+        Ok(DownloadDetails {
+            status: status?,
+            files: files?,
+            peers: peers.unwrap_or_default(),
+            uris: uris.unwrap_or_default(),
+        })
+    }
+
+    pub async fn pause(&self, gid: &str) -> Result<String> {
+        self.call("aria2.pause", vec![json!(gid)]).await
+    }
+
+    pub async fn unpause(&self, gid: &str) -> Result<String> {
+        self.call("aria2.unpause", vec![json!(gid)]).await
+    }
+
+    pub async fn remove(&self, gid: &str) -> Result<String> {
+        self.call("aria2.remove", vec![json!(gid)]).await
+    }
+
+    /// Remove without contacting trackers first; aria2 still finishes in the background
+    pub async fn force_remove(&self, gid: &str) -> Result<String> {
+        self.call("aria2.forceRemove", vec![json!(gid)]).await
+    }
+
+    /// Wait until aria2 reports a removed download as `removed`, i.e. it no longer writes to its files
+    async fn wait_until_removed(&self, gid: &str) -> Result<()> {
+        match self.wait_for_state(gid, DownloadState::Removed, STATE_CHANGE_TIMEOUT).await {
+            Ok(true) => Ok(()),
+            // Already dropped from aria2's list of results
+            Err(TorrentError::Aria2(e)) if e.is_gid_not_found() => Ok(()),
+            Ok(false) => Err(TorrentError::Io {
+                context: format!("{} was still being removed, so its files were kept", gid),
+                error: std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("aria2 did not finish within {}s", STATE_CHANGE_TIMEOUT.as_secs()),
+                ),
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Poll `tellStatus` until the download is in `state`; `false` if it still is not after `timeout`
+    async fn wait_for_state(&self, gid: &str, state: DownloadState, timeout: Duration) -> Result<bool> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if self.tell_status(gid).await?.state() == state {
+                return Ok(true);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Ok(false);
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    }
+
+    pub async fn remove_download_result(&self, gid: &str) -> Result<String> {
+        self.call("aria2.removeDownloadResult", vec![json!(gid)]).await
+    }
+
+    /// Execute an action and describe the outcome for the status bar
+    pub async fn perform(&self, action: &DownloadAction) -> Result<String> {
+        match action {
+            DownloadAction::Pause { gid } => {
+                self.pause(gid).await?;
+                Ok(format!("Paused {}", gid))
+            }
+            DownloadAction::Resume { gid } => {
+                self.unpause(gid).await?;
+                Ok(format!("Resumed {}", gid))
+            }
+            DownloadAction::Remove { gid, delete_files } => {
+                // Paths have to be looked up before aria2 forgets the download
+                let files = if *delete_files { self.get_files(gid).await? } else { Vec::new() };
+                let status = self.tell_status(gid).await?;
+                if status.state().is_stopped() {
+                    self.remove_download_result(gid).await?;
+                } else if *delete_files {
+                    // aria2 removes asynchronously; deleting while it still writes pieces or the
+                    // .aria2 control file would leave partial files behind
+                    self.force_remove(gid).await?;
+                    self.wait_until_removed(gid).await?;
+                } else {
+                    self.remove(gid).await?;
+                }
+                if *delete_files {
+                    let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+                    let deleted = delete_download_files(&paths, status.control_file().as_deref())
+                        .map_err(|error| TorrentError::Io {
+                            context: format!("removed {} but could not delete its files", gid),
+                            error,
+                        })?;
+                    Ok(format!("Removed {} and deleted {} file(s)", gid, deleted))
+                } else {
+                    Ok(format!("Removed {}", gid))
+                }
+            }
+            DownloadAction::RemoveResult { gid } => {
+                self.remove_download_result(gid).await?;
+                Ok(format!("Cleared {}", gid))
+            }
+            DownloadAction::Retry { gid, magnet } => {
+                let new_gid = self.add_uri(magnet).await?;
+                // The failed entry is only clutter once the torrent is queued again
+                let _ = self.remove_download_result(gid).await;
+                Ok(format!("Retrying {} as {}", gid, new_gid))
+            }
+            DownloadAction::SelectFiles { gid, files, resume } => {
+                self.change_option(gid, &[("select-file", files)]).await?;
+                if *resume {
+                    self.unpause(gid).await?;
+                }
+                Ok(format!("Selected files {} of {}", files, gid))
+            }
+            DownloadAction::SetLimits { gid, limits } => {
+                let options = limits.download_options();
+                let options: Vec<(&str, &str)> = options.iter().map(|(k, v)| (*k, v.as_str())).collect();
+                self.change_option(gid, &options).await?;
+                Ok(format!("Limited {} to {}", gid, limits))
+            }
+            DownloadAction::Move { gid, to } => {
+                let (pos, how) = to.position();
+                let position = self.change_position(gid, pos, how).await?;
+                Ok(format!("Moved {} to position {} in the queue", gid, position + 1))
+            }
+            DownloadAction::ForceStart { gid } => self.force_start(gid).await,
+        }
+    }
+
+    /// Queued and paused downloads, `num` at a time starting at `offset`
+    pub async fn tell_waiting(&self, offset: usize, num: usize) -> Result<Vec<TorrentStatus>> {
+        self.call("aria2.tellWaiting", vec![json!(offset), json!(num), json!(STATUS_KEYS)]).await
+    }
+
+    /// Completed, errored and removed downloads, `num` at a time starting at `offset`
+    pub async fn tell_stopped(&self, offset: usize, num: usize) -> Result<Vec<TorrentStatus>> {
+        self.call("aria2.tellStopped", vec![json!(offset), json!(num), json!(STATUS_KEYS)]).await
+    }
+
+    /// Every download aria2 knows about: active, then waiting, then stopped
+    pub async fn tell_all(&self) -> Result<Vec<TorrentStatus>> {
+        let mut downloads = self.tell_active().await?;
+        for stopped in [false, true] {
+            let mut offset = 0;
+            loop {
+                let page = if stopped {
+                    self.tell_stopped(offset, PAGE_SIZE).await?
+                } else {
+                    self.tell_waiting(offset, PAGE_SIZE).await?
+                };
+                let len = page.len();
+                downloads.extend(page);
+                if len < PAGE_SIZE {
+                    break;
+                }
+                offset += len;
+            }
+        }
+        Ok(downloads)
+    }
+}
+
+
+/// Removes the metadata-only download of an abandoned `fetch_metadata` from aria2, e.g. when
+/// the preview task is aborted; it would otherwise linger there as an unknown entry
+struct MetadataGuard {
+    client: Aria2Client,
+    gid: Option<String>,
+}
+
+impl MetadataGuard {
+    /// The download has been or is about to be cleaned up by `fetch_metadata` itself
+    fn disarm(&mut self) {
+        self.gid = None;
+    }
+}
+
+impl Drop for MetadataGuard {
+    fn drop(&mut self) {
+        // Drop cannot wait for aria2, so the removal runs on its own task
+        let (Some(gid), Ok(runtime)) = (self.gid.take(), tokio::runtime::Handle::try_current()) else {
+            return;
+        };
+        let client = self.client.clone();
+        runtime.spawn(async move {
+            let _ = client.force_remove(&gid).await;
+            let _ = client.remove_download_result(&gid).await;
+        });
+    }
+}
 
-    let json: serde_json::Value = res.json().await?;
-    let torrents = serde_json::from_value(json["result"].clone()).unwrap_or(vec![]);
-    Ok(torrents)
+fn option_map(options: &[(&str, &str)]) -> Value {
+    Value::Object(options.iter().map(|(k, v)| (k.to_string(), json!(v))).collect())
 }
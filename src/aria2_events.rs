@@ -0,0 +1,141 @@
+use futures::StreamExt;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Reconnect delays grow from the first to the last value
+const RECONNECT_DELAYS: &[u64] = &[1, 2, 5, 10, 30];
+
+/// Download state change pushed by aria2 over its WebSocket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadEvent {
+    Start,
+    Pause,
+    Stop,
+    Complete,
+    Error,
+    /// Torrent payload finished; aria2 keeps seeding
+    BtComplete,
+}
+
+impl DownloadEvent {
+    /// Map a notification method such as `aria2.onDownloadStart`
+    pub fn from_method(method: &str) -> Option<Self> {
+        Some(match method {
+            "aria2.onDownloadStart" => DownloadEvent::Start,
+            "aria2.onDownloadPause" => DownloadEvent::Pause,
+            "aria2.onDownloadStop" => DownloadEvent::Stop,
+            "aria2.onDownloadComplete" => DownloadEvent::Complete,
+            "aria2.onDownloadError" => DownloadEvent::Error,
+            "aria2.onBtDownloadComplete" => DownloadEvent::BtComplete,
+            _ => return None,
+        })
+    }
+}
+
+/// What the notification listener reports to the main loop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Aria2Notification {
+    Connected,
+    /// The socket dropped or could not be opened; the listener keeps retrying
+    Disconnected(String),
+    Download { event: DownloadEvent, gid: String },
+}
+
+#[derive(Debug, Deserialize)]
+struct RawNotification {
+    method: String,
+    #[serde(default)]
+    params: Vec<GidParam>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GidParam {
+    gid: String,
+}
+
+/// Parse a notification frame such as
+/// `{"jsonrpc":"2.0","method":"aria2.onDownloadStart","params":[{"gid":"2089b05ecca3d829"}]}`
+///
+/// Responses to calls and unknown methods yield nothing.
+pub fn parse_notification(text: &str) -> Vec<Aria2Notification> {
+    let Ok(raw) = serde_json::from_str::<RawNotification>(text) else {
+        return Vec::new();
+    };
+    let Some(event) = DownloadEvent::from_method(&raw.method) else {
+        return Vec::new();
+    };
+    raw.params
+        .into_iter()
+        .map(|param| Aria2Notification::Download { event, gid: param.gid })
+        .collect()
+}
+
+/// Background WebSocket listener; dropping it closes the connection
+pub struct Aria2Events {
+    updates: mpsc::UnboundedReceiver<Aria2Notification>,
+    task: JoinHandle<()>,
+}
+
+impl Aria2Events {
+    /// Connect to `ws_url` (e.g. `ws://localhost:6800/jsonrpc`), reconnecting whenever the socket drops
+    pub fn spawn(ws_url: &str) -> Self {
+        let (tx, updates) = mpsc::unbounded_channel();
+        let ws_url = ws_url.to_string();
+        let task = tokio::spawn(async move {
+            let mut attempt = 0;
+            loop {
+                let reason = match tokio_tungstenite::connect_async(ws_url.as_str()).await {
+                    Ok((mut socket, _)) => {
+                        attempt = 0;
+                        if tx.send(Aria2Notification::Connected).is_err() {
+                            return;
+                        }
+                        loop {
+                            match socket.next().await {
+                                Some(Ok(Message::Text(text))) => {
+                                    for notification in parse_notification(&text) {
+                                        if tx.send(notification).is_err() {
+                                            return;
+                                        }
+                                    }
+                                }
+                                Some(Ok(Message::Close(_))) | None => break "connection closed".to_string(),
+                                Some(Ok(_)) => {}
+                                Some(Err(e)) => break e.to_string(),
+                            }
+                        }
+                    }
+                    Err(e) => e.to_string(),
+                };
+
+                if tx.send(Aria2Notification::Disconnected(reason)).is_err() {
+                    return;
+                }
+                let delay = RECONNECT_DELAYS[attempt.min(RECONNECT_DELAYS.len() - 1)];
+                attempt += 1;
+                sleep(Duration::from_secs(delay)).await;
+            }
+        });
+
+        Self { updates, task }
+    }
+
+    /// Next notification if one is already waiting
+    pub fn try_next(&mut self) -> Option<Aria2Notification> {
+        self.updates.try_recv().ok()
+    }
+
+    /// Wait for the next notification
+    pub async fn next(&mut self) -> Option<Aria2Notification> {
+        self.updates.recv().await
+    }
+}
+
+impl Drop for Aria2Events {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+
+/// How far above a taken port to look for a free one
+const PORT_SEARCH_RANGE: u16 = 100;
+
+/// Written next to the session file for an aria2c we spawned, so the next tui-torrent can tell
+/// whether an aria2 on the port is ours and who is using it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceRecord {
+    /// aria2c process
+    pub pid: u32,
+    pub port: u16,
+    pub secret: String,
+    #[serde(default)]
+    pub session_file: Option<PathBuf>,
+    /// tui-torrent process that started or adopted the aria2c and will shut it down
+    pub owner_pid: u32,
+}
+
+impl InstanceRecord {
+    /// Read a record; a missing or unreadable file means there is none
+    pub fn load(path: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Write the record readable only by us, since it holds the RPC secret
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let contents = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        write_private(path, &contents)
+    }
+
+    pub fn remove(path: &Path) {
+        let _ = std::fs::remove_file(path);
+    }
+
+    /// Whether the tui-torrent that owns the aria2c is still running
+    pub fn owner_alive(&self) -> bool {
+        process_alive(self.owner_pid)
+    }
+}
+
+/// Write a file only its owner can read, for anything holding the RPC secret
+pub fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let file = options.open(path)?;
+        // The mode only applies to new files
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        io::Write::write_all(&mut &file, contents.as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        io::Write::write_all(&mut options.open(path)?, contents.as_bytes())
+    }
+}
+
+/// Whether a process with this id exists; assumed on platforms where we cannot tell
+///
+/// A process owned by another user still counts: signalling it is refused with EPERM, not ESRCH.
+pub fn process_alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        // 0 and negative ids would address whole process groups
+        let Some(pid) = libc::pid_t::try_from(pid).ok().filter(|&pid| pid > 0) else {
+            return false;
+        };
+        // SAFETY: signal 0 only checks that the process exists and may be signalled
+        if unsafe { libc::kill(pid, 0) } == 0 {
+            return true;
+        }
+        io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        true
+    }
+}
+
+/// Whether something already listens on the local port
+pub fn port_in_use(port: u16) -> bool {
+    TcpListener::bind(("127.0.0.1", port)).is_err()
+}
+
+/// First free local port above `taken`
+pub fn find_free_port(taken: u16) -> Option<u16> {
+    (1..=PORT_SEARCH_RANGE)
+        .filter_map(|offset| taken.checked_add(offset))
+        .find(|&port| !port_in_use(port))
+}
@@ -1,103 +1,230 @@
-use crate::utils::{get_default_download_dir, ensure_download_dir_exists};
-use std::process::{Command, Child, Stdio};
+use crate::aria2_client::Aria2Client;
+use crate::aria2_instance::{find_free_port, port_in_use, process_alive, write_private, InstanceRecord};
+use crate::bandwidth::SpeedLimits;
+use crate::config::Aria2Config;
+use crate::error::{self, TorrentError};
+use crate::log_buffer::{LogBuffer, LogLevel, LogSource};
+use crate::utils::ensure_download_dir_exists;
+use chrono::NaiveDateTime;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use std::path::Path;
+use std::process::{Command, Child, ExitStatus, Stdio};
 use std::io;
-use tokio::time::{sleep, Duration};
-use reqwest::Client;
+use tokio::time::{sleep, Duration, Instant};
+
+/// How long a freshly spawned aria2c gets to answer RPC calls
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct Aria2Manager {
     process: Option<Child>,
-    client: Client,
+    client: Aria2Client,
+    config: Aria2Config,
+    /// Limits last set by the bandwidth schedule
+    scheduled_limits: Option<SpeedLimits>,
+    /// Receives aria2c's console output and our own lifecycle messages
+    log: LogBuffer,
+    /// Pid of an aria2c left running by an earlier tui-torrent that we took over; shut down with us like a spawned one
+    adopted: Option<u32>,
 }
 
 impl Aria2Manager {
     pub fn new() -> Self {
+        Self::with_config(Aria2Config::default())
+    }
+
+    /// Manager for the endpoint and launch flags in the `[aria2]` config section
+    pub fn with_config(config: Aria2Config) -> Self {
         Self {
             process: None,
-            client: Client::new(),
+            client: Aria2Client::new(&config.rpc_url()).with_secret(config.secret.clone()),
+            config,
+            scheduled_limits: None,
+            log: LogBuffer::default(),
+            adopted: None,
         }
     }
 
-    /// Check if aria2 RPC is already running
+    /// Log to capture aria2c's output into, shared with the log pane
+    pub fn with_log(mut self, log: LogBuffer) -> Self {
+        self.log = log;
+        self
+    }
+
+    /// JSON-RPC endpoint, e.g. `http://localhost:6800/jsonrpc`
+    pub fn rpc_url(&self) -> String {
+        self.config.rpc_url()
+    }
+
+    /// RPC client for the managed (or already running) aria2
+    pub fn client(&self) -> &Aria2Client {
+        &self.client
+    }
+
+    /// Whether aria2c was started (or adopted) by us, rather than found already running
+    pub fn is_managed(&self) -> bool {
+        self.process.is_some() || self.adopted.is_some()
+    }
+
+    /// Check if aria2 RPC is already running; an instance that rejects our secret still counts
     pub async fn is_aria2_running(&self) -> bool {
-        let payload = serde_json::json!({
-            "jsonrpc": "2.0",
-            "method": "aria2.getVersion",
-            "id": "test"
-        });
-
-        match self.client
-            .post("http://localhost:6800/jsonrpc")
-            .json(&payload)
-            .send()
-            .await
-        {
-            Ok(response) => response.status().is_success(),
-            Err(_) => false,
-        }
+        matches!(self.client.get_version().await, Ok(_) | Err(TorrentError::Aria2(_)))
     }
 
-    /// Check if aria2c command is available
-    pub fn is_aria2_installed(&self) -> bool {
-        Command::new("aria2c")
-            .arg("--version")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .map(|status| status.success())
-            .unwrap_or(false)
+    /// Check if aria2c command is available; runs `aria2c --version` off the async workers
+    pub async fn is_aria2_installed(&self) -> bool {
+        tokio::task::spawn_blocking(|| {
+            Command::new("aria2c")
+                .arg("--version")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .map(|status| status.success())
+                .unwrap_or(false)
+        })
+        .await
+        .unwrap_or(false)
     }
 
     /// Start aria2c process if not already running
+    ///
+    /// An aria2 already on the port is only used when it proves to be ours: it accepts our secret
+    /// and turns away a wrong one, or the instance file of an earlier tui-torrent points at it.
+    /// Anything else on the port makes us start our own aria2c on the next free port instead.
     pub async fn ensure_aria2_running(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if self.is_aria2_running().await {
+        if self.adopt_recorded_instance().await {
             return Ok(());
         }
 
+        let port = self.config.port;
+        match self.client.get_version().await {
+            // It checks the secret and nobody else knows it, so this is the aria2 we are configured for (or started)
+            Ok(_) if self.client.secret().is_some() && enforces_secret(&self.client).await => {
+                self.note(LogLevel::Info, format!("Using aria2 at {}", self.rpc_url()));
+                return Ok(());
+            }
+            Ok(_) => {
+                self.note(
+                    LogLevel::Warn,
+                    format!(
+                        "aria2 on port {} accepts calls without a valid secret, so it cannot be told apart from someone else's. \
+                         Start it with --rpc-secret and set the same `secret` under [aria2] to use it",
+                        port
+                    ),
+                );
+                self.use_free_port()?;
+            }
+            // Something is listening but does not accept the secret the user gave us
+            Err(TorrentError::Aria2(e)) if self.config.secret.is_some() => {
+                return Err(format!(
+                    "aria2 at {} rejected the RPC request ({}). Check `secret` under [aria2] in the config or --rpc-secret",
+                    self.rpc_url(),
+                    e.message
+                )
+                .into());
+            }
+            Err(TorrentError::Aria2(e)) => {
+                self.note(
+                    LogLevel::Warn,
+                    format!("aria2 on port {} belongs to another user or program ({})", port, e.message),
+                );
+                self.use_free_port()?;
+            }
+            Err(_) if port_in_use(port) => {
+                self.note(LogLevel::Warn, format!("Port {} is taken by a program that does not speak aria2 RPC", port));
+                self.use_free_port()?;
+            }
+            Err(_) => {}
+        }
+
         // Check if aria2c is installed
-        if !self.is_aria2_installed() {
+        if !self.is_aria2_installed().await {
             return Err("aria2c not found. Please install aria2: brew install aria2 (macOS) or apt install aria2 (Ubuntu)".into());
         }
 
         // Get and ensure download directory exists
-        let download_dir = get_default_download_dir();
+        let download_dir = self.config.download_dir();
         ensure_download_dir_exists(&download_dir)?;
         
         let download_dir_str = download_dir.to_string_lossy();
+        // Unfinished downloads are saved here and picked up again on the next start
+        let session_args = match self.config.session_file() {
+            Some(path) => {
+                prepare_session_file(&path)
+                    .map_err(|e| format!("Cannot create session file {}: {}", path.display(), e))?;
+                let path = path.to_string_lossy().to_string();
+                vec![
+                    format!("--input-file={}", path),
+                    format!("--save-session={}", path),
+                    format!("--save-session-interval={}", self.config.save_session_interval),
+                ]
+            }
+            None => Vec::new(),
+        };
+        // Without a configured secret a fresh one is generated, and kept when aria2c is restarted
+        let secret = self
+            .config
+            .secret
+            .clone()
+            .or_else(|| self.client.secret().map(str::to_string))
+            .unwrap_or_else(generate_secret);
+        // On the command line the secret would show up in `ps` for every local user
+        let conf_file = self.config.conf_file();
+        write_private(&conf_file, &format!("rpc-secret={}\n", secret))
+            .map_err(|e| format!("Cannot write aria2c config {}: {}", conf_file.display(), e))?;
+        let config = &self.config;
         
         // Try to start aria2c with download directory
         let child = Command::new("aria2c")
-            .args(&[
+            .args([
+                &format!("--conf-path={}", conf_file.display()),
                 "--enable-rpc",
-                "--rpc-listen-all=true",
-                "--rpc-allow-origin-all=true",
-                "--rpc-listen-port=6800",
+                &format!("--rpc-listen-all={}", config.rpc_listen_all), // localhost only unless configured
+                &format!("--rpc-listen-port={}", config.port),
                 "--continue=true",
-                "--max-connection-per-server=16",
-                "--max-concurrent-downloads=16",
-                "--split=16",
-                "--min-split-size=1M",
+                &format!("--max-connection-per-server={}", config.max_connection_per_server),
+                &format!("--max-concurrent-downloads={}", config.max_concurrent_downloads),
+                &format!("--split={}", config.split),
+                &format!("--min-split-size={}", config.min_split_size),
                 "--daemon=false", // Don't daemonize so we can manage the process
                 "--dir", &download_dir_str, // Set download directory
                 "--auto-file-renaming=true", // Avoid filename conflicts
                 "--allow-overwrite=false", // Don't overwrite existing files
+                &format!("--console-log-level={}", config.log_level),
+                "--summary-interval=0", // No periodic progress summaries in the log
+                "--show-console-readout=false",
             ])
-            .stdout(Stdio::null()) // Suppress aria2c output
-            .stderr(Stdio::null())
+            .args(&session_args)
+            .args(&config.extra_args)
+            .stdout(Stdio::piped()) // Captured for the log pane
+            .stderr(Stdio::piped())
             .spawn();
 
-        match child {
+        let started: Result<(), Box<dyn std::error::Error + Send + Sync>> = match child {
             Ok(mut process) => {
-                // Wait a moment for aria2c to start up
-                sleep(Duration::from_secs(2)).await;
-                
-                // Check if it's actually running
-                if self.is_aria2_running().await {
-                    self.process = Some(process);
-                    Ok(())
-                } else {
-                    // Kill the process if it's not responding
-                    let _ = process.kill();
-                    Err("Failed to start aria2c RPC server - process started but not responding".into())
+                if let Some(stdout) = process.stdout.take() {
+                    self.log.capture_aria2(stdout);
+                }
+                if let Some(stderr) = process.stderr.take() {
+                    self.log.capture_aria2(stderr);
+                }
+                self.log.push(
+                    LogSource::App,
+                    LogLevel::Info,
+                    format!("Started aria2c (pid {}) on port {}", process.id(), self.config.port),
+                );
+                self.client = self.client.clone().with_secret(Some(secret));
+                match self.wait_until_ready(&mut process).await {
+                    Ok(()) => {
+                        self.record_instance(process.id());
+                        self.process = Some(process);
+                        Ok(())
+                    }
+                    Err(e) => {
+                        // Kill the process if it's not responding
+                        kill_and_reap(process).await;
+                        Err(e.into())
+                    }
                 }
             }
             Err(e) => {
@@ -107,48 +234,253 @@ impl Aria2Manager {
                     Err(format!("Failed to start aria2c: {}", e).into())
                 }
             }
+        };
+        // aria2c has read it by now, so the secret need not stay on disk
+        let _ = std::fs::remove_file(&conf_file);
+        started
+    }
+
+    /// Reuse the aria2c an earlier tui-torrent recorded, if it still runs with the same secret and session
+    ///
+    /// When the tui-torrent that owns it is gone we take it over and shut it down on quit;
+    /// otherwise we share it and leave it to its owner. Stale records are removed.
+    async fn adopt_recorded_instance(&mut self) -> bool {
+        let Some(path) = self.config.instance_file() else {
+            return false;
+        };
+        let Some(record) = InstanceRecord::load(&path) else {
+            return false;
+        };
+        let config = Aria2Config {
+            port: record.port,
+            ..self.config.clone()
+        };
+        let client = Aria2Client::new(&config.rpc_url()).with_secret(Some(record.secret.clone()));
+        let session_matches = match (&record.session_file, client.get_global_option().await) {
+            (_, Err(_)) => false,
+            (None, Ok(_)) => true,
+            (Some(session), Ok(options)) => options.get("save-session").map(std::path::PathBuf::from).as_ref() == Some(session),
+        };
+        if !session_matches || !enforces_secret(&client).await {
+            InstanceRecord::remove(&path);
+            self.note(LogLevel::Info, format!("Removed stale aria2c record {}", path.display()));
+            return false;
+        }
+
+        self.config = config;
+        self.client = client;
+        let me = std::process::id();
+        if record.owner_pid != me && record.owner_alive() {
+            self.note(
+                LogLevel::Info,
+                format!(
+                    "Sharing aria2c (pid {}) on port {} with the tui-torrent running as pid {}",
+                    record.pid, record.port, record.owner_pid
+                ),
+            );
+        } else {
+            self.adopted = Some(record.pid);
+            let record = InstanceRecord { owner_pid: me, ..record };
+            if let Err(e) = record.save(&path) {
+                self.note(LogLevel::Warn, format!("Could not update {}: {}", path.display(), e));
+            }
+            self.note(
+                LogLevel::Info,
+                format!("Took over aria2c (pid {}) on port {} left by an earlier tui-torrent", record.pid, record.port),
+            );
+        }
+        true
+    }
+
+    /// Move our endpoint to the first free port above the configured one
+    fn use_free_port(&mut self) -> Result<(), String> {
+        let taken = self.config.port;
+        let port = find_free_port(taken).ok_or_else(|| format!("Port {} is taken and no free port was found above it", taken))?;
+        self.config.port = port;
+        self.client = Aria2Client::new(&self.config.rpc_url()).with_secret(self.client.secret().map(str::to_string));
+        self.note(LogLevel::Warn, format!("Starting our own aria2c on port {} instead of {}", port, taken));
+        Ok(())
+    }
+
+    /// Remember the aria2c we just started so a later tui-torrent can recognise it
+    fn record_instance(&self, pid: u32) {
+        let (Some(path), Some(secret)) = (self.config.instance_file(), self.client.secret()) else {
+            return;
+        };
+        let record = InstanceRecord {
+            pid,
+            port: self.config.port,
+            secret: secret.to_string(),
+            session_file: self.config.session_file(),
+            owner_pid: std::process::id(),
+        };
+        if let Err(e) = record.save(&path) {
+            self.note(LogLevel::Warn, format!("Could not write {}: {}", path.display(), e));
+        }
+    }
+
+    fn note(&self, level: LogLevel, message: String) {
+        self.log.push(LogSource::App, level, message);
+    }
+
+    /// Poll the RPC port of a just spawned aria2c, backing off from 50ms up to 1s between tries
+    async fn wait_until_ready(&self, process: &mut Child) -> Result<(), String> {
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        let mut delay = Duration::from_millis(50);
+        loop {
+            if let Ok(Some(status)) = process.try_wait() {
+                // Give the output readers a moment to catch the reason
+                sleep(Duration::from_millis(100)).await;
+                return Err(match self.log.last_at(LogLevel::Warn) {
+                    Some(entry) => format!("aria2c exited during startup ({}): {}", status, entry.message),
+                    None => format!("aria2c exited during startup ({})", status),
+                });
+            }
+            // Only an aria2 that takes our secret and turns away a wrong one counts; an unsecured
+            // stranger on the port would accept both
+            if self.client.get_version().await.is_ok() && enforces_secret(&self.client).await {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(format!(
+                    "Failed to start aria2c RPC server - no answer on {} within {}s",
+                    self.rpc_url(),
+                    STARTUP_TIMEOUT.as_secs()
+                ));
+            }
+            sleep(delay).await;
+            delay = (delay * 2).min(Duration::from_secs(1));
+        }
+    }
+
+    /// Exit status of the managed aria2c once it has died; it is no longer managed afterwards
+    pub fn child_exit(&mut self) -> Option<ExitStatus> {
+        let status = self.process.as_mut()?.try_wait().ok()??;
+        self.process = None;
+        self.log.push(LogSource::App, LogLevel::Error, format!("aria2c exited ({})", status));
+        Some(status)
+    }
+
+    /// Whether the aria2c we took over has died; like `child_exit` it is no longer managed afterwards
+    ///
+    /// There is no child process to watch, so this is only worth asking once aria2 stops answering.
+    pub fn adopted_exit(&mut self) -> Option<String> {
+        let pid = self.adopted.filter(|&pid| !process_alive(pid))?;
+        self.adopted = None;
+        let reason = format!("aria2c (pid {}) we took over exited", pid);
+        self.log.push(LogSource::App, LogLevel::Error, reason.clone());
+        Some(reason)
+    }
+
+    /// Start aria2c again after it died, resuming from the saved session
+    pub async fn restart(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // A new aria2c starts with its own limits, so the schedule has to be sent again
+        self.scheduled_limits = None;
+        self.ensure_aria2_running().await
+    }
+
+    /// Apply the `[aria2.limits]` schedule for `now`
+    ///
+    /// Limits are only sent when the schedule moves into another period, so limits set by hand
+    /// stay until the next change. Returns the limits that were applied, if any.
+    pub async fn apply_bandwidth_schedule(&mut self, now: NaiveDateTime) -> error::Result<Option<SpeedLimits>> {
+        if !self.config.limits.is_configured() {
+            return Ok(None);
+        }
+        let limits = self.config.limits.limits_at(now);
+        if self.scheduled_limits == Some(limits) {
+            return Ok(None);
         }
+        self.client.set_global_limits(&limits).await?;
+        self.scheduled_limits = Some(limits);
+        Ok(Some(limits))
     }
 
     /// Get the download directory being used
     pub fn get_download_dir(&self) -> String {
-        get_default_download_dir().to_string_lossy().to_string()
+        self.config.download_dir().to_string_lossy().to_string()
     }
 
     /// Get aria2 version info
     pub async fn get_version(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        let payload = serde_json::json!({
-            "jsonrpc": "2.0",
-            "method": "aria2.getVersion",
-            "id": "version"
-        });
-
-        let response = self.client
-            .post("http://localhost:6800/jsonrpc")
-            .json(&payload)
-            .send()
-            .await?;
-
-        let json: serde_json::Value = response.json().await?;
-        
-        if let Some(result) = json.get("result") {
-            if let Some(version) = result.get("version") {
-                return Ok(version.as_str().unwrap_or("unknown").to_string());
+        Ok(self.client.get_version().await?.version)
+    }
+
+    /// Stop the managed aria2c gracefully so its unfinished downloads resume on the next launch
+    ///
+    /// The session is saved first; if aria2c does not exit in time it is killed like `stop` does.
+    /// An aria2 we neither started nor adopted is left running.
+    pub async fn shutdown(&mut self) {
+        if !self.is_managed() {
+            return;
+        }
+        self.adopted = None;
+        let _ = self.client.save_session().await;
+        let stopped = self.client.shutdown().await.is_ok();
+        if let Some(path) = self.config.instance_file() {
+            InstanceRecord::remove(&path);
+        }
+        let Some(mut process) = self.process.take() else {
+            return;
+        };
+        if stopped {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while Instant::now() < deadline {
+                if let Ok(Some(_)) = process.try_wait() {
+                    return;
+                }
+                sleep(Duration::from_millis(100)).await;
             }
         }
-
-        Ok("unknown".to_string())
+        kill_and_reap(process).await;
     }
 
-    /// Stop the managed aria2c process
+    /// Kill the managed aria2c process; only what the last periodic session save recorded survives
     pub fn stop(&mut self) {
         if let Some(mut process) = self.process.take() {
             let _ = process.kill();
             let _ = process.wait();
+            if let Some(path) = self.config.instance_file() {
+                InstanceRecord::remove(&path);
+            }
         }
     }
 }
 
+/// Make sure the session file exists; aria2c refuses to start with a missing `--input-file`
+fn prepare_session_file(path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(())
+}
+
+/// Whether the aria2 behind `client` rejects a wrong token; one started without `--rpc-secret`
+/// accepts any, so answering our secret alone does not make it ours
+async fn enforces_secret(client: &Aria2Client) -> bool {
+    let impostor = client.clone().with_secret(Some(generate_secret()));
+    matches!(impostor.get_version().await, Err(TorrentError::Aria2(_)))
+}
+
+/// Kill aria2c and wait for it on a blocking thread, so the async workers keep running
+async fn kill_and_reap(mut process: Child) {
+    let _ = tokio::task::spawn_blocking(move || {
+        let _ = process.kill();
+        let _ = process.wait();
+    })
+    .await;
+}
+
+/// Random RPC secret for an aria2c instance we start ourselves
+fn generate_secret() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
 impl Drop for Aria2Manager {
     fn drop(&mut self) {
         self.stop();
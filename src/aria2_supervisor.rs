@@ -0,0 +1,151 @@
+use crate::aria2_manager::Aria2Manager;
+use crate::bandwidth::SpeedLimits;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+
+/// Time between health checks while aria2 answers
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Waits after failed restarts grow from the first to the last value, in seconds
+const RESTART_DELAYS: &[u64] = &[1, 2, 5, 10, 30];
+
+/// What the supervisor reports to the main loop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SupervisorEvent {
+    /// aria2 stopped answering or the aria2c we started exited
+    Disconnected(String),
+    /// Starting aria2c again with the saved session
+    Restarting { attempt: usize },
+    /// aria2 answers again, after a restart or on its own; a restarted aria2c may have moved to
+    /// another port or secret, so the endpoint to use from now on comes along
+    Reconnected { rpc_url: String, secret: Option<String> },
+    /// Overall limits sent by the bandwidth schedule
+    LimitsApplied(SpeedLimits),
+    /// Something went wrong that does not affect the connection, e.g. applying the schedule
+    Error(String),
+}
+
+/// Background task owning the `Aria2Manager`: watches aria2, restarts a crashed aria2c and
+/// follows the bandwidth schedule. Dropping it kills a managed aria2c; `shutdown` stops it gracefully.
+pub struct Aria2Supervisor {
+    updates: mpsc::UnboundedReceiver<SupervisorEvent>,
+    stop: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<Aria2Manager>>,
+}
+
+impl Aria2Supervisor {
+    pub fn spawn(mut manager: Aria2Manager) -> Self {
+        let (tx, updates) = mpsc::unbounded_channel();
+        let (stop, mut stopped) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            // Whether aria2c is ours to bring back when it dies, whether we started it or took it over
+            let mut restartable = manager.is_managed();
+            let mut connected = true;
+            let mut attempt = 0;
+            loop {
+                let delay = match check(&mut manager, &mut restartable, &mut connected, &mut attempt, &tx).await {
+                    Some(delay) => delay,
+                    None => return manager,
+                };
+                tokio::select! {
+                    _ = &mut stopped => return manager,
+                    _ = sleep(delay) => {}
+                }
+            }
+        });
+
+        Self {
+            updates,
+            stop: Some(stop),
+            task: Some(task),
+        }
+    }
+
+    /// Next event if one is already waiting
+    pub fn try_next(&mut self) -> Option<SupervisorEvent> {
+        self.updates.try_recv().ok()
+    }
+
+    /// Wait for the next event
+    pub async fn next(&mut self) -> Option<SupervisorEvent> {
+        self.updates.recv().await
+    }
+
+    /// Stop supervising and shut aria2c down, saving its session
+    pub async fn shutdown(mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(task) = self.task.take()
+            && let Ok(mut manager) = task.await
+        {
+            manager.shutdown().await;
+        }
+    }
+}
+
+/// One round of supervision; returns how long to wait before the next, or `None` once the main loop is gone
+async fn check(
+    manager: &mut Aria2Manager,
+    restartable: &mut bool,
+    connected: &mut bool,
+    attempt: &mut usize,
+    tx: &mpsc::UnboundedSender<SupervisorEvent>,
+) -> Option<Duration> {
+    if let Some(status) = manager.child_exit() {
+        *connected = false;
+        tx.send(SupervisorEvent::Disconnected(format!("aria2c exited ({})", status))).ok()?;
+    }
+
+    if *restartable && !manager.is_managed() {
+        *attempt += 1;
+        tx.send(SupervisorEvent::Restarting { attempt: *attempt }).ok()?;
+        if let Err(e) = manager.restart().await {
+            tx.send(SupervisorEvent::Disconnected(format!("restarting aria2c failed: {}", e))).ok()?;
+            let delay = RESTART_DELAYS[(*attempt - 1).min(RESTART_DELAYS.len() - 1)];
+            return Some(Duration::from_secs(delay));
+        }
+        *attempt = 0;
+        // Another tui-torrent's aria2c may have been found instead; restarting that one is up to its owner
+        *restartable = manager.is_managed();
+    }
+
+    let up = manager.is_aria2_running().await;
+    // An aria2c we took over is not our child, so its death only shows as aria2 no longer answering
+    if !up && let Some(reason) = manager.adopted_exit() {
+        *connected = false;
+        tx.send(SupervisorEvent::Disconnected(reason)).ok()?;
+        return Some(Duration::from_secs(RESTART_DELAYS[0]));
+    }
+    if up != *connected {
+        *connected = up;
+        let event = if up {
+            SupervisorEvent::Reconnected {
+                rpc_url: manager.rpc_url(),
+                secret: manager.client().secret().map(str::to_string),
+            }
+        } else {
+            SupervisorEvent::Disconnected(format!("aria2 at {} is not responding", manager.rpc_url()))
+        };
+        tx.send(event).ok()?;
+    }
+
+    if *connected {
+        let now = chrono::Local::now().naive_local();
+        match manager.apply_bandwidth_schedule(now).await {
+            Ok(Some(limits)) => tx.send(SupervisorEvent::LimitsApplied(limits)).ok()?,
+            Ok(None) => {}
+            Err(e) => tx.send(SupervisorEvent::Error(format!("Applying the bandwidth schedule failed: {}", e))).ok()?,
+        }
+    }
+    Some(CHECK_INTERVAL)
+}
+
+impl Drop for Aria2Supervisor {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
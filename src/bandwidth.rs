@@ -0,0 +1,150 @@
+use crate::config::{LimitsConfig, ScheduleRule};
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
+use std::fmt;
+
+/// Download and upload limits in bytes per second; 0 is unlimited and `None` leaves a limit as it is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpeedLimits {
+    pub download: Option<u64>,
+    pub upload: Option<u64>,
+}
+
+impl SpeedLimits {
+    pub fn new(download: u64, upload: u64) -> Self {
+        Self {
+            download: Some(download),
+            upload: Some(upload),
+        }
+    }
+
+    /// Parse "<download> [<upload>]" as typed in the TUI, e.g. "500K 50K"; "-" keeps a limit unchanged
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut parts = input.split_whitespace();
+        let mut next = || match parts.next() {
+            None | Some("-") => Ok(None),
+            Some(value) => parse_speed(value)
+                .map(Some)
+                .ok_or_else(|| format!("'{}' is not a speed like 500K, 2M or 0", value)),
+        };
+        let limits = Self {
+            download: next()?,
+            upload: next()?,
+        };
+        if parts.next().is_some() {
+            return Err("expected at most a download and an upload limit".to_string());
+        }
+        Ok(limits)
+    }
+
+    /// Options for `aria2.changeGlobalOption`
+    pub fn global_options(&self) -> Vec<(&'static str, String)> {
+        self.options("max-overall-download-limit", "max-overall-upload-limit")
+    }
+
+    /// Options for `aria2.changeOption` on a single download
+    pub fn download_options(&self) -> Vec<(&'static str, String)> {
+        self.options("max-download-limit", "max-upload-limit")
+    }
+
+    fn options(&self, download_key: &'static str, upload_key: &'static str) -> Vec<(&'static str, String)> {
+        [(download_key, self.download), (upload_key, self.upload)]
+            .into_iter()
+            .filter_map(|(key, limit)| Some((key, limit?.to_string())))
+            .collect()
+    }
+}
+
+impl fmt::Display for SpeedLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |limit: Option<u64>| limit.map(format_speed_limit).unwrap_or_else(|| "unchanged".to_string());
+        write!(f, "↓{} ↑{}", show(self.download), show(self.upload))
+    }
+}
+
+/// Parse an aria2 speed such as "500K", "1.5M" or "2048"; K and M are multiples of 1024
+pub fn parse_speed(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (number, multiplier) = match value.chars().last()?.to_ascii_uppercase() {
+        'K' => (&value[..value.len() - 1], 1024.0),
+        'M' => (&value[..value.len() - 1], 1024.0 * 1024.0),
+        _ => (value, 1.0),
+    };
+    let number: f64 = number.parse().ok().filter(|n: &f64| n.is_finite() && *n >= 0.0)?;
+    Some((number * multiplier).round() as u64)
+}
+
+/// Speed in aria2's own notation, e.g. "500K", "2M" or "0"
+pub fn to_aria2_speed(bytes_per_sec: u64) -> String {
+    match bytes_per_sec {
+        0 => "0".to_string(),
+        b if b % (1024 * 1024) == 0 => format!("{}M", b / (1024 * 1024)),
+        b if b % 1024 == 0 => format!("{}K", b / 1024),
+        b => b.to_string(),
+    }
+}
+
+/// "500K/s", "1.5M/s" or "unlimited"
+pub fn format_speed_limit(bytes_per_sec: u64) -> String {
+    match bytes_per_sec {
+        0 => "unlimited".to_string(),
+        b if b >= 1024 * 1024 => format!("{}M/s", trim_decimal(b as f64 / (1024.0 * 1024.0))),
+        b if b >= 1024 => format!("{}K/s", trim_decimal(b as f64 / 1024.0)),
+        b => format!("{}B/s", b),
+    }
+}
+
+fn trim_decimal(value: f64) -> String {
+    let text = format!("{:.1}", value);
+    text.strip_suffix(".0").map(str::to_string).unwrap_or(text)
+}
+
+/// Parse a day name ("mon", "Tuesday", ...) or a group ("weekdays", "weekends", "daily")
+pub fn parse_days(name: &str) -> Option<Vec<Weekday>> {
+    use Weekday::*;
+    Some(match name.trim().to_ascii_lowercase().as_str() {
+        "weekdays" => vec![Mon, Tue, Wed, Thu, Fri],
+        "weekends" => vec![Sat, Sun],
+        "daily" | "all" => vec![Mon, Tue, Wed, Thu, Fri, Sat, Sun],
+        day => vec![day.parse().ok()?],
+    })
+}
+
+pub fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+impl ScheduleRule {
+    /// Whether the rule covers `now`; a window ending before it starts runs past midnight
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        let (Some(start), Some(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+        let on = |day: Weekday| {
+            self.days.is_empty()
+                || self.days.iter().filter_map(|d| parse_days(d)).flatten().any(|d| d == day)
+        };
+        let (today, time) = (now.weekday(), now.time());
+        if start <= end {
+            on(today) && start <= time && time < end
+        } else {
+            (on(today) && time >= start) || (on(today.pred()) && time < end)
+        }
+    }
+}
+
+impl LimitsConfig {
+    /// Whether anything is configured; otherwise aria2's own limits are left alone
+    pub fn is_configured(&self) -> bool {
+        self.download.is_some() || self.upload.is_some() || !self.schedule.is_empty()
+    }
+
+    /// Limits the schedule asks for at `now`: the first matching rule, else the defaults; unset means unlimited
+    pub fn limits_at(&self, now: NaiveDateTime) -> SpeedLimits {
+        let (download, upload) = match self.schedule.iter().find(|rule| rule.is_active(now)) {
+            Some(rule) => (&rule.download, &rule.upload),
+            None => (&self.download, &self.upload),
+        };
+        let speed = |limit: &Option<String>| limit.as_deref().and_then(parse_speed).unwrap_or(0);
+        SpeedLimits::new(speed(download), speed(upload))
+    }
+}
@@ -0,0 +1,495 @@
+use crate::api::{piratebay, yts};
+use crate::bandwidth;
+use crate::utils::get_default_download_dir;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Application configuration, loaded from `$XDG_CONFIG_HOME/tui-torrent/config.toml`
+///
+/// Every field has a default, so the file only needs the settings that differ.
+/// See the README for a fully commented example.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub aria2: Aria2Config,
+    pub search: SearchConfig,
+    pub providers: ProvidersConfig,
+}
+
+/// How aria2 is reached and, when we start it ourselves, launched
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Aria2Config {
+    pub host: String,
+    pub port: u16,
+    /// RPC secret; when unset a random one is generated for an aria2c we start ourselves
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Let a spawned aria2c accept RPC connections from other machines
+    pub rpc_listen_all: bool,
+    /// Defaults to the OS download directory
+    pub download_dir: Option<PathBuf>,
+    pub max_concurrent_downloads: u32,
+    pub max_connection_per_server: u32,
+    pub split: u32,
+    pub min_split_size: String,
+    /// Where a spawned aria2c keeps unfinished downloads between runs; defaults to
+    /// `$XDG_DATA_HOME/tui-torrent/aria2.session`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_file: Option<PathBuf>,
+    /// Seconds between automatic session saves; 0 only saves when tui-torrent quits
+    pub save_session_interval: u32,
+    /// `--console-log-level` of a spawned aria2c: debug, info, notice, warn or error
+    pub log_level: String,
+    /// Passed to aria2c verbatim after the built-in flags
+    pub extra_args: Vec<String>,
+    pub limits: LimitsConfig,
+}
+
+/// Global speed limits, optionally varying by time of day
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// aria2 speed such as "500K" or "2M"; "0" or unset is unlimited.
+    /// aria2's own limits are only left alone when no limit or schedule is configured at all
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upload: Option<String>,
+    /// First matching rule wins; outside every rule the limits above apply
+    pub schedule: Vec<ScheduleRule>,
+}
+
+/// Limits for a recurring time window, e.g. 09:00 to 18:00 on weekdays
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleRule {
+    /// Day names ("mon", "tue", ...) or "weekdays", "weekends", "daily"; empty means every day
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// "HH:MM"; a window ending before it starts runs past midnight
+    pub start: String,
+    pub end: String,
+    /// Unset means unlimited while the rule applies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upload: Option<String>,
+}
+
+impl Default for Aria2Config {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 6800,
+            secret: None,
+            rpc_listen_all: false,
+            download_dir: None,
+            max_concurrent_downloads: 16,
+            max_connection_per_server: 16,
+            split: 16,
+            min_split_size: "1M".to_string(),
+            session_file: None,
+            save_session_interval: 60,
+            log_level: "notice".to_string(),
+            extra_args: Vec::new(),
+            limits: LimitsConfig::default(),
+        }
+    }
+}
+
+impl Aria2Config {
+    pub fn rpc_url(&self) -> String {
+        format!("http://{}:{}/jsonrpc", self.host, self.port)
+    }
+
+    pub fn download_dir(&self) -> PathBuf {
+        self.download_dir.clone().unwrap_or_else(get_default_download_dir)
+    }
+
+    /// Record of the aria2c we spawned, kept next to its session file
+    pub fn instance_file(&self) -> Option<PathBuf> {
+        self.session_file().map(|path| path.with_extension("instance"))
+    }
+
+    /// aria2c config file passed with `--conf-path`, carrying the RPC secret so it stays out of
+    /// the process list; kept next to the session file, or in the temp dir without one
+    pub fn conf_file(&self) -> PathBuf {
+        self.session_file()
+            .map(|path| path.with_extension("conf"))
+            .unwrap_or_else(|| std::env::temp_dir().join(format!("tui-torrent-{}.conf", std::process::id())))
+    }
+
+    /// Session file for a spawned aria2c; `None` when no data directory can be found
+    pub fn session_file(&self) -> Option<PathBuf> {
+        self.session_file
+            .clone()
+            .or_else(|| default_data_dir().map(|dir| dir.join("aria2.session")))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    /// Seconds a single source may take before it is reported as timed out
+    pub source_timeout_secs: u64,
+    /// Number of merged results kept for display
+    pub max_results: usize,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            source_timeout_secs: 15,
+            max_results: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProvidersConfig {
+    pub yts: ProviderConfig,
+    pub piratebay: ProviderConfig,
+    pub x1337: ProviderConfig,
+    pub torznab: Vec<TorznabConfig>,
+}
+
+impl Default for ProvidersConfig {
+    fn default() -> Self {
+        Self {
+            yts: ProviderConfig::with_trackers(yts::DEFAULT_TRACKERS),
+            piratebay: ProviderConfig::with_trackers(piratebay::DEFAULT_TRACKERS),
+            x1337: ProviderConfig::default(),
+            torznab: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProviderConfig {
+    pub enabled: bool,
+    /// Base URLs tried in order; empty keeps the built-in list
+    pub mirrors: Vec<String>,
+    /// Trackers put into magnet links the provider builds from bare info-hashes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trackers: Option<Vec<String>>,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            mirrors: Vec::new(),
+            trackers: None,
+        }
+    }
+}
+
+impl ProviderConfig {
+    fn with_trackers(trackers: &[&str]) -> Self {
+        Self {
+            trackers: Some(trackers.iter().map(|t| t.to_string()).collect()),
+            ..Self::default()
+        }
+    }
+}
+
+/// A Jackett / Prowlarr Torznab endpoint
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TorznabConfig {
+    #[serde(default = "default_torznab_name")]
+    pub name: String,
+    pub url: String,
+    pub api_key: Option<String>,
+}
+
+fn default_torznab_name() -> String {
+    "Torznab".to_string()
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io { path: PathBuf, error: std::io::Error },
+    Parse { path: PathBuf, message: String },
+    /// One message per invalid setting
+    Invalid(Vec<String>),
+    Cli(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, error } => write!(f, "Cannot read {}: {}", path.display(), error),
+            ConfigError::Parse { path, message } => write!(f, "Invalid config {}: {}", path.display(), message),
+            ConfigError::Invalid(problems) => write!(f, "Invalid configuration:\n  - {}", problems.join("\n  - ")),
+            ConfigError::Cli(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Command line options; anything given here overrides the config file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CliOptions {
+    pub config_path: Option<PathBuf>,
+    pub rpc_host: Option<String>,
+    pub rpc_port: Option<u16>,
+    pub rpc_secret: Option<String>,
+    pub download_dir: Option<PathBuf>,
+    pub source_timeout_secs: Option<u64>,
+    pub max_results: Option<usize>,
+    pub print_config: bool,
+    pub help: bool,
+}
+
+pub const USAGE: &str = "Usage: tui-torrent [OPTIONS]
+
+Options:
+  -c, --config <PATH>        Config file (default: $XDG_CONFIG_HOME/tui-torrent/config.toml)
+      --rpc-host <HOST>      aria2 RPC host
+      --rpc-port <PORT>      aria2 RPC port
+      --rpc-secret <TOKEN>   aria2 RPC secret
+  -d, --download-dir <DIR>   Download directory
+      --timeout <SECS>       Per-source search timeout
+      --max-results <N>      Number of search results to keep
+      --print-config         Print the effective configuration and exit
+  -h, --help                 Print this help";
+
+impl CliOptions {
+    /// Parse arguments, excluding the program name
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, ConfigError> {
+        let mut options = CliOptions::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Accept both "--flag value" and "--flag=value"
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| ConfigError::Cli(format!("{} needs a value\n\n{}", flag, USAGE)))
+            };
+
+            match flag.as_str() {
+                "-c" | "--config" => options.config_path = Some(PathBuf::from(value()?)),
+                "--rpc-host" => options.rpc_host = Some(value()?),
+                "--rpc-port" => options.rpc_port = Some(parse_number(&flag, &value()?)?),
+                "--rpc-secret" => options.rpc_secret = Some(value()?),
+                "-d" | "--download-dir" => options.download_dir = Some(PathBuf::from(value()?)),
+                "--timeout" => options.source_timeout_secs = Some(parse_number(&flag, &value()?)?),
+                "--max-results" => options.max_results = Some(parse_number(&flag, &value()?)?),
+                "--print-config" => options.print_config = true,
+                "-h" | "--help" => options.help = true,
+                _ => return Err(ConfigError::Cli(format!("Unknown argument '{}'\n\n{}", arg, USAGE))),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, ConfigError> {
+    value
+        .parse()
+        .map_err(|_| ConfigError::Cli(format!("{} expects a number, got '{}'", flag, value)))
+}
+
+/// `$XDG_CONFIG_HOME/tui-torrent/config.toml`, falling back to `~/.config`
+pub fn default_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+    Some(base.join("tui-torrent").join("config.toml"))
+}
+
+/// `$XDG_DATA_HOME/tui-torrent`, falling back to `~/.local/share`
+pub fn default_data_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local").join("share")))?;
+    Some(base.join("tui-torrent"))
+}
+
+impl Config {
+    /// Load the config file, apply command line overrides and validate the result
+    ///
+    /// A missing file at the default location just means defaults; an explicitly given path must exist.
+    pub fn load(cli: &CliOptions) -> Result<Self, ConfigError> {
+        let mut config = match &cli.config_path {
+            Some(path) => Self::from_file(path)?,
+            None => match default_config_path() {
+                Some(path) if path.exists() => Self::from_file(&path)?,
+                _ => Self::default(),
+            },
+        };
+        config.apply_cli(cli);
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(|error| ConfigError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        Self::from_toml(&contents).map_err(|message| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message,
+        })
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, String> {
+        toml::from_str(contents).map_err(|e| e.to_string())
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn apply_cli(&mut self, cli: &CliOptions) {
+        if let Some(host) = &cli.rpc_host {
+            self.aria2.host = host.clone();
+        }
+        if let Some(port) = cli.rpc_port {
+            self.aria2.port = port;
+        }
+        if let Some(secret) = &cli.rpc_secret {
+            self.aria2.secret = Some(secret.clone());
+        }
+        if let Some(dir) = &cli.download_dir {
+            self.aria2.download_dir = Some(dir.clone());
+        }
+        if let Some(secs) = cli.source_timeout_secs {
+            self.search.source_timeout_secs = secs;
+        }
+        if let Some(max) = cli.max_results {
+            self.search.max_results = max;
+        }
+    }
+
+    /// Check every setting, reporting all problems at once
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.aria2.host.trim().is_empty() {
+            problems.push("aria2.host must not be empty".to_string());
+        }
+        if self.aria2.secret.as_ref().is_some_and(|s| s.is_empty()) {
+            problems.push("aria2.secret must not be empty; leave it out to generate one".to_string());
+        }
+        if !["debug", "info", "notice", "warn", "error"].contains(&self.aria2.log_level.as_str()) {
+            problems.push(format!(
+                "aria2.log_level must be debug, info, notice, warn or error, got '{}'",
+                self.aria2.log_level
+            ));
+        }
+        if self.aria2.port == 0 {
+            problems.push("aria2.port must be between 1 and 65535".to_string());
+        }
+        for (name, value) in [
+            ("aria2.max_concurrent_downloads", self.aria2.max_concurrent_downloads),
+            ("aria2.max_connection_per_server", self.aria2.max_connection_per_server),
+            ("aria2.split", self.aria2.split),
+        ] {
+            if value == 0 {
+                problems.push(format!("{} must be at least 1", name));
+            }
+        }
+        let limits = &self.aria2.limits;
+        let schedule_limits = limits.schedule.iter().enumerate().flat_map(|(i, rule)| {
+            [
+                (format!("aria2.limits.schedule[{}].download", i), &rule.download),
+                (format!("aria2.limits.schedule[{}].upload", i), &rule.upload),
+            ]
+        });
+        for (name, value) in [
+            ("aria2.limits.download".to_string(), &limits.download),
+            ("aria2.limits.upload".to_string(), &limits.upload),
+        ]
+        .into_iter()
+        .chain(schedule_limits)
+        {
+            if let Some(value) = value
+                && bandwidth::parse_speed(value).is_none()
+            {
+                problems.push(format!("{}: '{}' is not a speed like 500K, 2M or 0", name, value));
+            }
+        }
+        for (i, rule) in limits.schedule.iter().enumerate() {
+            for time in [&rule.start, &rule.end] {
+                if bandwidth::parse_time(time).is_none() {
+                    problems.push(format!("aria2.limits.schedule[{}]: '{}' is not a time like 09:00", i, time));
+                }
+            }
+            for day in &rule.days {
+                if bandwidth::parse_days(day).is_none() {
+                    problems.push(format!("aria2.limits.schedule[{}]: unknown day '{}'", i, day));
+                }
+            }
+        }
+        if self.search.source_timeout_secs == 0 {
+            problems.push("search.source_timeout_secs must be at least 1".to_string());
+        }
+        if self.search.max_results == 0 {
+            problems.push("search.max_results must be at least 1".to_string());
+        }
+
+        for (name, provider) in [
+            ("yts", &self.providers.yts),
+            ("piratebay", &self.providers.piratebay),
+            ("x1337", &self.providers.x1337),
+        ] {
+            for mirror in &provider.mirrors {
+                if !is_http_url(mirror) {
+                    problems.push(format!("providers.{}.mirrors: '{}' is not an http(s) URL", name, mirror));
+                }
+            }
+            if provider.trackers.as_ref().is_some_and(|t| t.iter().any(|t| t.trim().is_empty())) {
+                problems.push(format!("providers.{}.trackers must not contain empty entries", name));
+            }
+        }
+        // Names label results and pick sources in queries, so each one has to be unique
+        let mut names: Vec<&str> = [
+            ("YTS", &self.providers.yts),
+            ("PirateBay", &self.providers.piratebay),
+            ("1337x", &self.providers.x1337),
+        ]
+        .into_iter()
+        .filter(|(_, provider)| provider.enabled)
+        .map(|(name, _)| name)
+        .collect();
+        for indexer in &self.providers.torznab {
+            if !is_http_url(&indexer.url) {
+                problems.push(format!("providers.torznab '{}': '{}' is not an http(s) URL", indexer.name, indexer.url));
+            }
+            if names.iter().any(|name| name.eq_ignore_ascii_case(&indexer.name)) {
+                problems.push(format!(
+                    "providers.torznab '{}': another source already has this name; give each indexer its own `name`",
+                    indexer.name
+                ));
+            }
+            names.push(&indexer.name);
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+}
+
+fn is_http_url(value: &str) -> bool {
+    url::Url::parse(value).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
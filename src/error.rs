@@ -5,7 +5,29 @@ pub enum TorrentError {
     Network(reqwest::Error),
     Parse(String),
     NotFound(String),
-    Aria2(String),
+    Aria2(Aria2Error),
+    /// A local file operation, with what was being done when it failed
+    Io { context: String, error: std::io::Error },
+}
+
+/// A JSON-RPC `error` object returned by aria2
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct Aria2Error {
+    pub code: i64,
+    pub message: String,
+}
+
+impl Aria2Error {
+    /// aria2 answers "GID ... is not found" once a download is gone from both its queue and results
+    pub fn is_gid_not_found(&self) -> bool {
+        self.code == 1 && self.message.starts_with("GID ") && self.message.ends_with(" is not found")
+    }
+}
+
+impl fmt::Display for Aria2Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (code {})", self.message, self.code)
+    }
 }
 
 impl fmt::Display for TorrentError {
@@ -14,7 +36,8 @@ impl fmt::Display for TorrentError {
             TorrentError::Network(e) => write!(f, "Network error: {}", e),
             TorrentError::Parse(msg) => write!(f, "Parse error: {}", msg),
             TorrentError::NotFound(msg) => write!(f, "Not found: {}", msg),
-            TorrentError::Aria2(msg) => write!(f, "Aria2 error: {}", msg),
+            TorrentError::Aria2(e) => write!(f, "Aria2 error: {}", e),
+            TorrentError::Io { context, error } => write!(f, "{}: {}", context, error),
         }
     }
 }
@@ -27,4 +50,10 @@ impl From<reqwest::Error> for TorrentError {
     }
 }
 
-pub type Result<T> = std::result::Result<T, TorrentError>;
\ No newline at end of file
+impl From<Aria2Error> for TorrentError {
+    fn from(error: Aria2Error) -> Self {
+        TorrentError::Aria2(error)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, TorrentError>;
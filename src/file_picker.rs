@@ -0,0 +1,233 @@
+use crate::aria2_client::DownloadFile;
+use crate::preview::TorrentPreview;
+use std::collections::BTreeSet;
+
+/// A file offered in the picker; `index` is aria2's 1-based file index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickerFile {
+    pub index: u32,
+    /// Path relative to the download directory, with `/` separators
+    pub path: String,
+    pub length: u64,
+    pub selected: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickerRowKind {
+    /// Directory; holds its path prefix including the trailing `/`
+    Dir(String),
+    /// Position in `FilePicker::files`
+    File(usize),
+}
+
+/// One line of the tree as displayed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickerRow {
+    pub depth: usize,
+    pub name: String,
+    pub kind: PickerRowKind,
+}
+
+/// What the picker's selection is applied to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickerTarget {
+    /// Download known to aria2; `resume_after` unpauses it afterwards (it was held after fetching metadata)
+    Download { gid: String, resume_after: bool },
+    /// Previewed search result that is not downloading yet
+    Preview {
+        name: String,
+        uri: String,
+        /// Where the file list came from
+        origin: String,
+        /// File indexes are the torrent's own, so they can be passed to aria2 as they are
+        exact: bool,
+    },
+}
+
+/// Checkbox tree over the files of a torrent, applied through aria2's `select-file` option
+#[derive(Debug, Clone)]
+pub struct FilePicker {
+    pub target: PickerTarget,
+    pub files: Vec<PickerFile>,
+    pub rows: Vec<PickerRow>,
+    pub cursor: usize,
+}
+
+impl FilePicker {
+    pub fn new(target: PickerTarget, files: Vec<PickerFile>) -> Self {
+        let mut files = files;
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let rows = build_rows(&files);
+        Self {
+            target,
+            files,
+            rows,
+            cursor: 0,
+        }
+    }
+
+    /// Picker over `aria2.getFiles` output, with paths shown relative to the download's `dir`
+    pub fn from_download(gid: &str, files: &[DownloadFile], dir: Option<&str>, resume_after: bool) -> Self {
+        let entries = files
+            .iter()
+            .filter_map(|file| {
+                let path = file.path.replace('\\', "/");
+                let relative = dir
+                    .map(|dir| dir.replace('\\', "/"))
+                    .and_then(|dir| path.strip_prefix(&format!("{}/", dir.trim_end_matches('/'))).map(str::to_string))
+                    .unwrap_or(path);
+                Some(PickerFile {
+                    index: file.index.parse().ok()?,
+                    path: relative,
+                    length: file.length.parse().unwrap_or(0),
+                    selected: file.selected == "true",
+                })
+            })
+            .collect();
+        let target = PickerTarget::Download {
+            gid: gid.to_string(),
+            resume_after,
+        };
+        Self::new(target, entries)
+    }
+
+    /// Picker over a preview's file list with everything selected
+    pub fn from_preview(preview: &TorrentPreview) -> Self {
+        let entries = preview
+            .files
+            .iter()
+            .zip(1..)
+            .map(|(file, index)| PickerFile {
+                index,
+                path: file.path.replace('\\', "/"),
+                length: file.size_bytes.unwrap_or(0),
+                selected: true,
+            })
+            .collect();
+        let target = PickerTarget::Preview {
+            name: preview.name.clone(),
+            uri: preview.uri.clone(),
+            origin: preview.origin.clone(),
+            exact: preview.exact,
+        };
+        Self::new(target, entries)
+    }
+
+    /// Select exactly the files matching `wanted` paths, which may omit leading folders
+    pub fn preselect(&mut self, wanted: &[String]) {
+        for file in &mut self.files {
+            file.selected = wanted
+                .iter()
+                .any(|path| file.path == *path || file.path.ends_with(&format!("/{}", path)));
+        }
+    }
+
+    pub fn all_selected(&self) -> bool {
+        self.files.iter().all(|f| f.selected)
+    }
+
+    pub fn move_cursor(&mut self, forward: bool) {
+        if self.rows.is_empty() {
+            return;
+        }
+        self.cursor = if forward {
+            (self.cursor + 1) % self.rows.len()
+        } else if self.cursor == 0 {
+            self.rows.len() - 1
+        } else {
+            self.cursor - 1
+        };
+    }
+
+    /// Toggle the file under the cursor, or every file below the directory under the cursor
+    pub fn toggle(&mut self) {
+        let Some(row) = self.rows.get(self.cursor) else {
+            return;
+        };
+        match row.kind.clone() {
+            PickerRowKind::File(i) => self.files[i].selected = !self.files[i].selected,
+            PickerRowKind::Dir(prefix) => {
+                let select = self.dir_selection(&prefix) != Some(true);
+                for file in self.files.iter_mut().filter(|f| f.path.starts_with(&prefix)) {
+                    file.selected = select;
+                }
+            }
+        }
+    }
+
+    /// Select everything, or nothing if everything already is
+    pub fn toggle_all(&mut self) {
+        let select = !self.all_selected();
+        for file in &mut self.files {
+            file.selected = select;
+        }
+    }
+
+    /// `Some(true)` if every file below `prefix` is selected, `Some(false)` if none is, `None` if mixed
+    pub fn dir_selection(&self, prefix: &str) -> Option<bool> {
+        let mut states = self.files.iter().filter(|f| f.path.starts_with(prefix)).map(|f| f.selected);
+        let first = states.next()?;
+        states.all(|s| s == first).then_some(first)
+    }
+
+    pub fn selected_count(&self) -> usize {
+        self.files.iter().filter(|f| f.selected).count()
+    }
+
+    pub fn selected_size(&self) -> u64 {
+        self.files.iter().filter(|f| f.selected).map(|f| f.length).sum()
+    }
+
+    /// Value for aria2's `select-file` option, e.g. "1,3-5"; `None` when nothing is selected
+    pub fn select_file_option(&self) -> Option<String> {
+        let indexes: BTreeSet<u32> = self.files.iter().filter(|f| f.selected).map(|f| f.index).collect();
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for index in indexes {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == index => *end = index,
+                _ => ranges.push((index, index)),
+            }
+        }
+        if ranges.is_empty() {
+            return None;
+        }
+        Some(
+            ranges
+                .iter()
+                .map(|&(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+                .collect::<Vec<_>>()
+                .join(","),
+        )
+    }
+}
+
+/// Flatten sorted paths into directory and file rows
+fn build_rows(files: &[PickerFile]) -> Vec<PickerRow> {
+    let mut rows = Vec::new();
+    let mut open_dirs: Vec<String> = Vec::new();
+
+    for (i, file) in files.iter().enumerate() {
+        let parts: Vec<&str> = file.path.split('/').collect();
+        let (dirs, name) = parts.split_at(parts.len() - 1);
+
+        // Close directories this file is not in, then open the ones it is
+        let shared = open_dirs.iter().zip(dirs).take_while(|(open, dir)| open == *dir).count();
+        open_dirs.truncate(shared);
+        for (depth, dir) in dirs.iter().enumerate().skip(shared) {
+            open_dirs.push(dir.to_string());
+            rows.push(PickerRow {
+                depth,
+                name: dir.to_string(),
+                kind: PickerRowKind::Dir(format!("{}/", dirs[..=depth].join("/"))),
+            });
+        }
+
+        rows.push(PickerRow {
+            depth: dirs.len(),
+            name: name[0].to_string(),
+            kind: PickerRowKind::File(i),
+        });
+    }
+
+    rows
+}
@@ -1,9 +1,21 @@
 pub mod api;
 pub mod aria2_client;
+pub mod aria2_events;
+pub mod aria2_instance;
 pub mod aria2_manager;
+pub mod aria2_supervisor;
 pub mod app;
 pub mod ascii_art;
+pub mod bandwidth;
+pub mod config;
 pub mod error;
+pub mod file_picker;
+pub mod log_buffer;
+pub mod magnet;
+pub mod preview;
+pub mod query;
+pub mod torrent_file;
 pub mod torrent_search;
 pub mod tui;
+pub mod types;
 pub mod utils;
\ No newline at end of file
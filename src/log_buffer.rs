@@ -0,0 +1,146 @@
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::{Arc, Mutex};
+
+/// Lines kept before the oldest are dropped
+pub const DEFAULT_CAPACITY: usize = 2000;
+
+/// Where a log line came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogSource {
+    App,
+    Aria2,
+}
+
+impl LogSource {
+    pub fn label(self) -> &'static str {
+        match self {
+            LogSource::App => "app",
+            LogSource::Aria2 => "aria2",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    /// Level of an aria2 console line such as `06/03 12:00:00 [ERROR] CUID#7 - ...`
+    pub fn of_aria2_line(line: &str) -> Self {
+        if line.contains("[ERROR]") || line.contains("Exception") {
+            LogLevel::Error
+        } else if line.contains("[WARN]") {
+            LogLevel::Warn
+        } else {
+            LogLevel::Info
+        }
+    }
+
+    /// Level of one of our status messages, judged by the same words the status bar colours by
+    pub fn of_status(message: &str) -> Self {
+        let lower = message.to_lowercase();
+        if lower.contains("failed") || lower.contains("error") || lower.contains("could not") {
+            LogLevel::Error
+        } else if lower.contains("disconnected") || lower.contains("invalid") {
+            LogLevel::Warn
+        } else {
+            LogLevel::Info
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub time: DateTime<Local>,
+    pub source: LogSource,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+impl LogEntry {
+    /// Case-insensitive match of `text` against the message; empty text matches everything
+    pub fn matches(&self, text: &str) -> bool {
+        text.is_empty() || self.message.to_lowercase().contains(&text.to_lowercase())
+    }
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}] {}", self.time.format("%H:%M:%S"), self.source.label(), self.message)
+    }
+}
+
+/// Ring buffer of log lines shared between the aria2 output readers and the UI
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    entries: Arc<Mutex<VecDeque<LogEntry>>>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(capacity.min(DEFAULT_CAPACITY)))),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn push(&self, source: LogSource, level: LogLevel, message: impl Into<String>) {
+        let entry = LogEntry {
+            time: Local::now(),
+            source,
+            level,
+            message: message.into(),
+        };
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Copy of every line, oldest first
+    pub fn entries(&self) -> Vec<LogEntry> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+    }
+
+    /// Most recent line at `level` or worse, e.g. to explain why aria2c exited
+    pub fn last_at(&self, level: LogLevel) -> Option<LogEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().rev().find(|entry| entry.level >= level).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copy aria2c output into the buffer line by line on a background thread until the pipe closes
+    pub fn capture_aria2<R: Read + Send + 'static>(&self, output: R) {
+        let log = self.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(output).lines() {
+                let Ok(line) = line else { break };
+                let line = line.trim_end();
+                if !line.trim().is_empty() {
+                    log.push(LogSource::Aria2, LogLevel::of_aria2_line(line), line);
+                }
+            }
+        });
+    }
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
@@ -0,0 +1,79 @@
+use url::Url;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Extract the info-hash from a magnet link's `xt=urn:btih:` parameter, normalized to lowercase hex
+pub fn info_hash(magnet_link: &str) -> Option<String> {
+    let url = Url::parse(magnet_link).ok()?;
+    url.query_pairs()
+        .filter(|(key, _)| key == "xt")
+        .find_map(|(_, value)| {
+            let hash = value.strip_prefix("urn:btih:")?;
+            normalize_info_hash(hash)
+        })
+}
+
+/// Normalize a 40 character hex or 32 character base32 info-hash to lowercase hex
+pub fn normalize_info_hash(hash: &str) -> Option<String> {
+    let hash = hash.trim();
+    match hash.len() {
+        40 if hash.chars().all(|c| c.is_ascii_hexdigit()) => Some(hash.to_ascii_lowercase()),
+        32 => {
+            let bytes = decode_base32(hash)?;
+            Some(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+        }
+        _ => None,
+    }
+}
+
+/// All tracker URLs (`tr` parameters) of a magnet link, in order
+pub fn trackers(magnet_link: &str) -> Vec<String> {
+    match Url::parse(magnet_link) {
+        Ok(url) => url
+            .query_pairs()
+            .filter(|(key, _)| key == "tr")
+            .map(|(_, value)| value.into_owned())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// `&tr=` parameters for building a magnet link from a hash and a tracker list
+pub fn tracker_params(trackers: &[String]) -> String {
+    trackers.iter().map(|tracker| format!("&tr={}", tracker)).collect()
+}
+
+/// Append the given trackers to a magnet link, skipping ones it already lists
+pub fn with_trackers(magnet_link: &str, extra: &[String]) -> String {
+    let mut known = trackers(magnet_link);
+    let mut link = magnet_link.to_string();
+    for tracker in extra {
+        if !known.contains(tracker) {
+            link.push_str("&tr=");
+            link.push_str(&urlencoding::encode(tracker));
+            known.push(tracker.clone());
+        }
+    }
+    link
+}
+
+fn decode_base32(input: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer: u64 = 0;
+    let mut bits = 0;
+
+    for c in input.bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_uppercase())? as u64;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Some(bytes)
+}
@@ -1,33 +1,89 @@
 pub mod api;
 pub mod aria2_client;
+pub mod aria2_events;
+pub mod aria2_instance;
 pub mod aria2_manager;
+pub mod aria2_supervisor;
 pub mod app;
 pub mod ascii_art;
+pub mod bandwidth;
+pub mod config;
 pub mod error;
+pub mod file_picker;
+pub mod log_buffer;
+pub mod magnet;
+pub mod preview;
+pub mod query;
+pub mod torrent_file;
 pub mod torrent_search;
 pub mod tui;
+pub mod types;
 pub mod utils;
 
 use app::{App, AppMode};
+use aria2_client::Aria2Client;
+use aria2_events::{Aria2Events, Aria2Notification};
 use aria2_manager::Aria2Manager;
+use aria2_supervisor::{Aria2Supervisor, SupervisorEvent};
 use ascii_art::TUI_LOGO;
-use torrent_search::TorrentSearchEngine;
+use api::SearchRequest;
+use config::{CliOptions, Config};
+use file_picker::FilePicker;
+use log_buffer::{LogBuffer, LogLevel, LogSource};
+use preview::TorrentPreview;
+use torrent_search::{SearchHandle, TorrentSearchEngine};
+use types::DownloadState;
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use std::io;
+use std::sync::Arc;
 use tokio::time::{Duration, Instant};
 use ratatui::{backend::CrosstermBackend, Terminal};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Configuration problems are reported before touching the terminal
+    let cli = match CliOptions::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(2);
+        }
+    };
+    if cli.help {
+        println!("{}", config::USAGE);
+        return Ok(());
+    }
+    let config = match Config::load(&cli) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(2);
+        }
+    };
+    if cli.print_config {
+        print!("{}", config.to_toml());
+        return Ok(());
+    }
+
     println!("{}", TUI_LOGO);
     println!("🏴‍☠️ Starting TUI Torrent...");
     
-    let mut aria2_manager = Aria2Manager::new();
+    // aria2c's output and our status messages, shown in the log pane
+    let log = LogBuffer::default();
+    let mut aria2_manager = Aria2Manager::with_config(config.aria2.clone()).with_log(log.clone());
     
-    let aria2_available = match aria2_manager.ensure_aria2_running().await {
+    let started = aria2_manager.ensure_aria2_running().await;
+    // Explain which aria2 we ended up with, e.g. when the port belonged to someone else
+    for entry in log.entries().iter().filter(|entry| entry.source == LogSource::App) {
+        match entry.level {
+            LogLevel::Info => println!("🔎 {}", entry.message),
+            _ => eprintln!("⚠️  {}", entry.message),
+        }
+    }
+    let aria2_available = match started {
         Ok(()) => {
             if let Ok(version) = aria2_manager.get_version().await {
                 println!("📡 Connected to aria2 version: {}", version);
@@ -39,9 +95,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         Err(e) => {
             eprintln!("⚠️  Warning: {}", e);
-            eprintln!("💡 Downloads will not work without aria2. Install it with:");
-            eprintln!("   macOS: brew install aria2");
-            eprintln!("   Ubuntu: sudo apt install aria2");
+            log.push(LogSource::App, LogLevel::Error, e.to_string());
+            if e.to_string().contains("aria2c not found") {
+                eprintln!("💡 Downloads will not work without aria2. Install it with:");
+                eprintln!("   macOS: brew install aria2");
+                eprintln!("   Ubuntu: sudo apt install aria2");
+            }
             eprintln!();
             eprintln!("🔄 Continuing anyway... (search will still work)");
             false
@@ -64,16 +123,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Create app state
     let mut app = App::new();
-    let search_engine = TorrentSearchEngine::new();
+    app.log = log;
+    app.max_results = config.search.max_results;
+    let search_engine = TorrentSearchEngine::from_config(&config);
+    // Torznab category mapping falls back to the standard Newznab ids if the caps are unavailable
+    search_engine.prepare().await;
+    let search_engine = Arc::new(search_engine);
+    let mut aria2 = aria2_manager.client().clone();
+    // Status changes are pushed over aria2's WebSocket; polling covers the gaps
+    let mut aria2_events = aria2_available.then(|| Aria2Events::spawn(&aria2.ws_url()));
+    let download_dir = aria2_manager.get_download_dir();
+    let managed = aria2_manager.is_managed();
+    // From here on the supervisor owns the manager, restarting aria2c if it dies
+    let mut supervisor = aria2_available.then(|| Aria2Supervisor::spawn(aria2_manager));
 
-    // Track if we've already rendered the initial searching frame
-    let mut initial_search_frame_rendered = false;
+    if aria2_available {
+        if let Ok(limits) = aria2.global_limits().await {
+            app.global_limits = limits;
+        }
+        app.max_concurrent = aria2.max_concurrent_downloads().await.ok();
+    }
+
+    // Search currently running in the background, if any
+    let mut running_search: Option<SearchHandle> = None;
+    // File list being fetched for a search result, if any
+    let mut running_preview: Option<tokio::task::JoinHandle<Result<TorrentPreview, String>>> = None;
     
     // Update status based on aria2 availability
     if aria2_available {
-        let download_dir = aria2_manager.get_download_dir();
-        let short_path = if download_dir.len() > 40 {
-            format!("...{}", &download_dir[download_dir.len()-37..])
+        let chars = download_dir.chars().count();
+        let short_path = if chars > 40 {
+            format!("...{}", download_dir.chars().skip(chars - 37).collect::<String>())
         } else {
             download_dir
         };
@@ -87,6 +167,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let loading_tick_rate = Duration::from_millis(150); // Faster animation during search
     let mut last_tick = Instant::now();
     let mut last_update = Instant::now();
+    let mut last_detail_update = Instant::now();
 
     loop {
 
@@ -96,30 +177,56 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             break;
         }
 
-        // Perform search (blocking) but ensure searching frame displayed first
-        if app.mode == AppMode::Searching && app.search_in_progress {
-            if !initial_search_frame_rendered {
-                // Immediate render to show user the searching state before network calls
-                tui::render_ui(&mut terminal, &app)?;
-                initial_search_frame_rendered = true;
-            } else {
-                // Execute the search now
-                let query = app.search_query.clone();
-                let category = app.selected_category.clone();
-                match search_engine.search_torrents(&query, category.as_deref()).await {
-                    Ok(results) => app.finish_search(results),
-                    Err(e) => app.search_error(e.to_string()),
-                }
-                initial_search_frame_rendered = false; // reset for next time
+        // Launch newly requested searches in the background so input stays responsive
+        if app.search_requested {
+            app.search_requested = false;
+            // Skip sources the inline filters rule out entirely
+            let allowed_sources = search_engine
+                .sources()
+                .map(|source| source.name().to_string())
+                .filter(|name| app.search_filter.allows_source(name))
+                .collect();
+            let request = SearchRequest::new(&app.search_terms)
+                .with_category(app.selected_category)
+                .with_sources(Some(allowed_sources));
+            app.track_sources(
+                search_engine
+                    .sources_for(&request)
+                    .map(|source| source.name().to_string())
+                    .collect::<Vec<_>>(),
+            );
+            // Replacing a previous handle cancels that search
+            running_search = Some(search_engine.spawn_search(request));
+        }
+
+        // Merge whatever the sources have answered so far
+        if let Some(search) = running_search.as_mut() {
+            while let Some(update) = search.try_next() {
+                app.apply_source_update(update);
             }
-        } else {
-            initial_search_frame_rendered = false; // reset if we leave searching mode
         }
-        
+
+        // Search finished or was cancelled with Esc: abort any requests still in flight
+        if !app.search_in_progress && let Some(search) = running_search.take() {
+            search.cancel();
+        }
+
         // Handle torrent download request
         if app.download_requested && !app.search_results.is_empty() {
             if let Some(selected) = app.search_results.get(app.selected_index) {
-                match torrent_search::add_torrent(&selected.magnet_link).await {
+                // Picking files needs the torrent's metadata first; aria2 pauses the real download
+                // that follows the metadata download until the selection is applied
+                let added = if app.choose_files {
+                    aria2.add_uri_with_options(&selected.magnet_link, &[("pause-metadata", "true")]).await
+                } else {
+                    aria2.add_uri(&selected.magnet_link).await
+                };
+                match added {
+                    Ok(gid) if app.choose_files => {
+                        app.status_message = format!("Fetching file list for {}...", selected.name);
+                        app.awaiting_metadata.push(gid);
+                        app.mode = AppMode::Normal;
+                    }
                     Ok(gid) => {
                         app.status_message = format!("Added torrent: {} (GID: {})", selected.name, gid);
                         app.mode = AppMode::Normal;
@@ -130,11 +237,174 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
             app.download_requested = false;
+            app.choose_files = false;
         }
 
-        // Update downloads list every 2 seconds
-        if last_update.elapsed() >= Duration::from_secs(2) {
-            app.active_downloads = aria2_client::get_active_downloads().await?;
+        // Fetch a result's file list in the background; asking again replaces the previous lookup
+        if app.preview_requested {
+            app.preview_requested = false;
+            if let Some(result) = app.search_results.get(app.selected_index).cloned() {
+                app.status_message = format!("Loading file list for {}...", result.name);
+                let engine = Arc::clone(&search_engine);
+                let aria2 = aria2_available.then(|| aria2.clone());
+                if let Some(previous) = running_preview.replace(tokio::spawn(async move {
+                    TorrentPreview::fetch(&engine, aria2.as_ref(), &result).await
+                })) {
+                    previous.abort();
+                }
+            }
+        }
+        if app.can_open_file_picker() && running_preview.as_ref().is_some_and(|task| task.is_finished())
+            && let Some(task) = running_preview.take()
+        {
+            match task.await.unwrap_or_else(|e| Err(e.to_string())) {
+                Ok(preview) => app.open_file_picker(FilePicker::from_preview(&preview)),
+                Err(e) => app.status_message = format!("Could not load the file list: {}", e),
+            }
+        }
+
+        // Start a download chosen in a preview
+        if let Some(add) = app.pending_add.take() {
+            let options: Vec<(&str, &str)> = add.options.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+            match aria2.add_uri_with_options(&add.uri, &options).await {
+                Ok(gid) => {
+                    if let Some(files) = add.wanted_files {
+                        app.status_message = format!("Fetching metadata of {} to apply the selection...", add.name);
+                        app.awaiting_metadata.push(gid.clone());
+                        app.wanted_files.push((gid, files));
+                    } else {
+                        app.status_message = format!("Added torrent: {} (GID: {})", add.name, gid);
+                    }
+                    app.mode = AppMode::Normal;
+                }
+                Err(e) => app.status_message = format!("Failed to add torrent: {}", e),
+            }
+        }
+
+        // Load the file list for the picker once nothing else has the screen
+        if app.can_open_file_picker() && let Some((gid, resume_after)) = app.picker_request.take() {
+            let loaded = match aria2.tell_status(&gid).await {
+                Ok(status) => aria2.get_files(&gid).await.map(|files| (status, files)),
+                Err(e) => Err(e),
+            };
+            match loaded {
+                Ok((status, files)) => app.open_file_picker(FilePicker::from_download(
+                    &gid,
+                    &files,
+                    status.dir.as_deref(),
+                    resume_after,
+                )),
+                Err(e) => app.status_message = format!("Could not load the file list: {}", e),
+            }
+        }
+
+        // Run download controls requested from the downloads pane
+        if let Some(action) = app.pending_action.take() {
+            app.status_message = match aria2.perform(&action).await {
+                Ok(message) => message,
+                Err(e) => format!("{} failed: {}", action.describe(), e),
+            };
+            // Reflect the change right away even if no notification follows (e.g. clearing a result)
+            if let Ok(downloads) = aria2.tell_all().await {
+                app.set_downloads(downloads);
+            }
+        }
+
+        // Concurrency changed with +/- in the queue view
+        if let Some(max) = app.pending_concurrency.take() {
+            app.status_message = match aria2.set_max_concurrent_downloads(max).await {
+                Ok(_) => {
+                    app.max_concurrent = Some(max);
+                    format!("Running up to {} downloads at once", max)
+                }
+                Err(e) => format!("Changing the concurrency limit failed: {}", e),
+            };
+        }
+
+        // Overall limits typed with 'l'
+        if let Some(limits) = app.pending_global_limits.take() {
+            app.status_message = match aria2.set_global_limits(&limits).await {
+                Ok(_) => {
+                    app.global_limits = aria2.global_limits().await.unwrap_or(limits);
+                    format!("Overall limits set to {}", app.global_limits)
+                }
+                Err(e) => format!("Setting limits failed: {}", e),
+            };
+        }
+
+        // Follow aria2's health and the bandwidth schedule as reported by the supervisor
+        while let Some(event) = supervisor.as_mut().and_then(Aria2Supervisor::try_next) {
+            match event {
+                SupervisorEvent::Disconnected(reason) => app.aria2_disconnected(reason),
+                SupervisorEvent::Restarting { attempt } => {
+                    app.status_message = format!("Restarting aria2c with the saved session (attempt {})...", attempt);
+                }
+                SupervisorEvent::Reconnected { rpc_url, secret } => {
+                    // A restarted aria2c can come back on a free port or as another tui-torrent's instance
+                    if aria2.endpoint() != rpc_url || aria2.secret() != secret.as_deref() {
+                        aria2 = Aria2Client::new(&rpc_url).with_secret(secret);
+                        aria2_events = Some(Aria2Events::spawn(&aria2.ws_url()));
+                    }
+                    app.aria2_reconnected();
+                    if let Ok(downloads) = aria2.tell_all().await {
+                        app.set_downloads(downloads);
+                    }
+                    if let Ok(limits) = aria2.global_limits().await {
+                        app.global_limits = limits;
+                    }
+                    app.max_concurrent = aria2.max_concurrent_downloads().await.ok();
+                    last_update = Instant::now();
+                }
+                SupervisorEvent::LimitsApplied(limits) => {
+                    app.global_limits = limits;
+                    app.status_message = format!("Bandwidth schedule: {}", limits);
+                }
+                SupervisorEvent::Error(e) => app.status_message = e,
+            }
+        }
+
+        // Keep the detail screen current: load it when opened, then refresh with the list
+        if let Some(gid) = app.detail_gid.clone()
+            && (app.detail.is_none() || last_detail_update.elapsed() >= Duration::from_secs(2))
+        {
+            last_detail_update = Instant::now();
+            match aria2.details(&gid).await {
+                Ok(details) => app.detail = Some(details),
+                Err(e) => {
+                    app.close_detail();
+                    app.status_message = format!("Could not load download details: {}", e);
+                }
+            }
+        }
+
+        // Apply pushed status changes as they arrive
+        while let Some(notification) = aria2_events.as_mut().and_then(Aria2Events::try_next) {
+            match notification {
+                Aria2Notification::Connected => {
+                    app.live_updates = true;
+                    // Catch up on anything that changed while disconnected
+                    if let Ok(downloads) = aria2.tell_all().await {
+                        app.set_downloads(downloads);
+                    }
+                    last_update = Instant::now();
+                }
+                Aria2Notification::Disconnected(_) => app.live_updates = false,
+                Aria2Notification::Download { gid, .. } => match aria2.tell_status(&gid).await {
+                    Ok(status) => app.update_download(status),
+                    Err(_) => app.remove_download(&gid),
+                },
+            }
+        }
+
+        // Poll every 2 seconds while the socket is down; with live updates only progress
+        // of running downloads needs refreshing, since state changes are pushed
+        // While aria2 is down the supervisor reports when it is back
+        let needs_poll = !app.live_updates || app.downloads.iter().any(|d| d.state() == DownloadState::Active);
+        if aria2_available && app.aria2_offline.is_none() && needs_poll && last_update.elapsed() >= Duration::from_secs(2) {
+            match aria2.tell_all().await {
+                Ok(downloads) => app.set_downloads(downloads),
+                Err(e) => app.status_message = format!("Refreshing downloads failed: {}", e),
+            }
             last_update = Instant::now();
         }
 
@@ -142,6 +412,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let current_tick_rate = if app.search_in_progress { loading_tick_rate } else { tick_rate };
         if last_tick.elapsed() >= current_tick_rate {
             app.update_loading_animation();
+            app.record_status();
             tui::render_ui(&mut terminal, &app)?;
             last_tick = Instant::now();
         }
@@ -151,8 +422,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     terminal::disable_raw_mode()?;
     crossterm::execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
 
-    // Clean up aria2 process
-    aria2_manager.stop();
+    // Save the session and let aria2c exit on its own so downloads resume next time
+    if let Some(supervisor) = supervisor {
+        if managed {
+            println!("💾 Saving the aria2 session...");
+        }
+        supervisor.shutdown().await;
+    }
 
     Ok(())
 }
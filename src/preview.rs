@@ -0,0 +1,99 @@
+use crate::api::TorrentFileEntry;
+use crate::aria2_client::Aria2Client;
+use crate::torrent_file::TorrentMetadata;
+use crate::torrent_search::{TorrentSearchEngine, TorrentSearchResult};
+use std::time::Duration;
+
+/// Extensions that have no business in a media torrent and often carry malware
+pub const SUSPICIOUS_EXTENSIONS: &[&str] = &[
+    "apk", "bat", "cmd", "com", "cpl", "exe", "hta", "jar", "js", "jse", "lnk", "msi", "pif", "ps1", "scr",
+    "vbe", "vbs", "wsf",
+];
+
+/// How long aria2 may look for peers that can send a magnet's metadata
+pub const METADATA_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub fn is_suspicious(path: &str) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, ext)| SUSPICIOUS_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// File list of a search result, fetched without downloading it
+#[derive(Debug, Clone)]
+pub struct TorrentPreview {
+    pub name: String,
+    /// Link handed to aria2 when downloading
+    pub uri: String,
+    pub files: Vec<TorrentFileEntry>,
+    /// Where the list came from, e.g. "PirateBay" or "torrent metadata"
+    pub origin: String,
+    /// Files are in the torrent's own order, so their positions are aria2's `select-file` indexes
+    pub exact: bool,
+}
+
+impl TorrentPreview {
+    /// Ask the result's provider first, then read the torrent's own metadata
+    ///
+    /// Links to `.torrent` files are fetched directly; magnets need aria2 to find peers.
+    pub async fn fetch(
+        engine: &TorrentSearchEngine,
+        aria2: Option<&Aria2Client>,
+        result: &TorrentSearchResult,
+    ) -> Result<Self, String> {
+        let provider_error = match engine.details(result).await {
+            Ok(details) if !details.files.is_empty() => {
+                return Ok(Self {
+                    name: result.name.clone(),
+                    uri: result.magnet_link.clone(),
+                    files: details.files,
+                    origin: result.source.clone(),
+                    exact: false,
+                });
+            }
+            Ok(_) => format!("{} listed no files", result.source),
+            Err(e) => e.to_string(),
+        };
+
+        let metadata = if result.magnet_link.starts_with("http://") || result.magnet_link.starts_with("https://") {
+            fetch_torrent_file(&result.magnet_link, engine.source_timeout()).await?
+        } else if let Some(aria2) = aria2 {
+            aria2
+                .fetch_metadata(&result.magnet_link, METADATA_TIMEOUT)
+                .await
+                .map_err(|e| e.to_string())?
+        } else {
+            return Err(format!("{}; reading magnet metadata needs aria2", provider_error));
+        };
+
+        Ok(Self {
+            name: metadata.name,
+            uri: result.magnet_link.clone(),
+            files: metadata.files,
+            origin: "torrent metadata".to_string(),
+            exact: true,
+        })
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().filter_map(|f| f.size_bytes).sum()
+    }
+
+    pub fn suspicious_files(&self) -> Vec<&TorrentFileEntry> {
+        self.files.iter().filter(|f| is_suspicious(&f.path)).collect()
+    }
+}
+
+/// Download a `.torrent` file, giving up after the same timeout as the search sources
+async fn fetch_torrent_file(url: &str, timeout: Duration) -> Result<TorrentMetadata, String> {
+    let client = reqwest::Client::builder()
+        .user_agent("TUI-Torrent/1.0")
+        .timeout(timeout)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()));
+    }
+    let data = response.bytes().await.map_err(|e| e.to_string())?;
+    TorrentMetadata::from_bytes(&data)
+}
@@ -0,0 +1,221 @@
+use crate::torrent_search::TorrentSearchResult;
+use crate::utils::parse_size;
+use chrono::NaiveDate;
+use std::fmt;
+
+/// A search box input split into the text sent to providers and a filter over the results
+///
+/// Supported filters, mixed freely with search terms:
+/// `size:<4GB`, `seeders:>20`, `leechers:<=5`, `source:piratebay`, `-source:1337x`,
+/// `quality:1080p`, `after:2024-01-01`, `before:2024-06-30`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedQuery {
+    pub text: String,
+    pub filter: SearchFilter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn holds<T: PartialOrd>(self, actual: T, expected: T) -> bool {
+        match self {
+            Comparison::Less => actual < expected,
+            Comparison::LessOrEqual => actual <= expected,
+            Comparison::Equal => actual == expected,
+            Comparison::GreaterOrEqual => actual >= expected,
+            Comparison::Greater => actual > expected,
+        }
+    }
+
+    /// Split a leading operator off a filter value, defaulting to equality
+    fn split(value: &str) -> (Comparison, &str) {
+        for (prefix, op) in [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ] {
+            if let Some(rest) = value.strip_prefix(prefix) {
+                return (op, rest);
+            }
+        }
+        (Comparison::Equal, value)
+    }
+}
+
+/// Predicate applied to merged search results
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchFilter {
+    pub size: Vec<(Comparison, u64)>,
+    pub seeders: Vec<(Comparison, u32)>,
+    pub leechers: Vec<(Comparison, u32)>,
+    /// Lowercase source names; empty means every source
+    pub include_sources: Vec<String>,
+    pub exclude_sources: Vec<String>,
+    /// Lowercase quality tags that must appear in the name, e.g. "1080p"
+    pub quality: Vec<String>,
+    pub after: Option<NaiveDate>,
+    pub before: Option<NaiveDate>,
+}
+
+impl SearchFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == SearchFilter::default()
+    }
+
+    /// Whether results from the named source can pass this filter at all
+    pub fn allows_source(&self, source: &str) -> bool {
+        let source = source.to_lowercase();
+        (self.include_sources.is_empty() || self.include_sources.contains(&source))
+            && !self.exclude_sources.contains(&source)
+    }
+
+    pub fn matches(&self, result: &TorrentSearchResult) -> bool {
+        // A merged entry stays visible as long as one of its sources is allowed
+        let sources_ok = if result.sources.is_empty() {
+            self.allows_source(&result.source)
+        } else {
+            result.sources.iter().any(|s| self.allows_source(s))
+        };
+        if !sources_ok {
+            return false;
+        }
+
+        if !self.size.is_empty() {
+            match result.size_bytes {
+                Some(bytes) if self.size.iter().all(|&(op, v)| op.holds(bytes, v)) => {}
+                _ => return false,
+            }
+        }
+        if !self.seeders.iter().all(|&(op, v)| op.holds(result.seeders, v))
+            || !self.leechers.iter().all(|&(op, v)| op.holds(result.leechers, v))
+        {
+            return false;
+        }
+
+        let name = result.name.to_lowercase();
+        if !self.quality.iter().all(|q| name.contains(q.as_str())) {
+            return false;
+        }
+
+        if self.after.is_some() || self.before.is_some() {
+            let Some(uploaded) = result.uploaded_at.map(|at| at.date_naive()) else {
+                return false;
+            };
+            if self.after.is_some_and(|after| uploaded < after)
+                || self.before.is_some_and(|before| uploaded > before)
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// Only filters were given, nothing to send to the providers
+    MissingSearchTerms,
+    InvalidValue { filter: String, value: String },
+    /// `-` prefix on a filter that cannot be negated
+    UnsupportedNegation(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::MissingSearchTerms => write!(f, "add some search terms next to the filters"),
+            QueryError::InvalidValue { filter, value } => write!(f, "invalid value '{}' for {}:", value, filter),
+            QueryError::UnsupportedNegation(filter) => write!(f, "{}: cannot be negated", filter),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// Parse search box input into provider text and a result filter
+pub fn parse_query(input: &str) -> Result<ParsedQuery, QueryError> {
+    let mut text = Vec::new();
+    let mut filter = SearchFilter::default();
+
+    for token in input.split_whitespace() {
+        let (negated, body) = match token.strip_prefix('-') {
+            Some(rest) if rest.contains(':') => (true, rest),
+            _ => (false, token),
+        };
+
+        let Some((key, value)) = body.split_once(':') else {
+            text.push(token);
+            continue;
+        };
+        let key = key.to_lowercase();
+        let invalid = || QueryError::InvalidValue {
+            filter: key.clone(),
+            value: value.to_string(),
+        };
+
+        match key.as_str() {
+            "source" => {
+                if value.is_empty() {
+                    return Err(invalid());
+                }
+                let source = value.to_lowercase();
+                if negated {
+                    filter.exclude_sources.push(source);
+                } else {
+                    filter.include_sources.push(source);
+                }
+            }
+            "size" | "seeders" | "leechers" | "quality" | "after" | "before" if negated => {
+                return Err(QueryError::UnsupportedNegation(key));
+            }
+            "size" => {
+                let (op, amount) = Comparison::split(value);
+                filter.size.push((op, parse_size(amount).ok_or_else(invalid)?));
+            }
+            "seeders" | "leechers" => {
+                let (op, amount) = Comparison::split(value);
+                let amount = amount.parse().map_err(|_| invalid())?;
+                if key == "seeders" {
+                    filter.seeders.push((op, amount));
+                } else {
+                    filter.leechers.push((op, amount));
+                }
+            }
+            "quality" => {
+                if value.is_empty() {
+                    return Err(invalid());
+                }
+                filter.quality.push(value.to_lowercase());
+            }
+            "after" | "before" => {
+                let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| invalid())?;
+                if key == "after" {
+                    filter.after = Some(date);
+                } else {
+                    filter.before = Some(date);
+                }
+            }
+            // Not a filter, e.g. a title like "Re:Zero"
+            _ => text.push(token),
+        }
+    }
+
+    if text.is_empty() && filter != SearchFilter::default() {
+        return Err(QueryError::MissingSearchTerms);
+    }
+
+    Ok(ParsedQuery {
+        text: text.join(" "),
+        filter,
+    })
+}
@@ -0,0 +1,147 @@
+use crate::api::TorrentFileEntry;
+use std::collections::BTreeMap;
+
+/// Decoded bencode value, as found in `.torrent` files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bencode {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Bencode>),
+    Dict(BTreeMap<Vec<u8>, Bencode>),
+}
+
+impl Bencode {
+    /// Decode a complete bencoded document; trailing bytes are an error
+    pub fn decode(data: &[u8]) -> Result<Self, String> {
+        let (value, rest) = decode_value(data, 0)?;
+        if !rest.is_empty() {
+            return Err(format!("{} unexpected bytes after the end of the data", rest.len()));
+        }
+        Ok(value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Bencode> {
+        match self {
+            Bencode::Dict(entries) => entries.get(key.as_bytes()),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Bencode::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Byte string as text, replacing invalid UTF-8
+    pub fn as_str(&self) -> Option<String> {
+        match self {
+            Bencode::Bytes(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Bencode]> {
+        match self {
+            Bencode::List(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+// Nesting deeper than this is not a real torrent and would only exhaust the stack
+const MAX_DEPTH: usize = 64;
+
+fn decode_value(data: &[u8], depth: usize) -> Result<(Bencode, &[u8]), String> {
+    if depth > MAX_DEPTH {
+        return Err("bencode nested too deeply".to_string());
+    }
+    match data.first() {
+        Some(b'i') => {
+            let end = data.iter().position(|&b| b == b'e').ok_or("unterminated integer")?;
+            let text = std::str::from_utf8(&data[1..end]).map_err(|_| "invalid integer")?;
+            let value = text.parse().map_err(|_| format!("invalid integer '{}'", text))?;
+            Ok((Bencode::Int(value), &data[end + 1..]))
+        }
+        Some(b'l') => {
+            let mut items = Vec::new();
+            let mut rest = &data[1..];
+            while rest.first() != Some(&b'e') {
+                let (item, next) = decode_value(rest, depth + 1)?;
+                items.push(item);
+                rest = next;
+            }
+            Ok((Bencode::List(items), &rest[1..]))
+        }
+        Some(b'd') => {
+            let mut entries = BTreeMap::new();
+            let mut rest = &data[1..];
+            while rest.first() != Some(&b'e') {
+                let (key, next) = decode_value(rest, depth + 1)?;
+                let Bencode::Bytes(key) = key else {
+                    return Err("dictionary key is not a string".to_string());
+                };
+                let (value, next) = decode_value(next, depth + 1)?;
+                entries.insert(key, value);
+                rest = next;
+            }
+            Ok((Bencode::Dict(entries), &rest[1..]))
+        }
+        Some(b'0'..=b'9') => {
+            let colon = data.iter().position(|&b| b == b':').ok_or("unterminated string length")?;
+            let len: usize = std::str::from_utf8(&data[..colon])
+                .ok()
+                .and_then(|text| text.parse().ok())
+                .ok_or("invalid string length")?;
+            let start = colon + 1;
+            // The length comes from untrusted data and may be anything up to usize::MAX
+            let end = start.checked_add(len).ok_or("string length is too large")?;
+            let bytes = data.get(start..end).ok_or("string runs past the end of the data")?;
+            Ok((Bencode::Bytes(bytes.to_vec()), &data[end..]))
+        }
+        Some(&other) => Err(format!("unexpected byte 0x{:02x}", other)),
+        None => Err("unexpected end of data".to_string()),
+    }
+}
+
+/// Name and files of a torrent, in the order aria2 numbers them for `select-file`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TorrentMetadata {
+    pub name: String,
+    pub files: Vec<TorrentFileEntry>,
+}
+
+impl TorrentMetadata {
+    /// Read the `info` dictionary of a `.torrent` file; multi-file paths are prefixed with the torrent name
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        let torrent = Bencode::decode(data)?;
+        let info = torrent.get("info").ok_or("no info dictionary")?;
+        let name = info.get("name").and_then(Bencode::as_str).ok_or("torrent has no name")?;
+
+        let files = match info.get("files").and_then(Bencode::as_list) {
+            Some(files) => files
+                .iter()
+                .map(|file| {
+                    let parts: Vec<String> = file
+                        .get("path")
+                        .and_then(Bencode::as_list)
+                        .ok_or("file without a path")?
+                        .iter()
+                        .filter_map(Bencode::as_str)
+                        .collect();
+                    Ok(TorrentFileEntry {
+                        path: format!("{}/{}", name, parts.join("/")),
+                        size_bytes: file.get("length").and_then(Bencode::as_int).map(|len| len.max(0) as u64),
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()?,
+            None => vec![TorrentFileEntry {
+                path: name.clone(),
+                size_bytes: info.get("length").and_then(Bencode::as_int).map(|len| len.max(0) as u64),
+            }],
+        };
+
+        Ok(Self { name, files })
+    }
+}
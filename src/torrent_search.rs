@@ -1,68 +1,380 @@
-use crate::api::{X1337Client, YtsClient, PirateBayClient};
+use crate::api::{PirateBayClient, SearchRequest, SourceResult, TorrentDetails, TorrentSource, TorznabClient, X1337Client, YtsClient};
+use crate::config::Config;
+use crate::magnet;
+use crate::types::Category;
+use crate::utils::parse_size;
+use chrono::{DateTime, Utc};
+use futures::stream::{FuturesUnordered, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio::time::{timeout, Duration};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Default time a single source may take before it is reported as timed out
+pub const SOURCE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Default maximum number of merged results kept for display
+pub const MAX_RESULTS: usize = 50;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct TorrentSearchResult {
     pub name: String,
     pub size: String,
     pub seeders: u32,
     pub leechers: u32,
     pub magnet_link: String,
+    /// Source that produced this entry
+    pub source: String,
+    /// Lowercase hex info-hash, used to spot the same release across sources
+    pub info_hash: Option<String>,
+    /// Every source that returned this release, once duplicates are merged
+    pub sources: Vec<String>,
+    /// Size in bytes, parsed from the provider's size field
+    pub size_bytes: Option<u64>,
+    pub uploaded_at: Option<DateTime<Utc>>,
+    pub category: Option<Category>,
+    pub uploader: Option<String>,
+    pub file_count: Option<u32>,
+    /// Provider page or API URL describing this result, used by `TorrentSource::details`
+    pub details_url: Option<String>,
+}
+
+impl TorrentSearchResult {
+    /// Fill in the info-hash and source list if the provider left them empty
+    pub fn normalize(&mut self) {
+        if self.info_hash.is_none() {
+            self.info_hash = magnet::info_hash(&self.magnet_link);
+        }
+        if self.sources.is_empty() {
+            self.sources.push(self.source.clone());
+        }
+        if self.size_bytes.is_none() {
+            self.size_bytes = parse_size(&self.size);
+        }
+    }
+
+    /// Fold a duplicate of the same release into this entry
+    pub fn absorb(&mut self, other: TorrentSearchResult) {
+        self.seeders = self.seeders.max(other.seeders);
+        self.leechers = self.leechers.max(other.leechers);
+        for source in other.sources {
+            if !self.sources.contains(&source) {
+                self.sources.push(source);
+            }
+        }
+        self.magnet_link = magnet::with_trackers(&self.magnet_link, &magnet::trackers(&other.magnet_link));
+        self.size_bytes = self.size_bytes.or(other.size_bytes);
+        self.uploaded_at = self.uploaded_at.or(other.uploaded_at);
+        self.category = self.category.take().or(other.category);
+        self.uploader = self.uploader.take().or(other.uploader);
+        self.file_count = self.file_count.or(other.file_count);
+    }
+}
+
+/// Merge results sharing an info-hash into a single entry, keeping first-seen order
+pub fn merge_duplicates(results: Vec<TorrentSearchResult>) -> Vec<TorrentSearchResult> {
+    let mut merged: Vec<TorrentSearchResult> = Vec::with_capacity(results.len());
+    let mut by_hash: HashMap<String, usize> = HashMap::new();
+
+    for mut result in results {
+        result.normalize();
+        let existing = result
+            .info_hash
+            .as_ref()
+            .and_then(|hash| by_hash.get(hash).copied());
+        match existing {
+            Some(index) => merged[index].absorb(result),
+            None => {
+                if let Some(hash) = &result.info_hash {
+                    by_hash.insert(hash.clone(), merged.len());
+                }
+                merged.push(result);
+            }
+        }
+    }
+
+    merged
+}
+
+/// Progress of a single source during a search
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceState {
+    Pending,
+    /// Finished with the given number of results
+    Done(usize),
+    Failed(String),
+    TimedOut,
+}
+
+/// Outcome of one source, emitted as soon as that source answers
+#[derive(Debug)]
+pub struct SourceUpdate {
+    /// Position of the source among `sources_for(request)`; names need not be unique
+    pub index: usize,
     pub source: String,
+    pub outcome: Result<Vec<TorrentSearchResult>, SourceState>,
 }
 
-#[derive(Clone)]
+impl SourceUpdate {
+    /// State to display for the source this update belongs to
+    pub fn state(&self) -> SourceState {
+        match &self.outcome {
+            Ok(results) => SourceState::Done(results.len()),
+            Err(state) => state.clone(),
+        }
+    }
+}
+
+/// A search running on a background task
+///
+/// Dropping the handle cancels the search just like `cancel` does.
+#[derive(Debug)]
+pub struct SearchHandle {
+    updates: mpsc::UnboundedReceiver<SourceUpdate>,
+    task: JoinHandle<()>,
+}
+
+impl SearchHandle {
+    /// Next update that has already arrived, without waiting
+    pub fn try_next(&mut self) -> Option<SourceUpdate> {
+        self.updates.try_recv().ok()
+    }
+
+    /// Wait for the next update; `None` once every source has answered or the search was cancelled
+    pub async fn next(&mut self) -> Option<SourceUpdate> {
+        self.updates.recv().await
+    }
+
+    /// Abort the search, dropping every in-flight provider request
+    pub fn cancel(&self) {
+        self.task.abort();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl Drop for SearchHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Base URLs for the built-in providers, each tried in order; an empty list keeps the provider's defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MirrorConfig {
+    pub yts: Vec<String>,
+    pub piratebay: Vec<String>,
+    pub x1337: Vec<String>,
+}
+
+/// Multi-source search engine backed by a registry of `TorrentSource`s
 pub struct TorrentSearchEngine {
-    x1337_client: X1337Client,
-    yts_client: YtsClient,
-    piratebay_client: PirateBayClient,
+    sources: Vec<Box<dyn TorrentSource>>,
+    source_timeout: Duration,
+    max_results: usize,
 }
 
 impl TorrentSearchEngine {
+    /// Engine with the built-in YTS, PirateBay and 1337x sources registered
     pub fn new() -> Self {
+        Self::with_mirrors(&MirrorConfig::default())
+    }
+
+    /// Engine with the built-in sources pointed at the given mirrors
+    pub fn with_mirrors(mirrors: &MirrorConfig) -> Self {
+        let mut config = Config::default();
+        config.providers.yts.mirrors = mirrors.yts.clone();
+        config.providers.piratebay.mirrors = mirrors.piratebay.clone();
+        config.providers.x1337.mirrors = mirrors.x1337.clone();
+        Self::from_config(&config)
+    }
+
+    /// Engine without any sources registered
+    pub fn empty() -> Self {
         Self {
-            x1337_client: X1337Client::new(),
-            yts_client: YtsClient::new(),
-            piratebay_client: PirateBayClient::new(),
+            sources: Vec::new(),
+            source_timeout: SOURCE_TIMEOUT,
+            max_results: MAX_RESULTS,
         }
     }
 
-    pub async fn search_torrents(&self, query: &str, category: Option<&str>) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
-        let mut all_results = Vec::new();
+    /// Engine built from the `[search]` and `[providers]` sections of the config file
+    pub fn from_config(config: &Config) -> Self {
+        let providers = &config.providers;
+        let mut engine = Self::empty()
+            .with_source_timeout(Duration::from_secs(config.search.source_timeout_secs))
+            .with_max_results(config.search.max_results);
 
-        // Search YTS (movies)
-        if let Ok(Ok(mut results)) = timeout(Duration::from_secs(15), self.search_yts(query)).await {
-            all_results.append(&mut results);
+        if providers.yts.enabled {
+            let mut client = if providers.yts.mirrors.is_empty() {
+                YtsClient::new()
+            } else {
+                YtsClient::with_mirrors(providers.yts.mirrors.clone())
+            };
+            if let Some(trackers) = &providers.yts.trackers {
+                client = client.with_trackers(trackers.clone());
+            }
+            engine.register(Box::new(client));
         }
-
-        // Search PirateBay
-        if let Ok(Ok(mut results)) = timeout(Duration::from_secs(15), self.search_piratebay(query, category)).await {
-            all_results.append(&mut results);
+        if providers.piratebay.enabled {
+            let mut client = if providers.piratebay.mirrors.is_empty() {
+                PirateBayClient::new()
+            } else {
+                PirateBayClient::with_mirrors(providers.piratebay.mirrors.clone())
+            };
+            if let Some(trackers) = &providers.piratebay.trackers {
+                client = client.with_trackers(trackers.clone());
+            }
+            engine.register(Box::new(client));
+        }
+        if providers.x1337.enabled {
+            engine.register(Box::new(if providers.x1337.mirrors.is_empty() {
+                X1337Client::new()
+            } else {
+                X1337Client::with_mirrors(providers.x1337.mirrors.clone())
+            }));
         }
+        for indexer in &providers.torznab {
+            engine.register(Box::new(
+                TorznabClient::new(&indexer.url, indexer.api_key.as_deref()).with_name(&indexer.name),
+            ));
+        }
+
+        engine
+    }
+
+    pub fn with_source_timeout(mut self, source_timeout: Duration) -> Self {
+        self.source_timeout = source_timeout;
+        self
+    }
+
+    pub fn with_max_results(mut self, max_results: usize) -> Self {
+        self.max_results = max_results;
+        self
+    }
+
+    pub fn max_results(&self) -> usize {
+        self.max_results
+    }
+
+    pub fn source_timeout(&self) -> Duration {
+        self.source_timeout
+    }
+
+    /// Add a source; sources are queried in registration order
+    pub fn register(&mut self, source: Box<dyn TorrentSource>) {
+        self.sources.push(source);
+    }
+
+    pub fn with_source(mut self, source: Box<dyn TorrentSource>) -> Self {
+        self.register(source);
+        self
+    }
 
-        // Search 1337x
-        if let Ok(Ok(mut results)) = timeout(Duration::from_secs(15), self.search_1337x(query, category)).await {
-            all_results.append(&mut results);
+    /// Run every source's one-off setup concurrently; failures are left for the searches to report
+    ///
+    /// Each source gets the search timeout, so an unreachable indexer cannot hold up startup.
+    pub async fn prepare(&self) {
+        futures::future::join_all(self.sources().map(|source| timeout(self.source_timeout, source.prepare()))).await;
+    }
+
+    pub fn sources(&self) -> impl Iterator<Item = &dyn TorrentSource> {
+        self.sources.iter().map(|s| s.as_ref())
+    }
+
+    pub fn source(&self, name: &str) -> Option<&dyn TorrentSource> {
+        self.sources().find(|s| s.name() == name)
+    }
+
+    /// Ask the source that produced a result for its details, within the per-source timeout
+    pub async fn details(&self, result: &TorrentSearchResult) -> SourceResult<TorrentDetails> {
+        let source = self
+            .source(&result.source)
+            .filter(|source| source.capabilities().details)
+            .ok_or_else(|| format!("{} has no detail lookup", result.source))?;
+        match timeout(self.source_timeout, source.details(result)).await {
+            Ok(outcome) => outcome,
+            Err(_) => Err(format!("{} timed out", result.source).into()),
         }
+    }
 
-        // Sort by seeders (descending) and limit results
-        all_results.sort_by(|a, b| b.seeders.cmp(&a.seeders));
-        all_results.truncate(50);
-        
-        Ok(all_results)
+    pub async fn search_torrents(&self, query: &str, category: Option<Category>) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
+        self.search(&SearchRequest::new(query).with_category(category)).await
     }
 
-    async fn search_yts(&self, query: &str) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
-        self.yts_client.search(query, Some(20)).await
+    /// Sources that take part in the given request
+    pub fn sources_for<'a>(&'a self, request: &'a SearchRequest) -> impl Iterator<Item = &'a dyn TorrentSource> + 'a {
+        self.sources()
+            .filter(move |s| match &request.sources {
+                Some(names) => names.iter().any(|name| name == s.name()),
+                None => true,
+            })
+            // Sources without paging have nothing beyond the first page
+            .filter(move |s| request.page <= 1 || s.capabilities().paging)
+            // A source that cannot serve the category would only return unrelated results
+            .filter(move |s| request.category.is_none_or(|c| s.supports_category(c)))
     }
 
-    async fn search_piratebay(&self, query: &str, _category: Option<&str>) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
-        self.piratebay_client.search(query, None).await
+    /// Query all participating sources concurrently, yielding each one's outcome as it arrives
+    pub fn search_stream<'a>(&'a self, request: &'a SearchRequest) -> impl Stream<Item = SourceUpdate> + 'a {
+        self.sources_for(request)
+            .enumerate()
+            .map(|(index, source)| async move {
+                let outcome = match timeout(self.source_timeout, source.search(request)).await {
+                    Ok(Ok(mut results)) => {
+                        results.iter_mut().for_each(TorrentSearchResult::normalize);
+                        Ok(results)
+                    }
+                    Ok(Err(e)) => Err(SourceState::Failed(e.to_string())),
+                    Err(_) => Err(SourceState::TimedOut),
+                };
+                SourceUpdate {
+                    index,
+                    source: source.name().to_string(),
+                    outcome,
+                }
+            })
+            .collect::<FuturesUnordered<_>>()
     }
 
-    async fn search_1337x(&self, query: &str, category: Option<&str>) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
-        self.x1337_client.search(query, category).await
+    /// Run the search on a background task; the returned handle receives updates and can cancel it
+    pub fn spawn_search(self: &Arc<Self>, request: SearchRequest) -> SearchHandle {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let engine = Arc::clone(self);
+
+        let task = tokio::spawn(async move {
+            let mut updates = engine.search_stream(&request);
+            while let Some(update) = updates.next().await {
+                if tx.send(update).is_err() {
+                    // Receiver gone, nobody is interested any more
+                    break;
+                }
+            }
+        });
+
+        SearchHandle { updates: rx, task }
+    }
+
+    pub async fn search(&self, request: &SearchRequest) -> Result<Vec<TorrentSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut all_results = Vec::new();
+
+        let mut updates = self.search_stream(request);
+        while let Some(update) = updates.next().await {
+            if let Ok(mut results) = update.outcome {
+                all_results.append(&mut results);
+            }
+        }
+
+        // Collapse the same release found on several sources, then sort by seeders (descending) and limit results
+        let mut all_results = merge_duplicates(all_results);
+        all_results.sort_by_key(|r| std::cmp::Reverse(r.seeders));
+        all_results.truncate(self.max_results);
+        
+        Ok(all_results)
     }
 }
 
@@ -77,25 +389,3 @@ pub async fn search_torrents(query: &str) -> Result<Vec<TorrentSearchResult>, Bo
     let engine = TorrentSearchEngine::new();
     engine.search_torrents(query, None).await
 }
-
-// Function to add a torrent to aria2 via the RPC API
-pub async fn add_torrent(magnet_link: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-    let client = reqwest::Client::new();
-    let payload = serde_json::json!({
-        "jsonrpc": "2.0",
-        "method": "aria2.addUri",
-        "id": "1",
-        "params": [[magnet_link]]
-    });
-
-    let res = client
-        .post("http://localhost:6800/jsonrpc")
-        .json(&payload)
-        .send()
-        .await?;
-
-    let json: serde_json::Value = res.json().await?;
-    let gid = json["result"].as_str().unwrap_or("unknown").to_string();
-    
-    Ok(gid)
-}
@@ -1,11 +1,16 @@
-use crate::app::{App, AppMode};
-use crate::utils::{format_bytes, format_speed};
+use crate::app::{App, AppMode, DetailTab};
+use crate::file_picker::{PickerRowKind, PickerTarget};
+use crate::log_buffer::{LogLevel, LogSource};
+use crate::preview::is_suspicious;
+use crate::torrent_search::SourceState;
+use crate::types::DownloadState;
+use crate::utils::{format_bytes, format_duration, format_speed};
 use ratatui::{
-    Terminal,
-    layout::{Constraint, Direction, Layout, Alignment},
+    Frame, Terminal,
+    layout::{Constraint, Direction, Layout, Alignment, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Clear},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Clear},
 };
 use std::io::Result;
 
@@ -34,25 +39,199 @@ pub fn render_ui<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app:
                     Span::raw(" to search, "),
                     Span::styled("↑↓/jk", Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow)),
                     Span::raw(" to navigate, "),
+                    Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" details, "),
+                    Span::styled("o", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" files, "),
+                    Span::styled("f", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" to filter, "),
+                    Span::styled("l/L", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" limits (all/one), "),
+                    Span::styled("w", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" queue, "),
+                    Span::styled("v", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" log, "),
+                    Span::styled("p", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" pause/resume, "),
+                    Span::styled("x/X", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" remove (+files), "),
+                    Span::styled("r", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" retry, "),
                     Span::styled("q", Style::default().add_modifier(Modifier::BOLD).fg(Color::Red)),
                     Span::raw(" to quit"),
                 ]))
                 .block(Block::default().title("🏴‍☠️ TUI Torrent").borders(Borders::ALL));
                 f.render_widget(instructions, chunks[0]);
             },
+            AppMode::Detail => {
+                let name = app.detail.as_ref().map(|d| d.status.name()).unwrap_or_else(|| "Loading…".to_string());
+                let tabs: Vec<Span> = DetailTab::ALL
+                    .iter()
+                    .flat_map(|&tab| {
+                        let style = if tab == app.detail_tab {
+                            Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow)
+                        } else {
+                            Style::default().fg(Color::Gray)
+                        };
+                        [Span::styled(format!("[{}]", tab.label()), style), Span::raw(" ")]
+                    })
+                    .chain([
+                        Span::raw(" "),
+                        Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                        Span::raw(" switch, "),
+                        Span::styled("o", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                        Span::raw(" choose files, "),
+                        Span::styled("↑↓/jk", Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow)),
+                        Span::raw(" scroll, "),
+                        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD).fg(Color::Red)),
+                        Span::raw(" back"),
+                    ])
+                    .collect();
+                let header = Paragraph::new(Line::from(tabs))
+                    .block(Block::default().title(format!("🔎 {}", name)).borders(Borders::ALL));
+                f.render_widget(header, chunks[0]);
+            },
+            AppMode::Confirm => {
+                let prompt = app
+                    .confirm_action
+                    .as_ref()
+                    .map(|action| match app.downloads.iter().find(|d| d.gid == action.gid()) {
+                        Some(download) => format!("{} ({})", action.describe(), download.name()),
+                        None => action.describe(),
+                    })
+                    .unwrap_or_default();
+                let confirm = Paragraph::new(Line::from(vec![
+                    Span::styled(format!("{}? ", prompt), Style::default().fg(Color::LightRed)),
+                    Span::styled("y", Style::default().add_modifier(Modifier::BOLD).fg(Color::Green)),
+                    Span::raw(" to confirm, "),
+                    Span::styled("n/Esc", Style::default().add_modifier(Modifier::BOLD).fg(Color::Red)),
+                    Span::raw(" to cancel"),
+                ]))
+                .block(Block::default().title("⚠️  Confirm").borders(Borders::ALL));
+                f.render_widget(confirm, chunks[0]);
+            },
+            AppMode::FilePicker => {
+                let title = match &app.file_picker {
+                    Some(picker) => format!(
+                        "🗂  Select files ({} of {}, {})",
+                        picker.selected_count(),
+                        picker.files.len(),
+                        format_bytes(picker.selected_size())
+                    ),
+                    None => "🗂  Select files".to_string(),
+                };
+                let keys = Paragraph::new(Line::from(vec![
+                    Span::styled("Space", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" toggle, "),
+                    Span::styled("a", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" all/none, "),
+                    Span::styled("↑↓/jk", Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow)),
+                    Span::raw(" to navigate, "),
+                    Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD).fg(Color::Green)),
+                    Span::raw(" apply, "),
+                    Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD).fg(Color::Red)),
+                    Span::raw(" cancel"),
+                ]))
+                .block(Block::default().title(title).borders(Borders::ALL));
+                f.render_widget(keys, chunks[0]);
+            },
+            AppMode::Log => {
+                let source = app.log_source.map(LogSource::label).unwrap_or("all");
+                let line = if app.log_filter_typing {
+                    Line::from(vec![
+                        Span::styled(format!("Filter: {}", app.log_filter), Style::default().fg(Color::Yellow)),
+                        Span::styled("  Enter to keep, Esc to clear", Style::default().fg(Color::Gray)),
+                    ])
+                } else {
+                    Line::from(vec![
+                        Span::styled("/", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                        Span::raw(" filter, "),
+                        Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                        Span::raw(" source, "),
+                        Span::styled("↑↓/jk", Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow)),
+                        Span::raw(" scroll, "),
+                        Span::styled("G", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                        Span::raw(" newest, "),
+                        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD).fg(Color::Red)),
+                        Span::raw(" back"),
+                    ])
+                };
+                let title = if app.log_filter.is_empty() {
+                    format!("📜 Log [{}]", source)
+                } else {
+                    format!("📜 Log [{}] matching \"{}\"", source, app.log_filter)
+                };
+                let header = Paragraph::new(line).block(Block::default().title(title).borders(Borders::ALL));
+                f.render_widget(header, chunks[0]);
+            },
+            AppMode::Queue => {
+                let slots = app.max_concurrent.map(|max| max.to_string()).unwrap_or_else(|| "?".to_string());
+                let keys = Paragraph::new(Line::from(vec![
+                    Span::styled("K/J", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" move up/down, "),
+                    Span::styled("t/b", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" to top/bottom, "),
+                    Span::styled("f", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" force start, "),
+                    Span::styled("p", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" pause/resume, "),
+                    Span::styled("+/-", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" concurrency, "),
+                    Span::styled("↑↓/jk", Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow)),
+                    Span::raw(" to navigate, "),
+                    Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD).fg(Color::Red)),
+                    Span::raw(" back"),
+                ]))
+                .block(Block::default().title(format!("⏳ Queue ({} at a time)", slots)).borders(Borders::ALL));
+                f.render_widget(keys, chunks[0]);
+            },
+            AppMode::LimitInput => {
+                let target = match &app.limit_gid {
+                    Some(gid) => app
+                        .downloads
+                        .iter()
+                        .find(|d| d.gid == *gid)
+                        .map(|d| d.name())
+                        .unwrap_or_else(|| gid.clone()),
+                    None => "all downloads".to_string(),
+                };
+                let input = Paragraph::new(Line::from(vec![
+                    Span::styled(format!("Limits: {}", app.limit_input), Style::default().fg(Color::Yellow)),
+                    Span::styled(
+                        "  <download> [<upload>], e.g. 500K 50K · 0 = unlimited · - = unchanged",
+                        Style::default().fg(Color::Gray),
+                    ),
+                ]))
+                .block(Block::default().title(format!("🚦 Speed limits for {} (Enter to apply, Esc to cancel)", target)).borders(Borders::ALL));
+                f.render_widget(input, chunks[0]);
+            },
             AppMode::Search => {
-                let search_text = format!("Search: {}", app.search_query);
+                let category = app.selected_category.map(|c| c.label()).unwrap_or("All");
+                let search_text = Line::from(vec![
+                    Span::styled(format!("[{}] ", category), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                    Span::styled(format!("Search: {}", app.search_query), Style::default().fg(Color::Yellow)),
+                ]);
                 let search_bar = Paragraph::new(search_text)
-                    .style(Style::default().fg(Color::Yellow))
-                    .block(Block::default().title("🔍 Enter Search Query (Press Enter to search, Esc to cancel)").borders(Borders::ALL));
+                    .block(Block::default().title("🔍 Enter Search Query (Enter to search, Tab to change category, Esc to cancel)").borders(Borders::ALL));
                 f.render_widget(search_bar, chunks[0]);
             },
             AppMode::Searching => {
-                let searching_text = format!("Searching for: {}", app.search_query);
                 let loading_indicator = app.get_loading_indicator();
+                let mut spans = vec![
+                    Span::styled(format!("Searching for: {}", app.search_query), Style::default().fg(Color::LightYellow)),
+                    Span::raw("  "),
+                ];
+                for (name, state) in &app.source_states {
+                    let (label, color) = match state {
+                        SourceState::Pending => (format!("{} {}", name, loading_indicator), Color::Blue),
+                        SourceState::Done(count) => (format!("{} ✓ {}", name, count), Color::Green),
+                        SourceState::Failed(_) => (format!("{} ✗ failed", name), Color::Red),
+                        SourceState::TimedOut => (format!("{} ⏱ timed out", name), Color::Yellow),
+                    };
+                    spans.push(Span::styled(format!("[{}] ", label), Style::default().fg(color)));
+                }
                 let title = format!("{} Searching Multiple Sources...", loading_indicator);
-                let search_bar = Paragraph::new(searching_text)
-                    .style(Style::default().fg(Color::LightYellow))
+                let search_bar = Paragraph::new(Line::from(spans))
                     .block(Block::default().title(title).borders(Borders::ALL));
                 f.render_widget(search_bar, chunks[0]);
             },
@@ -61,6 +240,10 @@ pub fn render_ui<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app:
                     Span::raw("Press "),
                     Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD).fg(Color::Green)),
                     Span::raw(" to download, "),
+                    Span::styled("o", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" to choose files, "),
+                    Span::styled("i", Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                    Span::raw(" to preview, "),
                     Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD).fg(Color::Red)),
                     Span::raw(" to go back, "),
                     Span::styled("↑↓/jk", Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow)),
@@ -74,6 +257,8 @@ pub fn render_ui<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app:
         // Status bar
         let status_color = if app.search_in_progress {
             Color::Blue
+        } else if app.aria2_offline.is_some() {
+            Color::Yellow
         } else if app.status_message.contains("failed") || app.status_message.contains("error") {
             Color::Red
         } else {
@@ -94,58 +279,17 @@ pub fn render_ui<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app:
 
     // Render main content based on app mode
         match app.mode {
-            AppMode::Normal | AppMode::Search => {
-                if app.active_downloads.is_empty() {
-                    let empty_msg = Paragraph::new("No active downloads. Press 's' to search for torrents.")
-                        .style(Style::default().fg(Color::Gray))
-                        .alignment(Alignment::Center)
-                        .block(Block::default().title("📥 Active Downloads").borders(Borders::ALL));
-                    f.render_widget(empty_msg, chunks[1]);
-                } else {
-                    let items: Vec<ListItem> = app.active_downloads
-                        .iter()
-                        .enumerate()
-                        .map(|(i, t)| {
-                            let (formatted_completed, formatted_total, progress) = if let (Ok(completed), Ok(total)) = (t.completed_length.parse::<u64>(), t.total_length.parse::<u64>()) {
-                                let formatted_completed = format_bytes(completed);
-                                let formatted_total = format_bytes(total);
-                                let progress = if total > 0 {
-                                    format!(" ({:.1}%)", (completed as f64 / total as f64) * 100.0)
-                                } else {
-                                    String::new()
-                                };
-                                (formatted_completed, formatted_total, progress)
-                            } else {
-                                (t.completed_length.clone(), t.total_length.clone(), String::new())
-                            };
-
-                            let title = format!(
-                                "📁 {} - {}/{} {}",
-                                t.status, formatted_completed, formatted_total, progress
-                            );
-
-                            // Add speed info if available
-                            let title = if t.download_speed != "0" && !t.download_speed.is_empty() {
-                                format!("{} @ {}", title, format_speed(&t.download_speed))
-                            } else {
-                                title
-                            };
-                            let style = if i == app.selected_index && app.mode == AppMode::Normal {
-                                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-                            } else {
-                                Style::default()
-                            };
-                            ListItem::new(title).style(style)
-                        })
-                        .collect();
-
-                    let downloads = List::new(items).block(
-                        Block::default()
-                            .title("📥 Active Downloads")
-                            .borders(Borders::ALL),
-                    );
-                    f.render_widget(downloads, chunks[1]);
-                }
+            AppMode::Normal | AppMode::Search | AppMode::Confirm | AppMode::LimitInput => {
+                render_downloads(f, app, chunks[1])
+            }
+            AppMode::Detail => render_detail(f, app, chunks[1]),
+            AppMode::Queue => render_queue(f, app, chunks[1]),
+            AppMode::Log => render_log(f, app, chunks[1]),
+            AppMode::FilePicker => render_file_picker(f, app, chunks[1]),
+            AppMode::Searching if !app.search_results.is_empty() => {
+                // Show what has arrived so far while slower sources are still running
+                let title = format!("{} Torrent Results (more coming...)", app.get_loading_indicator());
+                render_results_list(f, app, chunks[1], &title);
             },
             AppMode::Searching => {
                 // Show loading animation with spinner and progress
@@ -166,52 +310,462 @@ pub fn render_ui<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app:
                         .block(Block::default().title("📋 Search Results").borders(Borders::ALL));
                     f.render_widget(empty_msg, chunks[1]);
                 } else {
-                    let items: Vec<ListItem> = app.search_results
-                        .iter()
-                        .enumerate()
-                        .map(|(i, result)| {
-                            // Color code by source
-                            let source_color = match result.source.as_str() {
-                                "YTS" => Color::Green,
-                                "PirateBay" => Color::Blue,
-                                "1337x" => Color::Magenta,
-                                _ => Color::Gray,
-                            };
-
-                            // Truncate long names
-                            let display_name = if result.name.len() > 60 {
-                                format!("{}...", &result.name[..57])
-                            } else {
-                                result.name.clone()
-                            };
-
-                            let title = Line::from(vec![
-                                Span::styled(format!("[{}] ", result.source), Style::default().fg(source_color).add_modifier(Modifier::BOLD)),
-                                Span::raw(display_name),
-                                Span::styled(format!(" | {} | ", result.size), Style::default().fg(Color::Cyan)),
-                                Span::styled(format!("S:{}", result.seeders), Style::default().fg(Color::Green)),
-                                Span::raw(" "),
-                                Span::styled(format!("L:{}", result.leechers), Style::default().fg(Color::Red)),
-                            ]);
-
-                            let style = if i == app.selected_index {
-                                Style::default().bg(Color::DarkGray)
-                            } else {
-                                Style::default()
-                            };
-                            ListItem::new(title).style(style)
-                        })
-                        .collect();
-
-                    let results_list = List::new(items).block(
-                        Block::default()
-                            .title("📋 Torrent Results")
-                            .borders(Borders::ALL),
-                    );
-                    f.render_widget(results_list, chunks[1]);
+                    render_results_list(f, app, chunks[1], "📋 Torrent Results");
                 }
             }
         }
     })?;
     Ok(())
 }
+
+fn render_downloads(f: &mut Frame, app: &App, area: Rect) {
+    let filter = app.download_filter.map(|state| format!(" [{}]", state)).unwrap_or_default();
+    let counts = app
+        .download_counts()
+        .iter()
+        .map(|(state, count)| format!("{} {}", count, state.label().to_lowercase()))
+        .collect::<Vec<_>>()
+        .join(" · ");
+    // Mark when aria2 pushes status changes instead of being polled
+    let live = match (&app.aria2_offline, app.live_updates) {
+        (Some(_), _) => " 🔌 aria2 disconnected, reconnecting…",
+        (None, true) => " ⚡ live",
+        (None, false) => "",
+    };
+    let limited = [app.global_limits.download, app.global_limits.upload]
+        .iter()
+        .any(|limit| limit.is_some_and(|l| l > 0));
+    let limits = if limited { format!(" 🚦 {}", app.global_limits) } else { String::new() };
+    let title = if counts.is_empty() {
+        format!("📥 Downloads{}{}{}", filter, live, limits)
+    } else {
+        format!("📥 Downloads{} ({}){}{}", filter, counts, live, limits)
+    };
+
+    let visible = app.visible_downloads();
+    if visible.is_empty() {
+        let message = match app.download_filter {
+            Some(state) => format!("No {} downloads. Press 'f' to change the filter.", state.label().to_lowercase()),
+            None => "No downloads yet. Press 's' to search for torrents.".to_string(),
+        };
+        let empty_msg = Paragraph::new(message)
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center)
+            .block(Block::default().title(title).borders(Borders::ALL));
+        f.render_widget(empty_msg, area);
+        return;
+    }
+
+    let items: Vec<ListItem> = visible
+        .iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let (completed, total) = (t.completed_bytes(), t.total_bytes());
+            let progress = if total > 0 {
+                format!("{:.1}%", (completed as f64 / total as f64) * 100.0)
+            } else {
+                "-".to_string()
+            };
+
+            let (icon, color) = match t.state() {
+                DownloadState::Active => ("▶", Color::Green),
+                DownloadState::Waiting => ("⏳", Color::Cyan),
+                DownloadState::Paused => ("⏸", Color::Yellow),
+                DownloadState::Error => ("❌", Color::Red),
+                DownloadState::Complete => ("✅", Color::Gray),
+                DownloadState::Removed => ("🗑", Color::DarkGray),
+            };
+
+            let mut title = format!(
+                "{} {} - {} of {} ({})",
+                icon,
+                t.name(),
+                progress,
+                format_bytes(total),
+                t.status
+            );
+
+            // Transfer details only matter while data is moving
+            if t.state() == DownloadState::Active {
+                title.push_str(&format!(
+                    " ↓{} ↑{} ratio {:.2} peers {}/{}",
+                    format_speed(&t.download_speed),
+                    format_speed(&t.upload_speed),
+                    t.ratio(),
+                    t.num_seeders,
+                    t.connections
+                ));
+                if let Some(eta) = t.eta() {
+                    title.push_str(&format!(" ETA {}", format_duration(eta)));
+                }
+            } else if t.upload_length != "0" && !t.upload_length.is_empty() {
+                title.push_str(&format!(" ratio {:.2}", t.ratio()));
+            }
+            if let Some(error) = t.error() {
+                title.push_str(&format!(" - {}", error));
+            }
+            if app.forced.contains(&t.gid) {
+                title.push_str(" 🚀 forced");
+            }
+            let style = if i == app.selected_index && app.mode == AppMode::Normal {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(color)
+            };
+            ListItem::new(title).style(style)
+        })
+        .collect();
+
+    let downloads = List::new(items).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL),
+    );
+    // The list can outgrow the pane; the state scrolls it so the selection stays in view
+    let mut state = ListState::default().with_selected(Some(app.selected_index));
+    f.render_stateful_widget(downloads, area, &mut state);
+}
+
+fn render_queue(f: &mut Frame, app: &App, area: Rect) {
+    let queued = app.queued_downloads();
+    let active = app.downloads.iter().filter(|d| d.state() == DownloadState::Active).count();
+    let title = match app.max_concurrent {
+        Some(max) => format!("⏳ Waiting downloads ({}) · {} of {} slots active", queued.len(), active, max),
+        None => format!("⏳ Waiting downloads ({}) · {} active", queued.len(), active),
+    };
+
+    if queued.is_empty() {
+        let empty = Paragraph::new("Nothing is waiting. Downloads beyond the concurrency limit queue up here.")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center)
+            .block(Block::default().title(title).borders(Borders::ALL));
+        f.render_widget(empty, area);
+        return;
+    }
+
+    let items: Vec<ListItem> = queued
+        .iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let (icon, color) = match t.state() {
+                DownloadState::Paused => ("⏸", Color::Yellow),
+                _ => ("⏳", Color::Cyan),
+            };
+            let mut line = format!("{:>3}. {} {} - {}", i + 1, icon, t.name(), format_bytes(t.total_bytes()));
+            if app.forced.contains(&t.gid) {
+                line.push_str(" 🚀 starting");
+            }
+            let style = if i == app.queue_index {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(color)
+            };
+            ListItem::new(line).style(style)
+        })
+        .collect();
+
+    let mut state = ListState::default().with_selected(Some(app.queue_index));
+    f.render_stateful_widget(List::new(items).block(Block::default().title(title).borders(Borders::ALL)), area, &mut state);
+}
+
+fn render_log(f: &mut Frame, app: &App, area: Rect) {
+    let entries = app.visible_log();
+    let total = app.log.len();
+    let title = if app.log_scroll == 0 {
+        format!("{} of {} lines", entries.len(), total)
+    } else {
+        format!("{} of {} lines, {} newer below", entries.len(), total, app.log_scroll)
+    };
+
+    if entries.is_empty() {
+        let empty = Paragraph::new("No log lines match.")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center)
+            .block(Block::default().title(title).borders(Borders::ALL));
+        f.render_widget(empty, area);
+        return;
+    }
+
+    // Newest lines at the bottom; scrolling moves the window back in time
+    let height = area.height.saturating_sub(2) as usize;
+    let end = entries.len().saturating_sub(app.log_scroll);
+    let start = end.saturating_sub(height);
+    let items: Vec<ListItem> = entries[start..end]
+        .iter()
+        .map(|entry| {
+            let color = match entry.level {
+                LogLevel::Error => Color::Red,
+                LogLevel::Warn => Color::Yellow,
+                LogLevel::Info if entry.source == LogSource::Aria2 => Color::Gray,
+                LogLevel::Info => Color::White,
+            };
+            ListItem::new(entry.to_string()).style(Style::default().fg(color))
+        })
+        .collect();
+
+    f.render_widget(List::new(items).block(Block::default().title(title).borders(Borders::ALL)), area);
+}
+
+fn render_detail(f: &mut Frame, app: &App, area: Rect) {
+    let Some(detail) = &app.detail else {
+        let loading = Paragraph::new("Loading download details...")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(loading, area);
+        return;
+    };
+    let status = &detail.status;
+
+    let parts = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(7), Constraint::Min(0)].as_ref())
+        .split(area);
+
+    let (completed, total) = (status.completed_bytes(), status.total_bytes());
+    let mut overview = vec![
+        Line::from(format!(
+            "Status: {}   Progress: {} of {} ({:.1}%)",
+            status.status,
+            format_bytes(completed),
+            format_bytes(total),
+            if total > 0 { completed as f64 / total as f64 * 100.0 } else { 0.0 }
+        )),
+        Line::from(format!(
+            "Speed: ↓{} ↑{}   Uploaded: {}   Ratio: {:.2}   ETA: {}",
+            format_speed(&status.download_speed),
+            format_speed(&status.upload_speed),
+            format_bytes(status.upload_length.parse().unwrap_or(0)),
+            status.ratio(),
+            status.eta().map(format_duration).unwrap_or_else(|| "-".to_string())
+        )),
+        Line::from(format!(
+            "Peers: {} connected, {} seeding   Pieces: {}",
+            status.connections, status.num_seeders, status.num_pieces
+        )),
+        Line::from(format!(
+            "GID: {}   Hash: {}   Dir: {}",
+            status.gid,
+            status.info_hash.as_deref().unwrap_or("-"),
+            status.dir.as_deref().unwrap_or("-")
+        )),
+    ];
+    if let Some(error) = status.error() {
+        overview.push(Line::from(Span::styled(error, Style::default().fg(Color::Red))));
+    }
+    f.render_widget(
+        Paragraph::new(overview).block(Block::default().title("Overview").borders(Borders::ALL)),
+        parts[0],
+    );
+
+    let rows: Vec<ListItem> = match app.detail_tab {
+        DetailTab::Files => detail
+            .files
+            .iter()
+            .map(|file| {
+                let selected = if file.selected == "true" { "[x]" } else { "[ ]" };
+                ListItem::new(format!(
+                    "{} {:>6.1}%  {:>10}  {}",
+                    selected,
+                    file.progress() * 100.0,
+                    format_bytes(file.length.parse().unwrap_or(0)),
+                    file.path
+                ))
+            })
+            .collect(),
+        DetailTab::Peers => {
+            let pieces = status.num_pieces.parse().unwrap_or(0);
+            detail
+                .peers
+                .iter()
+                .map(|peer| {
+                    let progress = peer
+                        .progress(pieces)
+                        .map(|p| format!("{:.1}%", p * 100.0))
+                        .unwrap_or_else(|| "-".to_string());
+                    let seeder = if peer.seeder == "true" { " (seed)" } else { "" };
+                    ListItem::new(format!(
+                        "{:<40} {:<22} ↓{:<10} ↑{:<10} {}{}",
+                        format!("{}:{}", peer.ip, peer.port),
+                        peer.client(),
+                        format_speed(&peer.download_speed),
+                        format_speed(&peer.upload_speed),
+                        progress,
+                        seeder
+                    ))
+                })
+                .collect()
+        }
+        DetailTab::Trackers => {
+            // aria2 exposes the announce list but not per-tracker results
+            let tiers = status.bittorrent.as_ref().map(|bt| bt.announce_list.as_slice()).unwrap_or_default();
+            tiers
+                .iter()
+                .enumerate()
+                .flat_map(|(tier, trackers)| {
+                    trackers.iter().map(move |tracker| ListItem::new(format!("tier {}  {}", tier + 1, tracker)))
+                })
+                .chain(detail.uris.iter().map(|uri| ListItem::new(format!("{:<7}  {}", uri.status, uri.uri))))
+                .collect()
+        }
+    };
+
+    let count = rows.len();
+    let title = format!("{} ({})", app.detail_tab.label(), count);
+    let body: Vec<ListItem> = if count == 0 {
+        vec![ListItem::new("Nothing to show").style(Style::default().fg(Color::Gray))]
+    } else {
+        rows.into_iter().skip(app.detail_scroll).collect()
+    };
+    f.render_widget(List::new(body).block(Block::default().title(title).borders(Borders::ALL)), parts[1]);
+}
+
+fn render_file_picker(f: &mut Frame, app: &App, area: Rect) {
+    let Some(picker) = &app.file_picker else {
+        return;
+    };
+
+    // Previews get a summary above the tree, since nothing about them is known yet
+    let area = if let PickerTarget::Preview { origin, exact, .. } = &picker.target {
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(4), Constraint::Min(0)].as_ref())
+            .split(area);
+        let total: u64 = picker.files.iter().map(|f| f.length).sum();
+        let suspicious: Vec<&str> = picker
+            .files
+            .iter()
+            .filter(|f| is_suspicious(&f.path))
+            .map(|f| f.path.rsplit('/').next().unwrap_or(&f.path))
+            .collect();
+        let mut summary = vec![Line::from(format!(
+            "{} files, {} in total · list from {}{}",
+            picker.files.len(),
+            format_bytes(total),
+            origin,
+            if *exact { "" } else { " (selection is confirmed once the metadata is in)" }
+        ))];
+        summary.push(if suspicious.is_empty() {
+            Line::from(Span::styled("No executables or scripts found", Style::default().fg(Color::Green)))
+        } else {
+            Line::from(Span::styled(
+                format!("⚠️  {} suspicious file(s): {}", suspicious.len(), suspicious.join(", ")),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ))
+        });
+        f.render_widget(
+            Paragraph::new(summary).block(Block::default().title("Preview").borders(Borders::ALL)),
+            parts[0],
+        );
+        parts[1]
+    } else {
+        area
+    };
+
+    // Scroll just far enough to keep the cursor on screen
+    let height = area.height.saturating_sub(2).max(1) as usize;
+    let offset = (picker.cursor + 1).saturating_sub(height);
+
+    let items: Vec<ListItem> = picker
+        .rows
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(height)
+        .map(|(i, row)| {
+            let indent = "  ".repeat(row.depth);
+            let (text, color) = match &row.kind {
+                PickerRowKind::Dir(prefix) => {
+                    let checkbox = match picker.dir_selection(prefix) {
+                        Some(true) => "[x]",
+                        Some(false) => "[ ]",
+                        None => "[~]",
+                    };
+                    let size: u64 = picker.files.iter().filter(|f| f.path.starts_with(prefix.as_str())).map(|f| f.length).sum();
+                    (format!("{}{} 📂 {}/  {}", indent, checkbox, row.name, format_bytes(size)), Color::Cyan)
+                }
+                PickerRowKind::File(index) => {
+                    let file = &picker.files[*index];
+                    let checkbox = if file.selected { "[x]" } else { "[ ]" };
+                    if is_suspicious(&file.path) {
+                        (format!("{}{} ⚠️  {}  {}", indent, checkbox, row.name, format_bytes(file.length)), Color::Red)
+                    } else {
+                        let color = if file.selected { Color::White } else { Color::DarkGray };
+                        (format!("{}{} {}  {}", indent, checkbox, row.name, format_bytes(file.length)), color)
+                    }
+                }
+            };
+            let style = if i == picker.cursor {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(color)
+            };
+            ListItem::new(text).style(style)
+        })
+        .collect();
+
+    let name = match &picker.target {
+        PickerTarget::Download { gid, .. } => app
+            .downloads
+            .iter()
+            .find(|d| d.gid == *gid)
+            .map(|d| d.name())
+            .unwrap_or_else(|| gid.clone()),
+        PickerTarget::Preview { name, .. } => name.clone(),
+    };
+    let title = format!("Files of {}", name);
+    f.render_widget(List::new(items).block(Block::default().title(title).borders(Borders::ALL)), area);
+}
+
+fn render_results_list(f: &mut Frame, app: &App, area: Rect, title: &str) {
+    let items: Vec<ListItem> = app.search_results
+        .iter()
+        .enumerate()
+        .map(|(i, result)| {
+            // Color code by source
+            let source_color = match result.source.as_str() {
+                "YTS" => Color::Green,
+                "PirateBay" => Color::Blue,
+                "1337x" => Color::Magenta,
+                _ => Color::Gray,
+            };
+
+            // Truncate long names by characters, since titles are often not ASCII
+            let display_name = if result.name.chars().count() > 60 {
+                format!("{}...", result.name.chars().take(57).collect::<String>())
+            } else {
+                result.name.clone()
+            };
+
+            // Merged duplicates list every source that carries the release
+            let source_label = if result.sources.len() > 1 {
+                result.sources.join("+")
+            } else {
+                result.source.clone()
+            };
+
+            let title = Line::from(vec![
+                Span::styled(format!("[{}] ", source_label), Style::default().fg(source_color).add_modifier(Modifier::BOLD)),
+                Span::raw(display_name),
+                Span::styled(format!(" | {} | ", result.size), Style::default().fg(Color::Cyan)),
+                Span::styled(format!("S:{}", result.seeders), Style::default().fg(Color::Green)),
+                Span::raw(" "),
+                Span::styled(format!("L:{}", result.leechers), Style::default().fg(Color::Red)),
+            ]);
+
+            let style = if i == app.selected_index {
+                Style::default().bg(Color::DarkGray)
+            } else {
+                Style::default()
+            };
+            ListItem::new(title).style(style)
+        })
+        .collect();
+
+    let results_list = List::new(items).block(
+        Block::default()
+            .title(title.to_string())
+            .borders(Borders::ALL),
+    );
+    f.render_widget(results_list, area);
+}
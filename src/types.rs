@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Content category a search can be restricted to
+///
+/// Each provider maps these onto its own category codes and skips the ones it cannot serve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Movies,
+    TV,
+    Music,
+    Games,
+    Software,
+    Anime,
+    Books,
+    Other,
+}
+
+impl Category {
+    pub const ALL: [Category; 8] = [
+        Category::Movies,
+        Category::TV,
+        Category::Music,
+        Category::Games,
+        Category::Software,
+        Category::Anime,
+        Category::Books,
+        Category::Other,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Category::Movies => "Movies",
+            Category::TV => "TV",
+            Category::Music => "Music",
+            Category::Games => "Games",
+            Category::Software => "Software",
+            Category::Anime => "Anime",
+            Category::Books => "Books",
+            Category::Other => "Other",
+        }
+    }
+
+    /// Step through "all categories" followed by every category, wrapping around
+    pub fn cycle(current: Option<Category>, forward: bool) -> Option<Category> {
+        let position = current.and_then(|c| Self::ALL.iter().position(|&a| a == c));
+        let len = Self::ALL.len();
+        match (position, forward) {
+            (None, true) => Some(Self::ALL[0]),
+            (None, false) => Some(Self::ALL[len - 1]),
+            (Some(i), true) if i + 1 < len => Some(Self::ALL[i + 1]),
+            (Some(i), false) if i > 0 => Some(Self::ALL[i - 1]),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl FromStr for Category {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|c| c.label().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("unknown category '{}'", s))
+    }
+}
+
+/// Lifecycle state of an aria2 download, in the order downloads are grouped for display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DownloadState {
+    Active,
+    Waiting,
+    Paused,
+    Error,
+    Complete,
+    Removed,
+}
+
+impl DownloadState {
+    pub const ALL: [DownloadState; 6] = [
+        DownloadState::Active,
+        DownloadState::Waiting,
+        DownloadState::Paused,
+        DownloadState::Error,
+        DownloadState::Complete,
+        DownloadState::Removed,
+    ];
+
+    /// Parse aria2's `status` field
+    pub fn from_aria2(status: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|s| s.aria2_name() == status)
+    }
+
+    pub fn aria2_name(self) -> &'static str {
+        match self {
+            DownloadState::Active => "active",
+            DownloadState::Waiting => "waiting",
+            DownloadState::Paused => "paused",
+            DownloadState::Error => "error",
+            DownloadState::Complete => "complete",
+            DownloadState::Removed => "removed",
+        }
+    }
+
+    /// Finished one way or another; only the result is left in aria2
+    pub fn is_stopped(self) -> bool {
+        matches!(self, DownloadState::Error | DownloadState::Complete | DownloadState::Removed)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DownloadState::Active => "Active",
+            DownloadState::Waiting => "Waiting",
+            DownloadState::Paused => "Paused",
+            DownloadState::Error => "Error",
+            DownloadState::Complete => "Complete",
+            DownloadState::Removed => "Removed",
+        }
+    }
+
+    /// Step through "all states" followed by every state, wrapping around
+    pub fn cycle(current: Option<DownloadState>, forward: bool) -> Option<DownloadState> {
+        let position = current.and_then(|c| Self::ALL.iter().position(|&a| a == c));
+        let len = Self::ALL.len();
+        match (position, forward) {
+            (None, true) => Some(Self::ALL[0]),
+            (None, false) => Some(Self::ALL[len - 1]),
+            (Some(i), true) if i + 1 < len => Some(Self::ALL[i + 1]),
+            (Some(i), false) if i > 0 => Some(Self::ALL[i - 1]),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DownloadState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
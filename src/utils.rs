@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Get the default download directory for the current OS
 pub fn get_default_download_dir() -> PathBuf {
@@ -40,6 +40,64 @@ pub fn ensure_download_dir_exists(path: &PathBuf) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Delete the files of a removed download along with aria2's `.aria2` control files
+///
+/// A single file's control file sits next to it; a multi-file torrent has one `control_file`
+/// beside its top folder instead. The directories the files lived in are removed as well once
+/// empty, up to the deepest directory shared by all files. Returns how many files were deleted.
+pub fn delete_download_files(paths: &[&str], control_file: Option<&Path>) -> std::io::Result<usize> {
+    // Magnets without metadata list "[METADATA]<hash>" instead of a real path
+    let paths: Vec<&Path> = paths
+        .iter()
+        .filter(|p| !p.is_empty() && !p.starts_with("[METADATA]"))
+        .map(Path::new)
+        .collect();
+
+    let mut deleted = 0;
+    for path in &paths {
+        for candidate in [path.to_path_buf(), PathBuf::from(format!("{}.aria2", path.display()))] {
+            match std::fs::remove_file(&candidate) {
+                Ok(()) if candidate == *path => deleted += 1,
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    if let Some(control_file) = control_file {
+        match std::fs::remove_file(control_file) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+
+    if paths.len() > 1 {
+        let root = paths
+            .iter()
+            .filter_map(|p| p.parent())
+            .reduce(|common, parent| {
+                common
+                    .ancestors()
+                    .find(|ancestor| parent.starts_with(ancestor))
+                    .unwrap_or(Path::new(""))
+            })
+            .unwrap_or(Path::new(""));
+        if !root.as_os_str().is_empty() {
+            for path in &paths {
+                for dir in path.ancestors().skip(1) {
+                    // Fails, and so stops, at the first directory that still has content
+                    if !dir.starts_with(root) || std::fs::remove_dir(dir).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(deleted)
+}
+
 /// Format bytes into human-readable format (B, KB, MB, GB, TB)
 pub fn format_bytes(bytes: u64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
@@ -74,4 +132,51 @@ pub fn format_speed(speed: &str) -> String {
     } else {
         format!("{}B/s", speed)
     }
-}
\ No newline at end of file
+}
+
+/// Format a remaining time compactly, e.g. "45s", "3m 10s", "2h 05m" or "3d 4h"
+pub fn format_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        3600..86400 => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
+
+/// Parse a human-readable size such as "700 MiB", "1.4 GB" or "1,2 GB" into bytes
+///
+/// SI units (KB, MB, GB, TB) are powers of 1000 and IEC units (KiB, MiB, GiB, TiB) powers of 1024.
+/// A comma is a thousands separator next to a dot or when groups of three digits follow it
+/// ("1,200 MB"); otherwise it is a decimal separator ("1,2 GB").
+pub fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+
+    let grouped = number.contains(',') && number.split(',').skip(1).all(|group| group.len() == 3);
+    let number = if number.contains('.') || grouped {
+        number.replace(',', "")
+    } else {
+        number.replace(',', ".")
+    };
+    let value: f64 = number.parse().ok()?;
+
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" | "bytes" => 1,
+        "k" | "kb" => 1_000,
+        "m" | "mb" => 1_000_000,
+        "g" | "gb" => 1_000_000_000,
+        "t" | "tb" => 1_000_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return None,
+    };
+
+    Some((value * multiplier as f64).round() as u64)
+}
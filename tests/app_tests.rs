@@ -1,15 +1,28 @@
-use tui_torrent::app::{App, AppMode};
-use tui_torrent::torrent_search::TorrentSearchResult;
+use crossterm::event::{KeyCode, KeyEvent};
+use tui_torrent::api::TorrentFileEntry;
+use tui_torrent::app::{AddRequest, App, AppMode, DetailTab};
+use tui_torrent::aria2_client::{DownloadAction, DownloadFile, QueueMove, TorrentStatus};
+use tui_torrent::bandwidth::SpeedLimits;
+use tui_torrent::file_picker::FilePicker;
+use tui_torrent::log_buffer::{LogLevel, LogSource};
+use tui_torrent::preview::TorrentPreview;
+use tui_torrent::torrent_search::{SourceState, SourceUpdate, TorrentSearchResult};
+use tui_torrent::types::{Category, DownloadState};
 
 fn sample_results() -> Vec<TorrentSearchResult> {
-    vec![TorrentSearchResult {
-        name: "Test Torrent".into(),
+    vec![result("Test Torrent", 100, "YTS")]
+}
+
+fn result(name: &str, seeders: u32, source: &str) -> TorrentSearchResult {
+    TorrentSearchResult {
+        name: name.into(),
         size: "1 GB".into(),
-        seeders: 100,
+        seeders,
         leechers: 10,
         magnet_link: "magnet:?xt=urn:btih:TEST".into(),
-        source: "YTS".into(),
-    }]
+        source: source.into(),
+        ..Default::default()
+    }
 }
 
 #[test]
@@ -54,3 +67,491 @@ fn loading_animation_cycles() {
     assert_ne!(initial, app.loading_frame); // progressed
     assert!(!app.search_progress.is_empty());
 }
+
+#[test]
+fn source_updates_merge_incrementally() {
+    let mut app = App::new();
+    app.start_search();
+    assert!(app.search_requested);
+    app.track_sources(vec!["YTS".to_string(), "1337x".to_string()]);
+
+    app.apply_source_update(SourceUpdate {
+        index: 0,
+        source: "YTS".into(),
+        outcome: Ok(vec![result("Low", 5, "YTS")]),
+    });
+    assert_eq!(app.mode, AppMode::Searching);
+    assert_eq!(app.search_results.len(), 1);
+    assert_eq!(app.source_states[0].1, SourceState::Done(1));
+    assert_eq!(app.source_states[1].1, SourceState::Pending);
+
+    app.apply_source_update(SourceUpdate {
+        index: 1,
+        source: "1337x".into(),
+        outcome: Err(SourceState::TimedOut),
+    });
+    assert_eq!(app.mode, AppMode::Results);
+    assert!(!app.search_in_progress);
+    assert!(app.status_message.contains("Found 1"));
+    assert!(app.status_message.contains("1337x"));
+}
+
+#[test]
+fn sources_sharing_a_name_are_tracked_apart() {
+    let mut app = App::new();
+    app.start_search();
+    app.track_sources(vec!["Torznab".to_string(), "Torznab".to_string()]);
+    app.apply_source_update(SourceUpdate { index: 1, source: "Torznab".into(), outcome: Ok(Vec::new()) });
+    assert_eq!(app.source_states[0].1, SourceState::Pending);
+    app.apply_source_update(SourceUpdate { index: 0, source: "Torznab".into(), outcome: Err(SourceState::TimedOut) });
+    assert!(!app.search_in_progress);
+}
+
+#[test]
+fn results_are_sorted_as_they_arrive() {
+    let mut app = App::new();
+    app.start_search();
+    app.track_sources(vec!["A".to_string(), "B".to_string(), "C".to_string()]);
+    app.apply_source_update(SourceUpdate { index: 0, source: "A".into(), outcome: Ok(vec![result("a", 5, "A")]) });
+    app.apply_source_update(SourceUpdate { index: 1, source: "B".into(), outcome: Ok(vec![result("b", 50, "B")]) });
+    let names: Vec<&str> = app.search_results.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(names, vec!["b", "a"]);
+}
+
+#[test]
+fn esc_cancels_running_search() {
+    let mut app = App::new();
+    app.search_query = "ubuntu".into();
+    app.start_search();
+    app.track_sources(vec!["YTS".to_string()]);
+    app.handle_key(KeyEvent::from(KeyCode::Esc));
+    assert_eq!(app.mode, AppMode::Normal);
+    assert!(!app.search_in_progress);
+    assert!(app.status_message.contains("cancelled"));
+
+    // Late answers from the aborted search are ignored
+    app.apply_source_update(SourceUpdate { index: 0, source: "YTS".into(), outcome: Ok(sample_results()) });
+    assert_eq!(app.mode, AppMode::Normal);
+    assert!(app.search_results.is_empty());
+}
+
+#[test]
+fn query_errors_stay_in_search_mode() {
+    let mut app = App::new();
+    app.mode = AppMode::Search;
+    app.search_query = "ubuntu seeders:>many".into();
+    app.handle_key(KeyEvent::from(KeyCode::Enter));
+    assert_eq!(app.mode, AppMode::Search);
+    assert!(!app.search_in_progress);
+    assert!(app.status_message.starts_with("Query error"));
+}
+
+#[test]
+fn inline_filters_apply_to_merged_results() {
+    let mut app = App::new();
+    app.search_query = "ubuntu seeders:>20".into();
+    app.start_search();
+    assert_eq!(app.search_terms, "ubuntu");
+    app.track_sources(vec!["YTS".to_string()]);
+    app.apply_source_update(SourceUpdate {
+        index: 0,
+        source: "YTS".into(),
+        outcome: Ok(vec![result("popular", 50, "YTS"), result("obscure", 2, "YTS")]),
+    });
+    assert_eq!(app.search_results.len(), 1);
+    assert_eq!(app.search_results[0].name, "popular");
+    assert!(app.status_message.contains("1 hidden"));
+}
+
+#[test]
+fn tab_cycles_search_category() {
+    let mut app = App::new();
+    app.mode = AppMode::Search;
+    app.handle_key(KeyEvent::from(KeyCode::Tab));
+    assert_eq!(app.selected_category, Some(Category::Movies));
+    app.handle_key(KeyEvent::from(KeyCode::Tab));
+    assert_eq!(app.selected_category, Some(Category::TV));
+    app.handle_key(KeyEvent::from(KeyCode::BackTab));
+    app.handle_key(KeyEvent::from(KeyCode::BackTab));
+    assert_eq!(app.selected_category, None);
+    app.handle_key(KeyEvent::from(KeyCode::BackTab));
+    assert_eq!(app.selected_category, Some(Category::Other));
+    assert_eq!(app.mode, AppMode::Search);
+}
+
+fn download(gid: &str, status: &str) -> TorrentStatus {
+    TorrentStatus {
+        gid: gid.to_string(),
+        status: status.to_string(),
+        total_length: "100".to_string(),
+        completed_length: "0".to_string(),
+        download_speed: "0".to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn pushed_status_changes_update_the_download_list() {
+    let mut app = App::new();
+    app.update_download(download("a", "active"));
+    app.update_download(download("b", "waiting"));
+    assert_eq!(app.downloads.len(), 2);
+
+    let mut progressed = download("a", "active");
+    progressed.completed_length = "50".to_string();
+    app.update_download(progressed);
+    assert_eq!(app.downloads[0].completed_length, "50");
+
+    // Finished downloads stay listed, grouped after the running ones
+    app.update_download(download("a", "complete"));
+    let gids: Vec<_> = app.downloads.iter().map(|d| d.gid.as_str()).collect();
+    assert_eq!(gids, vec!["b", "a"]);
+
+    app.selected_index = 1;
+    app.remove_download("a");
+    assert_eq!(app.downloads.len(), 1);
+    assert_eq!(app.selected_index, 0);
+}
+
+#[test]
+fn downloads_can_be_filtered_by_state() {
+    let mut app = App::new();
+    let mut failed = download("c", "error");
+    failed.error_code = Some("3".to_string());
+    failed.error_message = Some("Resource was not found".to_string());
+    app.set_downloads(vec![download("a", "complete"), failed, download("b", "active")]);
+
+    let gids: Vec<_> = app.downloads.iter().map(|d| d.gid.as_str()).collect();
+    assert_eq!(gids, vec!["b", "c", "a"]);
+    assert_eq!(app.downloads[1].error().as_deref(), Some("error 3: Resource was not found"));
+    assert_eq!(app.downloads[0].error(), None);
+
+    // 'f' steps through All -> Active -> Waiting -> Paused -> Error
+    for _ in 0..4 {
+        app.handle_key(KeyEvent::from(KeyCode::Char('f')));
+    }
+    assert_eq!(app.download_filter, Some(DownloadState::Error));
+    assert_eq!(app.visible_downloads().len(), 1);
+    assert_eq!(app.selected_download().map(|d| d.gid.as_str()), Some("c"));
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('F')));
+    assert_eq!(app.download_filter, Some(DownloadState::Paused));
+    assert!(app.visible_downloads().is_empty());
+}
+
+#[test]
+fn download_controls_queue_actions() {
+    let mut app = App::new();
+    let mut failed = download("e", "error");
+    failed.info_hash = Some("c9e15763f722f23e98a29decdfae341b98d53056".to_string());
+    app.set_downloads(vec![download("a", "active"), download("p", "paused"), failed]);
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('p')));
+    assert_eq!(app.pending_action.take(), Some(DownloadAction::Pause { gid: "a".to_string() }));
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('j')));
+    app.handle_key(KeyEvent::from(KeyCode::Char('p')));
+    assert_eq!(app.pending_action.take(), Some(DownloadAction::Resume { gid: "p".to_string() }));
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('j')));
+    app.handle_key(KeyEvent::from(KeyCode::Char('r')));
+    assert_eq!(
+        app.pending_action.take(),
+        Some(DownloadAction::Retry {
+            gid: "e".to_string(),
+            magnet: "magnet:?xt=urn:btih:c9e15763f722f23e98a29decdfae341b98d53056".to_string()
+        })
+    );
+
+    // Clearing a stopped download needs no confirmation
+    app.handle_key(KeyEvent::from(KeyCode::Char('x')));
+    assert_eq!(app.pending_action.take(), Some(DownloadAction::RemoveResult { gid: "e".to_string() }));
+}
+
+#[test]
+fn speed_limits_are_typed_in_a_prompt() {
+    let mut app = App::new();
+    app.set_downloads(vec![download("a", "active")]);
+    app.global_limits = SpeedLimits::new(512_000, 0);
+
+    // Overall limits start from the ones in force
+    app.handle_key(KeyEvent::from(KeyCode::Char('l')));
+    assert_eq!(app.mode, AppMode::LimitInput);
+    assert_eq!(app.limit_input, "500K 0");
+    app.handle_key(KeyEvent::from(KeyCode::Backspace));
+    for c in "50K".chars() {
+        app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+    }
+    app.handle_key(KeyEvent::from(KeyCode::Enter));
+    assert_eq!(app.mode, AppMode::Normal);
+    assert_eq!(app.pending_global_limits.take(), Some(SpeedLimits::new(512_000, 51_200)));
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('L')));
+    assert!(app.limit_input.is_empty());
+    for c in "fast".chars() {
+        app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+    }
+    app.handle_key(KeyEvent::from(KeyCode::Enter));
+    assert_eq!(app.mode, AppMode::LimitInput);
+    assert!(app.status_message.contains("Invalid limit"), "{}", app.status_message);
+
+    app.limit_input = "1M".to_string();
+    app.handle_key(KeyEvent::from(KeyCode::Enter));
+    assert_eq!(
+        app.pending_action.take(),
+        Some(DownloadAction::SetLimits {
+            gid: "a".to_string(),
+            limits: SpeedLimits { download: Some(1_048_576), upload: None },
+        })
+    );
+}
+
+#[test]
+fn queue_view_reorders_waiting_downloads() {
+    let mut app = App::new();
+    // aria2 lists the queue in order; paused entries keep their place in it
+    app.set_downloads(vec![
+        download("a", "active"),
+        download("w1", "waiting"),
+        download("p1", "paused"),
+        download("w2", "waiting"),
+        download("c", "complete"),
+    ]);
+    let queued: Vec<&str> = app.queued_downloads().iter().map(|d| d.gid.as_str()).collect();
+    assert_eq!(queued, vec!["w1", "p1", "w2"]);
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('w')));
+    assert_eq!(app.mode, AppMode::Queue);
+    app.handle_key(KeyEvent::from(KeyCode::Char('j')));
+    app.handle_key(KeyEvent::from(KeyCode::Char('K')));
+    assert_eq!(
+        app.pending_action.take(),
+        Some(DownloadAction::Move { gid: "p1".to_string(), to: QueueMove::Up })
+    );
+    assert_eq!(app.queue_index, 0);
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('b')));
+    assert_eq!(
+        app.pending_action.take(),
+        Some(DownloadAction::Move { gid: "w1".to_string(), to: QueueMove::Bottom })
+    );
+    assert_eq!(app.queue_index, 2);
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('f')));
+    assert_eq!(app.pending_action.take(), Some(DownloadAction::ForceStart { gid: "w2".to_string() }));
+    assert_eq!(app.forced, vec!["w2".to_string()]);
+
+    // Concurrency needs to be known before it can be changed
+    app.handle_key(KeyEvent::from(KeyCode::Char('+')));
+    assert_eq!(app.pending_concurrency, None);
+    app.max_concurrent = Some(1);
+    app.handle_key(KeyEvent::from(KeyCode::Char('-')));
+    assert_eq!(app.pending_concurrency.take(), Some(1));
+    app.handle_key(KeyEvent::from(KeyCode::Char('+')));
+    assert_eq!(app.pending_concurrency.take(), Some(2));
+
+    // A download that started leaves the queue; the forced mark goes once it stops
+    app.update_download(download("w2", "active"));
+    assert_eq!(app.queue, vec!["w1".to_string(), "p1".to_string()]);
+    assert_eq!(app.forced, vec!["w2".to_string()]);
+    app.update_download(download("w2", "complete"));
+    assert!(app.forced.is_empty());
+
+    app.handle_key(KeyEvent::from(KeyCode::Esc));
+    assert_eq!(app.mode, AppMode::Normal);
+}
+
+#[test]
+fn losing_aria2_keeps_the_download_list() {
+    let mut app = App::new();
+    app.live_updates = true;
+    app.set_downloads(vec![download("a", "active")]);
+
+    app.aria2_disconnected("aria2c exited (signal: 9)".to_string());
+    assert!(!app.live_updates);
+    assert_eq!(app.aria2_offline.as_deref(), Some("aria2c exited (signal: 9)"));
+    assert!(app.status_message.contains("reconnecting"), "{}", app.status_message);
+    assert_eq!(app.downloads.len(), 1);
+
+    app.aria2_reconnected();
+    assert_eq!(app.aria2_offline, None);
+}
+
+#[test]
+fn log_pane_filters_and_scrolls() {
+    let mut app = App::new();
+    app.status_message = "Added torrent: Ubuntu".to_string();
+    app.record_status();
+    app.record_status();
+    app.log.push(LogSource::Aria2, LogLevel::Error, "[ERROR] Download aborted");
+    app.log.push(LogSource::Aria2, LogLevel::Info, "[NOTICE] Download complete");
+    assert_eq!(app.visible_log().len(), 3);
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('v')));
+    assert_eq!(app.mode, AppMode::Log);
+    app.handle_key(KeyEvent::from(KeyCode::Tab));
+    assert_eq!(app.log_source, Some(LogSource::App));
+    assert_eq!(app.visible_log()[0].message, "Added torrent: Ubuntu");
+    app.handle_key(KeyEvent::from(KeyCode::Tab));
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('/')));
+    for c in "abort".chars() {
+        app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+    }
+    app.handle_key(KeyEvent::from(KeyCode::Enter));
+    let visible = app.visible_log();
+    assert_eq!(visible.len(), 1);
+    assert_eq!(visible[0].level, LogLevel::Error);
+
+    // Scrolling stops at the oldest matching line
+    app.handle_key(KeyEvent::from(KeyCode::Esc));
+    assert_eq!(app.mode, AppMode::Normal);
+    app.handle_key(KeyEvent::from(KeyCode::Char('v')));
+    app.handle_key(KeyEvent::from(KeyCode::Char('k')));
+    assert_eq!(app.log_scroll, 0);
+    app.handle_key(KeyEvent::from(KeyCode::Char('/')));
+    app.handle_key(KeyEvent::from(KeyCode::Esc));
+    app.handle_key(KeyEvent::from(KeyCode::Char('k')));
+    assert_eq!(app.log_scroll, 1);
+    app.handle_key(KeyEvent::from(KeyCode::Char('G')));
+    assert_eq!(app.log_scroll, 0);
+}
+
+#[test]
+fn removing_files_asks_for_confirmation() {
+    let mut app = App::new();
+    app.set_downloads(vec![download("a", "active")]);
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('X')));
+    assert_eq!(app.mode, AppMode::Confirm);
+    assert!(app.pending_action.is_none());
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('n')));
+    assert_eq!(app.mode, AppMode::Normal);
+    assert!(app.pending_action.is_none() && app.confirm_action.is_none());
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('X')));
+    app.handle_key(KeyEvent::from(KeyCode::Enter));
+    assert_eq!(app.mode, AppMode::Confirm);
+    assert!(app.pending_action.is_none());
+    app.handle_key(KeyEvent::from(KeyCode::Char('y')));
+    assert_eq!(
+        app.pending_action,
+        Some(DownloadAction::Remove { gid: "a".to_string(), delete_files: true })
+    );
+}
+
+#[test]
+fn enter_opens_download_details() {
+    let mut app = App::new();
+    app.set_downloads(vec![download("a", "active"), download("b", "paused")]);
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('j')));
+    app.handle_key(KeyEvent::from(KeyCode::Enter));
+    assert_eq!(app.mode, AppMode::Detail);
+    assert_eq!(app.detail_gid.as_deref(), Some("b"));
+
+    app.handle_key(KeyEvent::from(KeyCode::Tab));
+    assert_eq!(app.detail_tab, DetailTab::Peers);
+    // Nothing loaded yet, so there is nothing to scroll
+    app.handle_key(KeyEvent::from(KeyCode::Char('j')));
+    assert_eq!(app.detail_scroll, 0);
+
+    app.handle_key(KeyEvent::from(KeyCode::Esc));
+    assert_eq!(app.mode, AppMode::Normal);
+    assert!(app.detail_gid.is_none());
+}
+
+#[test]
+fn picked_files_are_applied_after_metadata_arrives() {
+    let mut app = App::new();
+    app.search_results = sample_results();
+    app.mode = AppMode::Results;
+    app.handle_key(KeyEvent::from(KeyCode::Char('o')));
+    assert!(app.download_requested && app.choose_files);
+
+    // The main loop added the magnet and waits for its metadata download
+    app.awaiting_metadata.push("meta".to_string());
+    app.mode = AppMode::Normal;
+    app.set_downloads(vec![download("meta", "active")]);
+    assert!(app.picker_request.is_none());
+
+    let mut resolved = download("meta", "complete");
+    resolved.followed_by = vec!["real".to_string()];
+    app.update_download(resolved);
+    assert_eq!(app.picker_request, Some(("real".to_string(), true)));
+    assert!(app.awaiting_metadata.is_empty());
+
+    let file = |index: &str, path: &str| DownloadFile {
+        index: index.to_string(),
+        path: path.to_string(),
+        length: "100".to_string(),
+        selected: "true".to_string(),
+        ..Default::default()
+    };
+    let files = vec![file("1", "/dl/Show/e01.mkv"), file("2", "/dl/Show/e02.mkv"), file("3", "/dl/Show/e03.mkv")];
+    app.open_file_picker(FilePicker::from_download("real", &files, Some("/dl"), true));
+    assert_eq!(app.mode, AppMode::FilePicker);
+
+    // Row 0 is the "Show" directory; untick the second episode
+    for key in [KeyCode::Char('j'), KeyCode::Char('j'), KeyCode::Char(' '), KeyCode::Enter] {
+        app.handle_key(KeyEvent::from(key));
+    }
+    assert_eq!(
+        app.pending_action,
+        Some(DownloadAction::SelectFiles { gid: "real".to_string(), files: "1,3".to_string(), resume: true })
+    );
+    assert_eq!(app.mode, AppMode::Normal);
+    assert!(app.file_picker.is_none());
+}
+
+#[test]
+fn previewed_selection_carries_over_to_the_download() {
+    let mut app = App::new();
+    app.search_results = sample_results();
+    app.mode = AppMode::Results;
+    app.handle_key(KeyEvent::from(KeyCode::Char('i')));
+    assert!(app.preview_requested);
+
+    let entry = |path: &str| TorrentFileEntry { path: path.to_string(), size_bytes: Some(10) };
+    let preview = TorrentPreview {
+        name: "Show".to_string(),
+        uri: "magnet:?xt=urn:btih:TEST".to_string(),
+        files: vec![entry("e01.mkv"), entry("e02.mkv")],
+        origin: "PirateBay".to_string(),
+        exact: false,
+    };
+    app.open_file_picker(FilePicker::from_preview(&preview));
+    for key in [KeyCode::Char('j'), KeyCode::Char(' '), KeyCode::Enter] {
+        app.handle_key(KeyEvent::from(key));
+    }
+    // The provider's order is not trusted, so the real list is picked from once the metadata is in
+    assert_eq!(
+        app.pending_add,
+        Some(AddRequest {
+            name: "Show".to_string(),
+            uri: "magnet:?xt=urn:btih:TEST".to_string(),
+            options: vec![("pause-metadata".to_string(), "true".to_string())],
+            wanted_files: Some(vec!["e01.mkv".to_string()]),
+        })
+    );
+    assert_eq!(app.mode, AppMode::Results);
+
+    app.awaiting_metadata.push("meta".to_string());
+    app.wanted_files.push(("meta".to_string(), vec!["e01.mkv".to_string()]));
+    let mut resolved = download("meta", "complete");
+    resolved.followed_by = vec!["real".to_string()];
+    app.update_download(resolved);
+
+    let file = |index: &str, path: &str| DownloadFile {
+        index: index.to_string(),
+        path: path.to_string(),
+        length: "10".to_string(),
+        selected: "true".to_string(),
+        ..Default::default()
+    };
+    let files = vec![file("1", "/dl/Show/e02.mkv"), file("2", "/dl/Show/e01.mkv")];
+    app.open_file_picker(FilePicker::from_download("real", &files, Some("/dl"), true));
+    assert_eq!(app.file_picker.as_ref().and_then(|p| p.select_file_option()).as_deref(), Some("2"));
+    assert!(app.wanted_files.is_empty());
+}
@@ -0,0 +1,452 @@
+mod common;
+
+use common::{Route, StubServer};
+use tui_torrent::aria2_client::{Aria2Client, DownloadAction, PeerInfo, QueueMove};
+use tui_torrent::bandwidth::SpeedLimits;
+use tui_torrent::error::TorrentError;
+
+const VERSION: &str = r#"{"id":"1","jsonrpc":"2.0","result":{"enabledFeatures":["BitTorrent","Metalink"],"version":"1.37.0"}}"#;
+
+const ADDED: &str = r#"{"id":"2","jsonrpc":"2.0","result":"2089b05ecca3d829"}"#;
+
+const ACTIVE: &str = r#"{"id":"3","jsonrpc":"2.0","result":[{"gid":"2089b05ecca3d829","status":"active","totalLength":"1000","completedLength":"250","downloadSpeed":"50","infoHash":"c9e15763f722f23e98a29decdfae341b98d53056"}]}"#;
+
+const BAD_URI: &str = r#"{"id":"4","jsonrpc":"2.0","error":{"code":1,"message":"No URI to download."}}"#;
+
+fn client(server: &StubServer) -> Aria2Client {
+    Aria2Client::new(&format!("{}/jsonrpc", server.base_url))
+}
+
+#[tokio::test]
+async fn decodes_typed_results() {
+    let server = StubServer::start(vec![
+        Route::new("aria2.getVersion", "application/json", VERSION),
+        Route::new("aria2.addUri", "application/json", ADDED),
+        Route::new("aria2.tellActive", "application/json", ACTIVE),
+    ])
+    .await;
+    let aria2 = client(&server);
+
+    let version = aria2.get_version().await.unwrap();
+    assert_eq!(version.version, "1.37.0");
+    assert!(version.enabled_features.iter().any(|f| f == "BitTorrent"));
+
+    assert_eq!(aria2.add_uri("magnet:?xt=urn:btih:abc").await.unwrap(), "2089b05ecca3d829");
+
+    let active = aria2.tell_active().await.unwrap();
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].completed_length, "250");
+
+    // Every call carries its own id and the parameters as a JSON array
+    let bodies = server.bodies();
+    assert!(bodies[0].contains(r#""id":"1""#), "{}", bodies[0]);
+    assert!(bodies[1].contains(r#""id":"2""#), "{}", bodies[1]);
+    assert!(bodies[1].contains(r#""params":[["magnet:?xt=urn:btih:abc"]]"#), "{}", bodies[1]);
+    assert!(server.requests().iter().all(|r| r.starts_with("POST /jsonrpc")));
+}
+
+fn status_list(prefix: &str, count: usize, status: &str) -> String {
+    let items: Vec<String> = (0..count)
+        .map(|i| {
+            format!(
+                r#"{{"gid":"{}{}","status":"{}","totalLength":"1","completedLength":"1","downloadSpeed":"0"}}"#,
+                prefix, i, status
+            )
+        })
+        .collect();
+    format!(r#"{{"id":"1","jsonrpc":"2.0","result":[{}]}}"#, items.join(","))
+}
+
+#[tokio::test]
+async fn tell_all_merges_every_state_and_pages() {
+    let first_page = status_list("done", 100, "complete");
+    let second_page = status_list("old", 3, "error");
+    let server = StubServer::start(vec![
+        Route::new("aria2.tellActive", "application/json", ACTIVE),
+        Route::new("aria2.tellWaiting", "application/json", &status_list("queued", 2, "waiting")),
+        Route::new(r#""aria2.tellStopped","params":[0,100,"#, "application/json", &first_page),
+        Route::new(r#""aria2.tellStopped","params":[100,100,"#, "application/json", &second_page),
+    ])
+    .await;
+
+    let all = client(&server).tell_all().await.unwrap();
+    assert_eq!(all.len(), 1 + 2 + 100 + 3);
+    assert_eq!(all[0].status, "active");
+    assert_eq!(all[1].status, "waiting");
+    assert_eq!(all.last().unwrap().gid, "old2");
+    assert_eq!(server.bodies().len(), 4);
+}
+
+const RICH_ACTIVE: &str = r#"{"id":"1","jsonrpc":"2.0","result":[{
+  "gid":"2089b05ecca3d829","status":"active","totalLength":"1000","completedLength":"400",
+  "downloadSpeed":"100","uploadSpeed":"20","uploadLength":"200","connections":"12","numSeeders":"4",
+  "infoHash":"c9e15763f722f23e98a29decdfae341b98d53056","dir":"/downloads",
+  "bittorrent":{"announceList":[["udp://tracker.example:1337"]],"info":{"name":"Ubuntu 24.04"},"mode":"single"},
+  "errorCode":"0","followedBy":["3089b05ecca3d829"]
+}]}"#;
+
+#[tokio::test]
+async fn rich_status_with_key_filtering() {
+    let server = StubServer::start(vec![Route::new("aria2.tellActive", "application/json", RICH_ACTIVE)]).await;
+
+    let active = client(&server).tell_active().await.unwrap();
+    let download = &active[0];
+    assert_eq!(download.name(), "Ubuntu 24.04");
+    assert_eq!(download.ratio(), 0.5);
+    assert_eq!(download.eta(), Some(std::time::Duration::from_secs(6)));
+    assert_eq!(download.num_seeders, "4");
+    assert_eq!(download.connections, "12");
+    assert_eq!(download.dir.as_deref(), Some("/downloads"));
+    assert_eq!(download.followed_by, vec!["3089b05ecca3d829"]);
+    assert_eq!(
+        download.magnet_link().as_deref(),
+        Some("magnet:?xt=urn:btih:c9e15763f722f23e98a29decdfae341b98d53056&dn=Ubuntu%2024.04&tr=udp://tracker.example:1337")
+    );
+
+    // Only the listed keys are requested, and never the per-file details
+    let body = &server.bodies()[0];
+    assert!(body.contains(r#""bittorrent""#) && body.contains(r#""uploadSpeed""#), "{}", body);
+    assert!(!body.contains(r#""files""#), "{}", body);
+}
+
+const STATUS: &str = r#"{"id":"1","jsonrpc":"2.0","result":{"gid":"g1","status":"active","totalLength":"1000","completedLength":"500","numPieces":"8","bittorrent":{"announceList":[["udp://a:1"],["udp://b:2","udp://c:3"]],"info":{"name":"Ubuntu"}}}}"#;
+const FILES: &str = r#"{"id":"2","jsonrpc":"2.0","result":[{"index":"1","path":"/dl/Ubuntu/ubuntu.iso","length":"1000","completedLength":"250","selected":"true","uris":[]}]}"#;
+const PEERS: &str = r#"{"id":"3","jsonrpc":"2.0","result":[{"peerId":"%2DqB4630%2D%A1%B2","ip":"10.0.0.2","port":"51413","bitfield":"f0","amChoking":"true","peerChoking":"false","downloadSpeed":"1024","uploadSpeed":"0","seeder":"false"}]}"#;
+const NO_URIS: &str = r#"{"id":"4","jsonrpc":"2.0","error":{"code":1,"message":"No URI data is available"}}"#;
+
+#[tokio::test]
+async fn fetches_download_details() {
+    let server = StubServer::start(vec![
+        Route::new("aria2.tellStatus", "application/json", STATUS),
+        Route::new("aria2.getFiles", "application/json", FILES),
+        Route::new("aria2.getPeers", "application/json", PEERS),
+        Route::new("aria2.getUris", "application/json", NO_URIS),
+    ])
+    .await;
+
+    let details = client(&server).details("g1").await.unwrap();
+    assert_eq!(details.status.name(), "Ubuntu");
+    assert_eq!(details.status.bittorrent.as_ref().unwrap().announce_list.len(), 2);
+    assert_eq!(details.files[0].progress(), 0.25);
+    assert!(details.uris.is_empty());
+
+    let peer = &details.peers[0];
+    assert_eq!(peer.client(), "qBittorrent 4.6.3");
+    assert_eq!(peer.progress(8), Some(0.5));
+}
+
+#[test]
+fn decodes_peer_clients() {
+    let peer = |id: &str| PeerInfo { peer_id: id.to_string(), ..Default::default() };
+    assert_eq!(peer("A2-1-37-0-%01%02").client(), "aria2 1.37.0");
+    assert_eq!(peer("-TR4050-abcdefghijkl").client(), "Transmission 4.0.5");
+    assert_eq!(peer("-XX1234-abcdefghijkl").client(), "XX 1.2.3.4");
+    assert_eq!(peer("M7-2-2--abcdefghijk").client(), "unknown");
+}
+
+#[tokio::test]
+async fn performs_download_actions() {
+    let ok = r#"{"id":"1","jsonrpc":"2.0","result":"OK"}"#;
+    let server = StubServer::start(vec![
+        Route::new("aria2.addUri", "application/json", ADDED),
+        Route::new("aria2.removeDownloadResult", "application/json", ok),
+        Route::new("aria2.pause", "application/json", r#"{"id":"1","jsonrpc":"2.0","result":"g1"}"#),
+    ])
+    .await;
+    let aria2 = client(&server);
+
+    let message = aria2.perform(&DownloadAction::Pause { gid: "g1".to_string() }).await.unwrap();
+    assert_eq!(message, "Paused g1");
+
+    let retry = DownloadAction::Retry {
+        gid: "old".to_string(),
+        magnet: "magnet:?xt=urn:btih:abc".to_string(),
+    };
+    assert_eq!(aria2.perform(&retry).await.unwrap(), "Retrying old as 2089b05ecca3d829");
+
+    let bodies = server.bodies();
+    assert!(bodies[0].contains(r#""method":"aria2.pause","params":["g1"]"#), "{}", bodies[0]);
+    assert!(bodies[1].contains("aria2.addUri"));
+    assert!(bodies[2].contains(r#""method":"aria2.removeDownloadResult","params":["old"]"#), "{}", bodies[2]);
+}
+
+#[tokio::test]
+async fn deletes_files_only_after_aria2_has_removed_the_download() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("ubuntu.iso");
+    std::fs::write(&file, b"partial").unwrap();
+    std::fs::write(dir.path().join("ubuntu.iso.aria2"), b"control").unwrap();
+    let files = format!(
+        r#"{{"id":"1","jsonrpc":"2.0","result":[{{"index":"1","path":"{}","length":"7","completedLength":"7","selected":"true","uris":[]}}]}}"#,
+        file.display()
+    );
+    let status = |state: &str| format!(r#"{{"id":"1","jsonrpc":"2.0","result":{{"gid":"g1","status":"{}"}}}}"#, state);
+    let server = StubServer::start(vec![
+        Route::new("aria2.getFiles", "application/json", &files),
+        // Still shutting down when first asked after the removal
+        Route::new("aria2.tellStatus", "application/json", &status("active"))
+            .then(&status("active"))
+            .then(&status("removed")),
+        Route::new("aria2.forceRemove", "application/json", r#"{"id":"1","jsonrpc":"2.0","result":"g1"}"#),
+    ])
+    .await;
+
+    let remove = DownloadAction::Remove {
+        gid: "g1".to_string(),
+        delete_files: true,
+    };
+    assert_eq!(client(&server).perform(&remove).await.unwrap(), "Removed g1 and deleted 1 file(s)");
+    assert!(!file.exists());
+    assert!(!dir.path().join("ubuntu.iso.aria2").exists());
+
+    let methods: Vec<String> = server
+        .bodies()
+        .iter()
+        .map(|b| b.split(r#""method":""#).nth(1).unwrap().split('"').next().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        methods,
+        ["aria2.getFiles", "aria2.tellStatus", "aria2.forceRemove", "aria2.tellStatus", "aria2.tellStatus"]
+    );
+}
+
+#[tokio::test]
+async fn only_a_missing_gid_counts_as_removed() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("ubuntu.iso");
+    std::fs::write(&file, b"partial").unwrap();
+    let files = format!(
+        r#"{{"id":"1","jsonrpc":"2.0","result":[{{"index":"1","path":"{}","length":"7","completedLength":"7","selected":"true","uris":[]}}]}}"#,
+        file.display()
+    );
+    let active = r#"{"id":"1","jsonrpc":"2.0","result":{"gid":"g1","status":"active"}}"#;
+    let remove = DownloadAction::Remove {
+        gid: "g1".to_string(),
+        delete_files: true,
+    };
+
+    // Any other error leaves it open whether aria2 still writes, so the files stay
+    let busy = r#"{"id":"1","jsonrpc":"2.0","error":{"code":1,"message":"Too many requests"}}"#;
+    let server = StubServer::start(vec![
+        Route::new("aria2.getFiles", "application/json", &files),
+        Route::new("aria2.tellStatus", "application/json", active).then(busy),
+        Route::new("aria2.forceRemove", "application/json", r#"{"id":"1","jsonrpc":"2.0","result":"g1"}"#),
+    ])
+    .await;
+    assert!(matches!(client(&server).perform(&remove).await, Err(TorrentError::Aria2(_))));
+    assert!(file.exists());
+
+    let gone = r#"{"id":"1","jsonrpc":"2.0","error":{"code":1,"message":"GID g1 is not found"}}"#;
+    let server = StubServer::start(vec![
+        Route::new("aria2.getFiles", "application/json", &files),
+        Route::new("aria2.tellStatus", "application/json", active).then(gone),
+        Route::new("aria2.forceRemove", "application/json", r#"{"id":"1","jsonrpc":"2.0","result":"g1"}"#),
+    ])
+    .await;
+    assert_eq!(client(&server).perform(&remove).await.unwrap(), "Removed g1 and deleted 1 file(s)");
+    assert!(!file.exists());
+    assert_eq!(server.requests().len(), 4);
+}
+
+#[tokio::test]
+async fn selects_files_with_options() {
+    let ok = r#"{"id":"1","jsonrpc":"2.0","result":"OK"}"#;
+    let server = StubServer::start(vec![
+        Route::new("aria2.addUri", "application/json", ADDED),
+        Route::new("aria2.changeOption", "application/json", ok),
+        Route::new("aria2.unpause", "application/json", r#"{"id":"1","jsonrpc":"2.0","result":"g1"}"#),
+    ])
+    .await;
+    let aria2 = client(&server);
+
+    aria2
+        .add_uri_with_options("magnet:?xt=urn:btih:abc", &[("pause-metadata", "true")])
+        .await
+        .unwrap();
+    let select = DownloadAction::SelectFiles {
+        gid: "g1".to_string(),
+        files: "1,3-5".to_string(),
+        resume: true,
+    };
+    assert_eq!(aria2.perform(&select).await.unwrap(), "Selected files 1,3-5 of g1");
+
+    let bodies = server.bodies();
+    assert!(bodies[0].contains(r#"["magnet:?xt=urn:btih:abc"],{"pause-metadata":"true"}"#), "{}", bodies[0]);
+    assert!(bodies[1].contains(r#""params":["g1",{"select-file":"1,3-5"}]"#), "{}", bodies[1]);
+    assert!(bodies[2].contains(r#""method":"aria2.unpause","params":["g1"]"#), "{}", bodies[2]);
+}
+
+#[tokio::test]
+async fn sets_global_and_per_download_limits() {
+    let ok = r#"{"id":"1","jsonrpc":"2.0","result":"OK"}"#;
+    let options = r#"{"id":"1","jsonrpc":"2.0","result":{"dir":"/tmp","max-overall-download-limit":"500K","max-overall-upload-limit":"0"}}"#;
+    let server = StubServer::start(vec![
+        Route::new("aria2.changeGlobalOption", "application/json", ok),
+        Route::new("aria2.changeOption", "application/json", ok),
+        Route::new("aria2.getGlobalOption", "application/json", options),
+    ])
+    .await;
+    let aria2 = client(&server);
+
+    aria2.set_global_limits(&SpeedLimits::new(512_000, 0)).await.unwrap();
+    assert_eq!(aria2.global_limits().await.unwrap(), SpeedLimits::new(512_000, 0));
+    let limit = DownloadAction::SetLimits {
+        gid: "g1".to_string(),
+        limits: SpeedLimits { download: None, upload: Some(51_200) },
+    };
+    assert_eq!(aria2.perform(&limit).await.unwrap(), "Limited g1 to ↓unchanged ↑50K/s");
+
+    let bodies = server.bodies();
+    assert!(
+        bodies[0].contains(r#""params":[{"max-overall-download-limit":"512000","max-overall-upload-limit":"0"}]"#),
+        "{}",
+        bodies[0]
+    );
+    assert!(bodies[2].contains(r#""params":["g1",{"max-upload-limit":"51200"}]"#), "{}", bodies[2]);
+}
+
+#[tokio::test]
+async fn reorders_and_force_starts_queued_downloads() {
+    let ok = r#"{"id":"1","jsonrpc":"2.0","result":"OK"}"#;
+    let paused = r#"{"id":"1","jsonrpc":"2.0","result":{"gid":"g1","status":"paused","totalLength":"1","completedLength":"0","downloadSpeed":"0"}}"#;
+    let options = r#"{"id":"1","jsonrpc":"2.0","result":{"max-concurrent-downloads":"1"}}"#;
+    let server = StubServer::start(vec![
+        Route::new("aria2.changePosition", "application/json", r#"{"id":"1","jsonrpc":"2.0","result":0}"#),
+        Route::new("aria2.tellStatus", "application/json", paused).then(&paused.replace("paused", "active")),
+        Route::new("aria2.unpause", "application/json", r#"{"id":"1","jsonrpc":"2.0","result":"g1"}"#),
+        Route::new("aria2.tellActive", "application/json", ACTIVE),
+        Route::new("aria2.getGlobalOption", "application/json", options),
+        Route::new("aria2.changeGlobalOption", "application/json", ok),
+    ])
+    .await;
+    let aria2 = client(&server);
+
+    let up = DownloadAction::Move { gid: "g1".to_string(), to: QueueMove::Up };
+    assert_eq!(aria2.perform(&up).await.unwrap(), "Moved g1 to position 1 in the queue");
+    let force = DownloadAction::ForceStart { gid: "g1".to_string() };
+    assert_eq!(
+        aria2.perform(&force).await.unwrap(),
+        "Force started g1 beyond the limit of 1 concurrent downloads"
+    );
+
+    let bodies = server.bodies();
+    assert!(bodies[0].contains(r#""params":["g1",-1,"POS_CUR"]"#), "{}", bodies[0]);
+    assert!(bodies[2].contains(r#""params":["g1",0,"POS_SET"]"#), "{}", bodies[2]);
+    assert!(bodies[3].contains(r#""method":"aria2.unpause""#), "{}", bodies[3]);
+    assert!(bodies[6].contains(r#""params":[{"max-concurrent-downloads":"2"}]"#), "{}", bodies[6]);
+    // Put back once the download runs, so the configured limit is not lost
+    assert!(bodies[7].contains(r#""method":"aria2.tellStatus""#), "{}", bodies[7]);
+    assert!(bodies[8].contains(r#""params":[{"max-concurrent-downloads":"1"}]"#), "{}", bodies[8]);
+}
+
+#[tokio::test]
+async fn fetches_metadata_only() {
+    let dir = tempfile::tempdir().unwrap();
+    let hash = "c9e15763f722f23e98a29decdfae341b98d53056";
+    let torrent = dir.path().join(format!("{}.torrent", hash));
+    std::fs::write(&torrent, "d4:infod6:lengthi42e4:name8:file.isoee").unwrap();
+    let status = format!(
+        r#"{{"id":"1","jsonrpc":"2.0","result":{{"gid":"2089b05ecca3d829","status":"complete","dir":"{}","infoHash":"{}"}}}}"#,
+        dir.path().display(),
+        hash
+    );
+    let server = StubServer::start(vec![
+        Route::new("aria2.addUri", "application/json", ADDED),
+        Route::new("aria2.tellStatus", "application/json", &status),
+        Route::new("aria2.removeDownloadResult", "application/json", r#"{"id":"1","jsonrpc":"2.0","result":"OK"}"#),
+    ])
+    .await;
+
+    let metadata = client(&server)
+        .fetch_metadata("magnet:?xt=urn:btih:c9e1", std::time::Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(metadata.name, "file.iso");
+    assert_eq!(metadata.files[0].size_bytes, Some(42));
+    // The temporary .torrent is cleaned up
+    assert!(!torrent.exists());
+    assert!(server.bodies()[0].contains(r#"{"bt-metadata-only":"true","bt-save-metadata":"true"}"#));
+}
+
+#[tokio::test]
+async fn aborted_metadata_fetch_removes_its_download() {
+    let ok = r#"{"id":"1","jsonrpc":"2.0","result":"OK"}"#;
+    let server = StubServer::start(vec![
+        Route::new("aria2.addUri", "application/json", ADDED),
+        Route::new("aria2.tellStatus", "application/json", r#"{"id":"1","jsonrpc":"2.0","result":{"gid":"2089b05ecca3d829","status":"active"}}"#),
+        Route::new("aria2.forceRemove", "application/json", ADDED),
+        Route::new("aria2.removeDownloadResult", "application/json", ok),
+    ])
+    .await;
+    let aria2 = client(&server);
+
+    // Like a preview replaced by another while aria2 still looks for peers
+    let fetch = tokio::spawn(async move {
+        aria2.fetch_metadata("magnet:?xt=urn:btih:c9e1", std::time::Duration::from_secs(60)).await
+    });
+    while !server.bodies().iter().any(|b| b.contains("aria2.tellStatus")) {
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    fetch.abort();
+    let _ = fetch.await;
+    let cleaned_up = async {
+        while !server.bodies().iter().any(|b| b.contains("aria2.removeDownloadResult")) {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+    };
+    tokio::time::timeout(std::time::Duration::from_secs(5), cleaned_up)
+        .await
+        .expect("the metadata download was left in aria2");
+    let bodies = server.bodies();
+    assert!(bodies.iter().any(|b| b.contains(r#""method":"aria2.forceRemove","params":["2089b05ecca3d829"]"#)));
+}
+
+#[tokio::test]
+async fn saves_the_session_before_shutting_down() {
+    let ok = r#"{"id":"1","jsonrpc":"2.0","result":"OK"}"#;
+    let server = StubServer::start(vec![
+        Route::new("aria2.saveSession", "application/json", ok),
+        Route::new("aria2.shutdown", "application/json", ok),
+    ])
+    .await;
+    let aria2 = client(&server);
+
+    assert_eq!(aria2.save_session().await.unwrap(), "OK");
+    assert_eq!(aria2.shutdown().await.unwrap(), "OK");
+
+    let bodies = server.bodies();
+    assert!(bodies[0].contains(r#""method":"aria2.saveSession","params":[]"#), "{}", bodies[0]);
+    assert!(bodies[1].contains(r#""method":"aria2.shutdown","params":[]"#), "{}", bodies[1]);
+}
+
+#[tokio::test]
+async fn surfaces_rpc_errors() {
+    let server = StubServer::start(vec![Route::new("aria2.addUri", "application/json", BAD_URI)]).await;
+
+    match client(&server).add_uri("not a uri").await {
+        Err(TorrentError::Aria2(error)) => {
+            assert_eq!(error.code, 1);
+            assert_eq!(error.message, "No URI to download.");
+        }
+        other => panic!("expected an aria2 error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn sends_secret_token_first() {
+    let server = StubServer::start(vec![Route::new("aria2.addUri", "application/json", ADDED)]).await;
+    let aria2 = client(&server).with_secret(Some("s3cret".to_string()));
+
+    aria2.add_uri("magnet:?xt=urn:btih:abc").await.unwrap();
+    assert!(
+        server.bodies()[0].contains(r#""params":["token:s3cret",["magnet:?xt=urn:btih:abc"]]"#),
+        "{}",
+        server.bodies()[0]
+    );
+}
+
+#[tokio::test]
+async fn unreachable_endpoint_is_a_network_error() {
+    let aria2 = Aria2Client::new("http://127.0.0.1:9/jsonrpc");
+    assert!(matches!(aria2.get_version().await, Err(TorrentError::Network(_))));
+}
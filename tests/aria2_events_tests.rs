@@ -0,0 +1,58 @@
+use futures::SinkExt;
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+use tui_torrent::aria2_client::Aria2Client;
+use tui_torrent::aria2_events::{parse_notification, Aria2Events, Aria2Notification, DownloadEvent};
+
+#[test]
+fn parses_download_notifications() {
+    let start = r#"{"jsonrpc":"2.0","method":"aria2.onDownloadStart","params":[{"gid":"2089b05ecca3d829"}]}"#;
+    assert_eq!(
+        parse_notification(start),
+        vec![Aria2Notification::Download {
+            event: DownloadEvent::Start,
+            gid: "2089b05ecca3d829".to_string()
+        }]
+    );
+
+    let bt = r#"{"jsonrpc":"2.0","method":"aria2.onBtDownloadComplete","params":[{"gid":"a"}]}"#;
+    assert!(matches!(
+        parse_notification(bt)[..],
+        [Aria2Notification::Download { event: DownloadEvent::BtComplete, .. }]
+    ));
+
+    // Call responses and unknown methods are not notifications
+    assert!(parse_notification(r#"{"id":"1","jsonrpc":"2.0","result":"OK"}"#).is_empty());
+    assert!(parse_notification(r#"{"jsonrpc":"2.0","method":"aria2.onSomething","params":[{"gid":"a"}]}"#).is_empty());
+    assert!(parse_notification("not json").is_empty());
+}
+
+#[test]
+fn websocket_url_follows_rpc_endpoint() {
+    assert_eq!(Aria2Client::new("http://localhost:6800/jsonrpc").ws_url(), "ws://localhost:6800/jsonrpc");
+    assert_eq!(Aria2Client::new("https://nas:6800/jsonrpc").ws_url(), "wss://nas:6800/jsonrpc");
+}
+
+#[tokio::test]
+async fn listener_reports_events_and_disconnects() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}/jsonrpc", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let complete = r#"{"jsonrpc":"2.0","method":"aria2.onDownloadComplete","params":[{"gid":"g1"}]}"#;
+        socket.send(Message::Text(complete.to_string())).await.unwrap();
+        socket.close(None).await.unwrap();
+    });
+
+    let mut events = Aria2Events::spawn(&url);
+    assert_eq!(events.next().await, Some(Aria2Notification::Connected));
+    assert_eq!(
+        events.next().await,
+        Some(Aria2Notification::Download {
+            event: DownloadEvent::Complete,
+            gid: "g1".to_string()
+        })
+    );
+    assert!(matches!(events.next().await, Some(Aria2Notification::Disconnected(_))));
+}
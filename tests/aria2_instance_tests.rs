@@ -0,0 +1,224 @@
+mod common;
+
+use common::{Route, StubServer};
+use std::path::Path;
+use tui_torrent::aria2_instance::{find_free_port, port_in_use, process_alive, InstanceRecord};
+use tui_torrent::aria2_manager::Aria2Manager;
+use tui_torrent::config::Aria2Config;
+
+const VERSION: &str = r#"{"id":"1","jsonrpc":"2.0","result":{"enabledFeatures":[],"version":"1.37.0"}}"#;
+const UNAUTHORIZED: &str = r#"{"id":"1","jsonrpc":"2.0","error":{"code":1,"message":"Unauthorized"}}"#;
+const OK: &str = r#"{"id":"1","jsonrpc":"2.0","result":"OK"}"#;
+
+fn port_of(server: &StubServer) -> u16 {
+    server.base_url.rsplit(':').next().unwrap().parse().unwrap()
+}
+
+fn config_in(dir: &Path, port: u16) -> Aria2Config {
+    Aria2Config {
+        host: "127.0.0.1".to_string(),
+        port,
+        session_file: Some(dir.join("aria2.session")),
+        download_dir: Some(dir.join("downloads")),
+        ..Aria2Config::default()
+    }
+}
+
+/// getVersion answered only for `secret`, like an aria2c started with `--rpc-secret`
+fn secured_version(secret: &str) -> Vec<Route> {
+    vec![
+        Route::new(&format!(r#""aria2.getVersion","params":["token:{}"]"#, secret), "application/json", VERSION),
+        Route::new("aria2.getVersion", "application/json", UNAUTHORIZED),
+    ]
+}
+
+/// Id of a process that has already exited
+fn dead_pid() -> u32 {
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    child.wait().unwrap();
+    child.id()
+}
+
+#[test]
+fn records_round_trip_and_ports_are_probed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data").join("aria2.instance");
+    let record = InstanceRecord {
+        pid: 42,
+        port: 6801,
+        secret: "s3cret".to_string(),
+        session_file: Some(dir.path().join("aria2.session")),
+        owner_pid: std::process::id(),
+    };
+    record.save(&path).unwrap();
+    assert_eq!(InstanceRecord::load(&path), Some(record.clone()));
+    assert!(record.owner_alive());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+    InstanceRecord::remove(&path);
+    assert_eq!(InstanceRecord::load(&path), None);
+    assert!(!process_alive(dead_pid()));
+    // init is alive even when it belongs to another user and cannot be signalled
+    #[cfg(unix)]
+    assert!(process_alive(1));
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let taken = listener.local_addr().unwrap().port();
+    assert!(port_in_use(taken));
+    let free = find_free_port(taken).unwrap();
+    assert!(free > taken && !port_in_use(free));
+}
+
+#[tokio::test]
+async fn does_not_attach_to_an_aria2_without_a_secret() {
+    // Accepts any token, including the one we were configured with
+    let server = StubServer::start(vec![Route::new("aria2.getVersion", "application/json", VERSION)]).await;
+    let port = port_of(&server);
+    for secret in [None, Some("mine".to_string())] {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Aria2Config {
+            secret,
+            ..config_in(dir.path(), port)
+        };
+        let mut manager = Aria2Manager::with_config(config);
+
+        // Whether or not aria2c is installed here, the stranger's port is not used
+        let _ = manager.ensure_aria2_running().await;
+        assert!(!manager.rpc_url().contains(&format!(":{}/", port)), "{}", manager.rpc_url());
+        manager.shutdown().await;
+    }
+    assert!(!server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn uses_a_configured_aria2_that_checks_the_secret() {
+    let server = StubServer::start(secured_version("mine")).await;
+    let dir = tempfile::tempdir().unwrap();
+    let config = Aria2Config {
+        secret: Some("mine".to_string()),
+        ..config_in(dir.path(), port_of(&server))
+    };
+    let mut manager = Aria2Manager::with_config(config);
+    manager.ensure_aria2_running().await.unwrap();
+    assert!(!manager.is_managed());
+    assert_eq!(manager.rpc_url(), format!("{}/jsonrpc", server.base_url));
+
+    // A second call with a made-up token was turned away
+    let bodies = server.bodies();
+    assert_eq!(bodies.len(), 2);
+    assert!(!bodies[1].contains("token:mine"), "{}", bodies[1]);
+}
+
+#[tokio::test]
+async fn takes_over_an_aria2c_left_by_an_earlier_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let session = dir.path().join("aria2.session");
+    let options = format!(
+        r#"{{"id":"1","jsonrpc":"2.0","result":{{"save-session":"{}"}}}}"#,
+        session.display()
+    );
+    let mut routes = secured_version("s3cret");
+    routes.extend([
+        Route::new("aria2.getGlobalOption", "application/json", &options),
+        Route::new("aria2.saveSession", "application/json", OK),
+        Route::new("aria2.shutdown", "application/json", OK),
+    ]);
+    let server = StubServer::start(routes).await;
+    let config = config_in(dir.path(), 6800);
+    let path = config.instance_file().unwrap();
+    InstanceRecord {
+        pid: 4242,
+        port: port_of(&server),
+        secret: "s3cret".to_string(),
+        session_file: Some(session),
+        owner_pid: dead_pid(),
+    }
+    .save(&path)
+    .unwrap();
+
+    let mut manager = Aria2Manager::with_config(config);
+    manager.ensure_aria2_running().await.unwrap();
+    assert!(manager.is_managed());
+    assert!(manager.rpc_url().contains(&format!(":{}/", port_of(&server))));
+    assert_eq!(InstanceRecord::load(&path).unwrap().owner_pid, std::process::id());
+
+    manager.shutdown().await;
+    let bodies = server.bodies();
+    assert!(bodies[0].contains(r#""params":["token:s3cret"]"#), "{}", bodies[0]);
+    assert!(bodies.iter().any(|b| b.contains("aria2.saveSession")));
+    assert!(bodies.iter().any(|b| b.contains("aria2.shutdown")));
+    assert!(!path.exists());
+    assert!(server.requests().iter().all(|r| r.starts_with("POST /jsonrpc")));
+}
+
+#[tokio::test]
+async fn shares_an_aria2c_whose_owner_still_runs() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut routes = secured_version("s3cret");
+    routes.push(Route::new(
+        "aria2.getGlobalOption",
+        "application/json",
+        r#"{"id":"1","jsonrpc":"2.0","result":{}}"#,
+    ));
+    let server = StubServer::start(routes).await;
+    let mut owner = std::process::Command::new("sleep").arg("5").spawn().unwrap();
+    let config = config_in(dir.path(), 6800);
+    let path = config.instance_file().unwrap();
+    InstanceRecord {
+        pid: 4242,
+        port: port_of(&server),
+        secret: "s3cret".to_string(),
+        session_file: None,
+        owner_pid: owner.id(),
+    }
+    .save(&path)
+    .unwrap();
+
+    let mut manager = Aria2Manager::with_config(config);
+    manager.ensure_aria2_running().await.unwrap();
+    // Its owner shuts it down, not us
+    assert!(!manager.is_managed());
+    manager.shutdown().await;
+    assert!(path.exists());
+    assert!(server.bodies().iter().all(|b| !b.contains("aria2.shutdown")));
+
+    owner.kill().unwrap();
+    owner.wait().unwrap();
+}
+
+
+#[tokio::test]
+async fn notices_when_a_taken_over_aria2c_dies() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut routes = secured_version("s3cret");
+    routes.push(Route::new(
+        "aria2.getGlobalOption",
+        "application/json",
+        r#"{"id":"1","jsonrpc":"2.0","result":{}}"#,
+    ));
+    let server = StubServer::start(routes).await;
+    let config = config_in(dir.path(), 6800);
+    let path = config.instance_file().unwrap();
+    InstanceRecord {
+        pid: dead_pid(),
+        port: port_of(&server),
+        secret: "s3cret".to_string(),
+        session_file: None,
+        owner_pid: dead_pid(),
+    }
+    .save(&path)
+    .unwrap();
+
+    let mut manager = Aria2Manager::with_config(config);
+    manager.ensure_aria2_running().await.unwrap();
+    assert!(manager.is_managed());
+    // No longer ours, so the supervisor starts a new one
+    let reason = manager.adopted_exit().unwrap();
+    assert!(reason.contains("exited"), "{}", reason);
+    assert!(!manager.is_managed());
+    assert_eq!(manager.adopted_exit(), None);
+    assert!(!server.requests().is_empty());
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use common::{Route, StubServer};
+use tui_torrent::aria2_manager::Aria2Manager;
+use tui_torrent::aria2_supervisor::{Aria2Supervisor, SupervisorEvent};
+use tui_torrent::bandwidth::SpeedLimits;
+use tui_torrent::config::Aria2Config;
+
+const VERSION: &str = r#"{"id":"1","jsonrpc":"2.0","result":{"enabledFeatures":[],"version":"1.37.0"}}"#;
+
+fn config_for(base_url: &str) -> Aria2Config {
+    let port = base_url.rsplit(':').next().unwrap().parse().unwrap();
+    Aria2Config {
+        host: "127.0.0.1".to_string(),
+        port,
+        ..Aria2Config::default()
+    }
+}
+
+#[tokio::test]
+async fn applies_the_schedule_while_aria2_answers() {
+    let server = StubServer::start(vec![
+        Route::new("aria2.getVersion", "application/json", VERSION),
+        Route::new("aria2.changeGlobalOption", "application/json", r#"{"id":"1","jsonrpc":"2.0","result":"OK"}"#),
+    ])
+    .await;
+    let mut config = config_for(&server.base_url);
+    config.limits.download = Some("1M".to_string());
+
+    let mut supervisor = Aria2Supervisor::spawn(Aria2Manager::with_config(config));
+    assert_eq!(
+        supervisor.next().await,
+        Some(SupervisorEvent::LimitsApplied(SpeedLimits::new(1_048_576, 0)))
+    );
+    // An aria2 we did not start is left alone
+    supervisor.shutdown().await;
+    assert!(server.requests().iter().all(|r| r.starts_with("POST /jsonrpc")));
+    assert!(server.bodies().iter().all(|body| !body.contains("aria2.shutdown")));
+}
+
+#[tokio::test]
+async fn reports_an_aria2_that_stops_answering() {
+    // Nothing listens on a port that was just released
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let config = config_for(&format!("http://{}", listener.local_addr().unwrap()));
+    drop(listener);
+
+    let mut supervisor = Aria2Supervisor::spawn(Aria2Manager::with_config(config));
+    match supervisor.next().await {
+        Some(SupervisorEvent::Disconnected(reason)) => assert!(reason.contains("not responding"), "{}", reason),
+        other => panic!("expected a disconnect, got {:?}", other),
+    }
+}
@@ -0,0 +1,69 @@
+use chrono::NaiveDate;
+use tui_torrent::bandwidth::{format_speed_limit, parse_speed, to_aria2_speed, SpeedLimits};
+use tui_torrent::config::Config;
+
+#[test]
+fn parses_aria2_speeds() {
+    assert_eq!(parse_speed("500K"), Some(512_000));
+    assert_eq!(parse_speed("1.5m"), Some(1_572_864));
+    assert_eq!(parse_speed("2048"), Some(2048));
+    assert_eq!(parse_speed("0"), Some(0));
+    assert_eq!(parse_speed("fast"), None);
+    assert_eq!(parse_speed("-1K"), None);
+
+    assert_eq!(to_aria2_speed(512_000), "500K");
+    assert_eq!(to_aria2_speed(2 * 1024 * 1024), "2M");
+    assert_eq!(format_speed_limit(1_572_864), "1.5M/s");
+    assert_eq!(format_speed_limit(0), "unlimited");
+}
+
+#[test]
+fn parses_typed_limits() {
+    assert_eq!(SpeedLimits::parse("500K 50K").unwrap(), SpeedLimits::new(512_000, 51_200));
+    let download_only = SpeedLimits::parse("1M").unwrap();
+    assert_eq!(download_only.download, Some(1_048_576));
+    assert_eq!(download_only.upload, None);
+    assert_eq!(SpeedLimits::parse("- 0").unwrap().upload, Some(0));
+    assert!(SpeedLimits::parse("1M 2M 3M").is_err());
+    assert!(SpeedLimits::parse("lots").is_err());
+
+    let options = download_only.global_options();
+    assert_eq!(options, vec![("max-overall-download-limit", "1048576".to_string())]);
+    assert_eq!(download_only.to_string(), "↓1M/s ↑unchanged");
+}
+
+#[test]
+fn schedule_picks_the_limits_for_the_time() {
+    let config = Config::from_toml(
+        r#"
+        [aria2.limits]
+        upload = "100K"
+
+        [[aria2.limits.schedule]]
+        days = ["weekdays"]
+        start = "09:00"
+        end = "18:00"
+        download = "500K"
+        upload = "50K"
+
+        [[aria2.limits.schedule]]
+        days = ["sat"]
+        start = "23:00"
+        end = "07:00"
+        download = "2M"
+        "#,
+    )
+    .unwrap();
+    assert!(config.validate().is_ok());
+    let limits = &config.aria2.limits;
+    let at = |day: u32, hour: u32, min: u32| NaiveDate::from_ymd_opt(2024, 6, day).unwrap().and_hms_opt(hour, min, 0).unwrap();
+
+    // 2024-06-03 is a Monday, 2024-06-08 a Saturday
+    assert_eq!(limits.limits_at(at(3, 12, 0)), SpeedLimits::new(512_000, 51_200));
+    assert_eq!(limits.limits_at(at(3, 18, 0)), SpeedLimits::new(0, 102_400));
+    assert_eq!(limits.limits_at(at(8, 12, 0)), SpeedLimits::new(0, 102_400));
+    // The Saturday night window carries on into Sunday morning
+    assert_eq!(limits.limits_at(at(8, 23, 30)), SpeedLimits::new(2_097_152, 0));
+    assert_eq!(limits.limits_at(at(9, 6, 59)), SpeedLimits::new(2_097_152, 0));
+    assert_eq!(limits.limits_at(at(10, 6, 59)), SpeedLimits::new(0, 102_400));
+}
@@ -0,0 +1,132 @@
+//! Minimal HTTP server serving canned responses to provider clients under test
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// A canned response, served to requests whose path and query (or body) contain `needle`
+#[derive(Clone)]
+pub struct Route {
+    pub needle: String,
+    pub content_type: String,
+    pub body: String,
+    /// Bodies served to later matching requests in turn; the last one repeats
+    pub later: Vec<String>,
+    served: Arc<AtomicUsize>,
+}
+
+impl Route {
+    pub fn new(needle: &str, content_type: &str, body: &str) -> Self {
+        Self {
+            needle: needle.to_string(),
+            content_type: content_type.to_string(),
+            body: body.to_string(),
+            later: Vec::new(),
+            served: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Serve `body` from the next matching request on, e.g. a download changing state
+    #[allow(dead_code)]
+    pub fn then(mut self, body: &str) -> Self {
+        self.later.push(body.to_string());
+        self
+    }
+
+    fn next_body(&self) -> &str {
+        match self.served.fetch_add(1, Ordering::SeqCst) {
+            0 => &self.body,
+            n => self.later.get(n - 1).or(self.later.last()).unwrap_or(&self.body),
+        }
+    }
+}
+
+pub struct StubServer {
+    pub base_url: String,
+    /// Request lines ("GET /path?query HTTP/1.1") in arrival order
+    pub requests: Arc<Mutex<Vec<String>>>,
+    /// Request bodies in arrival order
+    pub bodies: Arc<Mutex<Vec<String>>>,
+}
+
+impl StubServer {
+    /// Start serving the routes on a random local port; unmatched requests get a 404
+    pub async fn start(routes: Vec<Route>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind stub server");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&requests);
+        let seen_bodies = Arc::clone(&bodies);
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    break;
+                };
+                let routes = routes.clone();
+                let seen = Arc::clone(&seen);
+                let seen_bodies = Arc::clone(&seen_bodies);
+                tokio::spawn(async move {
+                    let mut buffer = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    while !buffer.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let header_end = buffer.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+                    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+                    let request_line = head.lines().next().unwrap_or_default().to_string();
+                    let content_length = head
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length").then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    while buffer.len() < header_end + content_length {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let body = String::from_utf8_lossy(&buffer[header_end..]).to_string();
+                    seen.lock().unwrap().push(request_line.clone());
+                    seen_bodies.lock().unwrap().push(body.clone());
+
+                    let response = match routes
+                        .iter()
+                        .find(|r| request_line.contains(&r.needle) || body.contains(&r.needle))
+                    {
+                        Some(route) => {
+                            let body = route.next_body();
+                            format!(
+                                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                                route.content_type,
+                                body.len(),
+                                body
+                            )
+                        }
+                        None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+                    };
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+
+        Self { base_url, requests, bodies }
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    #[allow(dead_code)]
+    pub fn bodies(&self) -> Vec<String> {
+        self.bodies.lock().unwrap().clone()
+    }
+}
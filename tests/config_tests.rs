@@ -0,0 +1,195 @@
+use std::path::PathBuf;
+use tui_torrent::config::{CliOptions, Config, ConfigError};
+use tui_torrent::torrent_search::TorrentSearchEngine;
+
+fn args(list: &[&str]) -> Vec<String> {
+    list.iter().map(|a| a.to_string()).collect()
+}
+
+#[test]
+fn partial_file_keeps_defaults() {
+    let config = Config::from_toml(
+        r#"
+        [aria2]
+        port = 6801
+
+        [search]
+        max_results = 10
+
+        [providers.x1337]
+        enabled = false
+
+        [[providers.torznab]]
+        url = "http://localhost:9117/api"
+        api_key = "secret"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.aria2.port, 6801);
+    assert_eq!(config.aria2.host, "localhost");
+    assert_eq!(config.aria2.rpc_url(), "http://localhost:6801/jsonrpc");
+    assert_eq!(config.aria2.secret, None);
+    assert!(!config.aria2.rpc_listen_all);
+    assert_eq!(config.search.max_results, 10);
+    assert_eq!(config.search.source_timeout_secs, 15);
+    assert!(!config.providers.x1337.enabled);
+    assert!(config.providers.yts.trackers.as_ref().is_some_and(|t| !t.is_empty()));
+    assert_eq!(config.providers.torznab[0].name, "Torznab");
+    assert!(config.validate().is_ok());
+
+    let engine = TorrentSearchEngine::from_config(&config);
+    let names: Vec<_> = engine.sources().map(|s| s.name().to_string()).collect();
+    assert_eq!(names, vec!["YTS", "PirateBay", "Torznab"]);
+    assert_eq!(engine.max_results(), 10);
+}
+
+#[test]
+fn session_file_defaults_to_the_data_dir() {
+    let config = Config::default();
+    assert_eq!(config.aria2.save_session_interval, 60);
+    if let Some(path) = config.aria2.session_file() {
+        assert!(path.ends_with("tui-torrent/aria2.session"), "{}", path.display());
+    }
+
+    let config = Config::from_toml("[aria2]\nsession_file = \"/srv/aria2.session\"\nsave_session_interval = 0").unwrap();
+    assert_eq!(config.aria2.session_file(), Some(PathBuf::from("/srv/aria2.session")));
+    // The secret goes to aria2c through a config file next to it, not its command line
+    assert_eq!(config.aria2.conf_file(), PathBuf::from("/srv/aria2.conf"));
+    assert_eq!(config.aria2.save_session_interval, 0);
+}
+
+#[test]
+fn rejects_unknown_keys_and_bad_types() {
+    assert!(Config::from_toml("[aria2]\nprot = 6800").unwrap_err().contains("prot"));
+    assert!(Config::from_toml("[search]\nmax_results = \"many\"").is_err());
+}
+
+#[test]
+fn validation_reports_every_problem() {
+    let config = Config::from_toml(
+        r#"
+        [aria2]
+        port = 0
+        split = 0
+
+        [providers.yts]
+        mirrors = ["ftp://example.com"]
+        "#,
+    )
+    .unwrap();
+
+    match config.validate() {
+        Err(ConfigError::Invalid(problems)) => {
+            assert_eq!(problems.len(), 3, "{:?}", problems);
+            assert!(problems.iter().any(|p| p.contains("aria2.port")));
+            assert!(problems.iter().any(|p| p.contains("aria2.split")));
+            assert!(problems.iter().any(|p| p.contains("ftp://example.com")));
+        }
+        other => panic!("expected validation error, got {:?}", other),
+    }
+}
+
+#[test]
+fn validates_the_bandwidth_schedule_and_log_level() {
+    let config = Config::from_toml(
+        r#"
+        [aria2]
+        log_level = "chatty"
+
+        [aria2.limits]
+        download = "fast"
+
+        [[aria2.limits.schedule]]
+        days = ["someday"]
+        start = "9am"
+        end = "18:00"
+        upload = "50K"
+        "#,
+    )
+    .unwrap();
+
+    match config.validate() {
+        Err(ConfigError::Invalid(problems)) => {
+            assert_eq!(problems.len(), 4, "{:?}", problems);
+            assert!(problems.iter().any(|p| p.contains("aria2.log_level")));
+            assert!(problems.iter().any(|p| p.contains("aria2.limits.download")));
+            assert!(problems.iter().any(|p| p.contains("'9am'")));
+            assert!(problems.iter().any(|p| p.contains("'someday'")));
+        }
+        other => panic!("expected validation error, got {:?}", other),
+    }
+}
+
+#[test]
+fn source_names_must_be_unique() {
+    let config = Config::from_toml(
+        r#"
+        [providers.x1337]
+        enabled = false
+
+        [[providers.torznab]]
+        url = "http://localhost:9117/a"
+
+        [[providers.torznab]]
+        url = "http://localhost:9117/b"
+
+        [[providers.torznab]]
+        name = "yts"
+        url = "http://localhost:9117/c"
+
+        [[providers.torznab]]
+        name = "1337x"
+        url = "http://localhost:9117/d"
+        "#,
+    )
+    .unwrap();
+
+    match config.validate() {
+        Err(ConfigError::Invalid(problems)) => {
+            // The second default "Torznab" and "yts"; 1337x is free while the built-in one is off
+            assert_eq!(problems.len(), 2, "{:?}", problems);
+            assert!(problems.iter().any(|p| p.contains("'Torznab'")));
+            assert!(problems.iter().any(|p| p.contains("'yts'")));
+        }
+        other => panic!("expected validation error, got {:?}", other),
+    }
+}
+
+#[test]
+fn command_line_overrides_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "[aria2]\nhost = \"nas\"\nport = 6801\n").unwrap();
+
+    let cli = CliOptions::parse(args(&[
+        "--config",
+        path.to_str().unwrap(),
+        "--rpc-port=7000",
+        "--rpc-secret",
+        "hunter2",
+        "-d",
+        "/tmp/dl",
+        "--max-results",
+        "5",
+    ]))
+    .unwrap();
+    let config = Config::load(&cli).unwrap();
+
+    assert_eq!(config.aria2.host, "nas");
+    assert_eq!(config.aria2.port, 7000);
+    assert_eq!(config.aria2.secret.as_deref(), Some("hunter2"));
+    assert_eq!(config.aria2.download_dir, Some(PathBuf::from("/tmp/dl")));
+    assert_eq!(config.search.max_results, 5);
+}
+
+#[test]
+fn command_line_errors() {
+    assert!(matches!(CliOptions::parse(args(&["--bogus"])), Err(ConfigError::Cli(_))));
+    assert!(matches!(CliOptions::parse(args(&["--rpc-port", "abc"])), Err(ConfigError::Cli(_))));
+    assert!(matches!(CliOptions::parse(args(&["--config"])), Err(ConfigError::Cli(_))));
+
+    // An explicitly requested file has to exist
+    let cli = CliOptions::parse(args(&["--config", "/nonexistent/tui-torrent.toml"])).unwrap();
+    assert!(matches!(Config::load(&cli), Err(ConfigError::Io { .. })));
+}
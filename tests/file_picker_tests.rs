@@ -0,0 +1,73 @@
+use tui_torrent::file_picker::{FilePicker, PickerFile, PickerRowKind, PickerTarget};
+
+fn file(index: u32, path: &str, length: u64) -> PickerFile {
+    PickerFile {
+        index,
+        path: path.to_string(),
+        length,
+        selected: true,
+    }
+}
+
+fn pack() -> FilePicker {
+    FilePicker::new(
+        PickerTarget::Download {
+            gid: "g1".to_string(),
+            resume_after: false,
+        },
+        vec![
+            file(3, "Pack/S01/e02.mkv", 200),
+            file(1, "Pack/S01/e01.mkv", 100),
+            file(4, "Pack/S02/e01.mkv", 300),
+            file(2, "Pack/sample.txt", 1),
+            file(5, "Pack/S02/e02.mkv", 400),
+        ],
+    )
+}
+
+#[test]
+fn builds_a_tree_of_directories_and_files() {
+    let picker = pack();
+    let rows: Vec<String> = picker
+        .rows
+        .iter()
+        .map(|row| format!("{}{}", "  ".repeat(row.depth), row.name))
+        .collect();
+    assert_eq!(
+        rows,
+        vec!["Pack", "  S01", "    e01.mkv", "    e02.mkv", "  S02", "    e01.mkv", "    e02.mkv", "  sample.txt"]
+    );
+    assert_eq!(picker.rows[1].kind, PickerRowKind::Dir("Pack/S01/".to_string()));
+}
+
+#[test]
+fn toggling_directories_and_compacting_the_selection() {
+    let mut picker = pack();
+    assert_eq!(picker.select_file_option().as_deref(), Some("1-5"));
+
+    // Untick season 2, then the sample file
+    picker.cursor = 4;
+    picker.toggle();
+    assert_eq!(picker.dir_selection("Pack/S02/"), Some(false));
+    assert_eq!(picker.dir_selection("Pack/"), None);
+    picker.cursor = 7;
+    picker.toggle();
+    assert_eq!(picker.select_file_option().as_deref(), Some("1,3"));
+    assert_eq!(picker.selected_size(), 300);
+
+    // A partly selected directory is filled up first
+    picker.cursor = 0;
+    picker.toggle();
+    assert_eq!(picker.selected_count(), 5);
+
+    picker.toggle_all();
+    assert_eq!(picker.select_file_option(), None);
+}
+
+#[test]
+fn preselects_files_by_trailing_path() {
+    let mut picker = pack();
+    picker.preselect(&["S02/e01.mkv".to_string(), "sample.txt".to_string()]);
+    assert_eq!(picker.select_file_option().as_deref(), Some("2,4"));
+    assert!(!picker.all_selected());
+}
@@ -0,0 +1,43 @@
+use tui_torrent::log_buffer::{LogBuffer, LogLevel, LogSource};
+
+#[test]
+fn keeps_the_newest_lines() {
+    let log = LogBuffer::new(3);
+    for i in 0..5 {
+        log.push(LogSource::App, LogLevel::Info, format!("line {}", i));
+    }
+    let messages: Vec<String> = log.entries().into_iter().map(|e| e.message).collect();
+    assert_eq!(messages, vec!["line 2", "line 3", "line 4"]);
+}
+
+#[test]
+fn classifies_aria2_output() {
+    assert_eq!(
+        LogLevel::of_aria2_line("06/03 12:00:00 [ERROR] IPv4 RPC: failed to bind TCP port 6800"),
+        LogLevel::Error
+    );
+    assert_eq!(LogLevel::of_aria2_line("06/03 12:00:01 [WARN] Neither --rpc-secret nor --rpc-user is set"), LogLevel::Warn);
+    assert_eq!(LogLevel::of_aria2_line("06/03 12:00:02 [NOTICE] Download complete: /tmp/a.iso"), LogLevel::Info);
+    assert_eq!(LogLevel::of_status("Failed to add torrent: timeout"), LogLevel::Error);
+    assert_eq!(LogLevel::of_status("Added torrent: Ubuntu"), LogLevel::Info);
+}
+
+#[test]
+fn captures_piped_output_and_finds_the_last_error() {
+    let log = LogBuffer::default();
+    let output = "06/03 12:00:00 [NOTICE] IPv4 RPC: listening on TCP port 6800\n\n06/03 12:00:01 [ERROR] Exception caught\n";
+    log.capture_aria2(std::io::Cursor::new(output.as_bytes().to_vec()));
+    for _ in 0..100 {
+        if log.len() == 2 {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let entries = log.entries();
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().all(|e| e.source == LogSource::Aria2));
+    assert_eq!(log.last_at(LogLevel::Warn).unwrap().message, "06/03 12:00:01 [ERROR] Exception caught");
+    assert!(entries[0].matches("listening ON"));
+    assert!(!entries[0].matches("error"));
+}
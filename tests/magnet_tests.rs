@@ -0,0 +1,86 @@
+use tui_torrent::magnet::{info_hash, normalize_info_hash, trackers, with_trackers};
+use tui_torrent::torrent_search::{merge_duplicates, TorrentSearchResult};
+
+const HEX: &str = "c9e15763f722f23e98a29decdfae341b98d53056";
+const BASE32: &str = "ZHQVOY7XELZD5GFCTXWN7LRUDOMNKMCW";
+
+fn result(source: &str, seeders: u32, leechers: u32, magnet_link: &str) -> TorrentSearchResult {
+    TorrentSearchResult {
+        name: format!("Release from {}", source),
+        size: "1 GB".into(),
+        seeders,
+        leechers,
+        magnet_link: magnet_link.into(),
+        source: source.into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn info_hash_accepts_hex_and_base32() {
+    let upper = format!("magnet:?xt=urn:btih:{}&dn=test", HEX.to_uppercase());
+    assert_eq!(info_hash(&upper).as_deref(), Some(HEX));
+
+    let base32 = format!("magnet:?dn=test&xt=urn:btih:{}", BASE32);
+    assert_eq!(info_hash(&base32).as_deref(), Some(HEX));
+    assert_eq!(normalize_info_hash(&BASE32.to_lowercase()).as_deref(), Some(HEX));
+
+    assert_eq!(info_hash("magnet:?xt=urn:btih:TEST"), None);
+    assert_eq!(info_hash("not a magnet"), None);
+}
+
+#[test]
+fn trackers_are_decoded_and_merged_without_duplicates() {
+    let link = format!("magnet:?xt=urn:btih:{}&tr=udp%3A%2F%2Fa.example%3A80", HEX);
+    assert_eq!(trackers(&link), vec!["udp://a.example:80".to_string()]);
+
+    let merged = with_trackers(&link, &["udp://a.example:80".into(), "udp://b.example:80".into()]);
+    assert_eq!(trackers(&merged), vec!["udp://a.example:80".to_string(), "udp://b.example:80".to_string()]);
+}
+
+#[test]
+fn duplicates_are_merged_across_sources() {
+    let results = vec![
+        result("PirateBay", 10, 50, &format!("magnet:?xt=urn:btih:{}&tr=udp://a.example:80", HEX)),
+        result("YTS", 3, 0, "magnet:?xt=urn:btih:0000000000000000000000000000000000000000"),
+        result("1337x", 40, 5, &format!("magnet:?xt=urn:btih:{}&tr=udp://b.example:80", BASE32)),
+    ];
+
+    let merged = merge_duplicates(results);
+    assert_eq!(merged.len(), 2);
+
+    let release = &merged[0];
+    assert_eq!(release.source, "PirateBay");
+    assert_eq!(release.sources, vec!["PirateBay".to_string(), "1337x".to_string()]);
+    assert_eq!(release.seeders, 40);
+    assert_eq!(release.leechers, 50);
+    assert_eq!(
+        trackers(&release.magnet_link),
+        vec!["udp://a.example:80".to_string(), "udp://b.example:80".to_string()]
+    );
+    assert_eq!(merged[1].sources, vec!["YTS".to_string()]);
+}
+
+#[test]
+fn results_without_hash_are_kept_apart() {
+    let results = vec![
+        result("A", 1, 1, "magnet:?xt=urn:btih:TEST"),
+        result("B", 1, 1, "magnet:?xt=urn:btih:TEST"),
+    ];
+    assert_eq!(merge_duplicates(results).len(), 2);
+}
+
+#[test]
+fn merged_entry_keeps_known_metadata() {
+    let mut first = result("PirateBay", 1, 1, &format!("magnet:?xt=urn:btih:{}", HEX));
+    first.size = "1.4 GB".into();
+    let mut second = result("YTS", 1, 1, &format!("magnet:?xt=urn:btih:{}", HEX));
+    second.uploader = Some("someone".into());
+    second.file_count = Some(3);
+
+    let merged = merge_duplicates(vec![first, second]);
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].size_bytes, Some(1_400_000_000));
+    assert_eq!(merged[0].uploader.as_deref(), Some("someone"));
+    assert_eq!(merged[0].file_count, Some(3));
+}
@@ -0,0 +1,41 @@
+use tui_torrent::api::TorrentFileEntry;
+use tui_torrent::preview::is_suspicious;
+use tui_torrent::torrent_file::{Bencode, TorrentMetadata};
+
+const MULTI_FILE: &[u8] = b"d8:announce3:url4:infod5:filesld6:lengthi100e4:pathl3:S015:a.mkveed6:lengthi7e4:pathl9:setup.exeeee4:name4:Pack12:piece lengthi16384eee";
+
+#[test]
+fn reads_files_in_torrent_order() {
+    let metadata = TorrentMetadata::from_bytes(MULTI_FILE).unwrap();
+    assert_eq!(metadata.name, "Pack");
+    assert_eq!(
+        metadata.files,
+        vec![
+            TorrentFileEntry { path: "Pack/S01/a.mkv".to_string(), size_bytes: Some(100) },
+            TorrentFileEntry { path: "Pack/setup.exe".to_string(), size_bytes: Some(7) },
+        ]
+    );
+
+    let single = TorrentMetadata::from_bytes(b"d4:infod6:lengthi42e4:name8:file.isoee").unwrap();
+    assert_eq!(single.files, vec![TorrentFileEntry { path: "file.iso".to_string(), size_bytes: Some(42) }]);
+}
+
+#[test]
+fn rejects_malformed_bencode() {
+    assert!(Bencode::decode(b"i12").is_err());
+    assert!(Bencode::decode(b"5:abc").is_err());
+    assert!(Bencode::decode(b"di1e3:fooe").is_err());
+    assert!(Bencode::decode(b"le extra").is_err());
+    assert!(Bencode::decode(&[b'l'; 1000]).is_err());
+    // A length that overflows instead of merely running past the end
+    assert!(Bencode::decode(b"18446744073709551615:abc").is_err());
+    assert!(TorrentMetadata::from_bytes(b"d3:foo3:bare").is_err());
+}
+
+#[test]
+fn flags_executables_and_scripts() {
+    assert!(is_suspicious("Movie/Movie.mp4.exe"));
+    assert!(is_suspicious("Codec/INSTALL.BAT"));
+    assert!(!is_suspicious("Movie/Movie.mkv"));
+    assert!(!is_suspicious("README"));
+}
@@ -0,0 +1,132 @@
+mod common;
+
+use common::{Route, StubServer};
+use std::time::Duration;
+use tui_torrent::api::{PirateBayClient, TorrentSource, X1337Client, YtsClient};
+use tui_torrent::types::Category;
+
+/// Nothing listens here, so requests fail straight away
+const DEAD_MIRROR: &str = "http://127.0.0.1:9";
+
+const LISTING: &str = r#"<html><body><table><tbody>
+<tr>
+  <td class="coll-1 name"><a href="/sub/1/0/" class="icon"></a><a href="/torrent/42/ubuntu/">Ubuntu 24.04</a></td>
+  <td class="coll-2 seeds">77</td>
+  <td class="coll-3 leeches">8</td>
+  <td class="coll-date">Jan. 5th '24</td>
+  <td class="coll-4 size">5.7 GB<span class="seeds">77</span></td>
+  <td class="coll-5 user"><a href="/user/someone/">someone</a></td>
+</tr>
+</tbody></table></body></html>"#;
+
+const DETAIL: &str = r#"<html><body>
+<a href="magnet:?xt=urn:btih:c9e15763f722f23e98a29decdfae341b98d53056&dn=ubuntu">Magnet Download</a>
+</body></html>"#;
+
+const APIBAY: &str = r#"[{"id":"1","name":"Ubuntu 24.04","info_hash":"C9E15763F722F23E98A29DECDFAE341B98D53056","leechers":"4","seeders":"50","num_files":"1","size":"6114656256","username":"someone","added":"1704067200","status":"vip","category":"303","imdb":""}]"#;
+
+const FILES_PAGE: &str = r#"<html><body><div id="files"><ul>
+<li><i class="flaticon-folder"></i>Season 1<ul>
+  <li><i class="flaticon-video"></i>Show.S01E01.mkv (1.4 GB)</li>
+  <li><i class="flaticon-file"></i>readme.txt (2 KB)</li>
+</ul></li>
+</ul></div></body></html>"#;
+
+const APIBAY_FILES: &str = r#"[{"name":["Ubuntu 24.04.iso"],"size":[6114656256]},{"name":["readme.txt"],"size":[120]}]"#;
+
+const YTS: &str = r#"{"status":"ok","data":{"movie_count":0}}"#;
+
+#[tokio::test]
+async fn x1337_fetches_magnets_from_the_mirror_that_answered() {
+    let server = StubServer::start(vec![
+        Route::new("/search/ubuntu/1/", "text/html", LISTING),
+        Route::new("/torrent/42/ubuntu/", "text/html", DETAIL),
+    ])
+    .await;
+    let client = X1337Client::with_mirrors(vec![DEAD_MIRROR.to_string(), server.base_url.clone()])
+        .with_request_delay(Duration::ZERO);
+
+    let results = client.search("ubuntu", None).await.unwrap();
+    assert_eq!(results.len(), 1);
+    let result = &results[0];
+    assert_eq!(result.name, "Ubuntu 24.04");
+    assert_eq!(result.seeders, 77);
+    assert_eq!(result.size, "5.7 GB");
+    assert_eq!(result.size_bytes, Some(5_700_000_000));
+    assert_eq!(result.uploader.as_deref(), Some("someone"));
+    assert!(result.uploaded_at.is_some());
+    assert!(result.magnet_link.starts_with("magnet:?xt=urn:btih:c9e1"));
+
+    let requests = server.requests();
+    assert!(requests.iter().any(|r| r.contains("/torrent/42/ubuntu/")));
+}
+
+#[tokio::test]
+async fn x1337_reports_failure_when_every_mirror_is_down() {
+    let client = X1337Client::with_mirrors(vec![DEAD_MIRROR.to_string()]).with_request_delay(Duration::ZERO);
+    assert!(client.search("ubuntu", None).await.is_err());
+}
+
+#[tokio::test]
+async fn piratebay_falls_back_to_next_mirror() {
+    let server = StubServer::start(vec![Route::new("/q.php?q=ubuntu", "application/json", APIBAY)]).await;
+    let client = PirateBayClient::with_mirrors(vec![DEAD_MIRROR.to_string(), format!("{}/", server.base_url)]);
+
+    let results = client.search("ubuntu", None).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].seeders, 50);
+    assert_eq!(results[0].size_bytes, Some(6114656256));
+    assert_eq!(results[0].file_count, Some(1));
+}
+
+#[tokio::test]
+async fn piratebay_searches_top_level_categories() {
+    let video = r#"[
+        {"id":"1","name":"Show S01E01 1080p","info_hash":"C9E15763F722F23E98A29DECDFAE341B98D53056","leechers":"4","seeders":"50","num_files":"1","size":"1","username":"","added":"0","status":"","category":"208","imdb":""},
+        {"id":"2","name":"Film 2160p","info_hash":"D9E15763F722F23E98A29DECDFAE341B98D53056","leechers":"4","seeders":"60","num_files":"1","size":"1","username":"","added":"0","status":"","category":"211","imdb":""}
+    ]"#;
+    let server = StubServer::start(vec![Route::new("/q.php?q=show", "application/json", video)]).await;
+    let client = PirateBayClient::with_mirrors(vec![server.base_url.clone()]);
+
+    let results = client.search("show", Some(Category::TV)).await.unwrap();
+    assert!(server.requests()[0].contains("cat=200"), "{:?}", server.requests());
+    // Video also holds movies, which a TV search leaves out
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].name, "Show S01E01 1080p");
+}
+
+#[tokio::test]
+async fn yts_uses_configured_base_url() {
+    let server = StubServer::start(vec![Route::new("/api/v2/list_movies.json", "application/json", YTS)]).await;
+    let client = YtsClient::with_mirrors(vec![format!("{}/api/v2", server.base_url)]);
+
+    let results = client.search("nothing", None).await.unwrap();
+    assert!(results.is_empty());
+    assert!(server.requests()[0].contains("query_term=nothing"));
+}
+
+#[tokio::test]
+async fn providers_list_the_files_of_a_result() {
+    let server = StubServer::start(vec![
+        Route::new("/q.php?q=ubuntu", "application/json", APIBAY),
+        Route::new("/f.php?id=1", "application/json", APIBAY_FILES),
+        Route::new("/torrent/42/show/", "text/html", FILES_PAGE),
+    ])
+    .await;
+
+    let piratebay = PirateBayClient::with_mirrors(vec![server.base_url.clone()]);
+    let result = piratebay.search("ubuntu", None).await.unwrap().remove(0);
+    assert_eq!(result.details_url, Some(format!("{}/f.php?id=1", server.base_url)));
+    let files = TorrentSource::details(&piratebay, &result).await.unwrap().files;
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].path, "Ubuntu 24.04.iso");
+    assert_eq!(files[0].size_bytes, Some(6114656256));
+
+    let x1337 = X1337Client::with_mirrors(vec![server.base_url.clone()]);
+    let files = x1337.files(&format!("{}/torrent/42/show/", server.base_url)).await.unwrap();
+    let paths: Vec<_> = files.iter().map(|f| (f.path.as_str(), f.size_bytes)).collect();
+    assert_eq!(
+        paths,
+        vec![("Season 1/Show.S01E01.mkv", Some(1_400_000_000)), ("Season 1/readme.txt", Some(2_000))]
+    );
+}
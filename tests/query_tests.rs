@@ -0,0 +1,67 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use tui_torrent::query::{parse_query, Comparison, QueryError};
+use tui_torrent::torrent_search::TorrentSearchResult;
+
+fn result(name: &str, sources: &[&str], seeders: u32, size_bytes: Option<u64>) -> TorrentSearchResult {
+    TorrentSearchResult {
+        name: name.into(),
+        seeders,
+        size_bytes,
+        source: sources[0].into(),
+        sources: sources.iter().map(|s| s.to_string()).collect(),
+        uploaded_at: Some(Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn splits_text_from_filters() {
+    let parsed = parse_query(
+        "ubuntu size:<4GB seeders:>20 source:piratebay -source:1337x quality:1080p after:2024-01-01 desktop",
+    )
+    .unwrap();
+
+    assert_eq!(parsed.text, "ubuntu desktop");
+    assert_eq!(parsed.filter.size, vec![(Comparison::Less, 4_000_000_000)]);
+    assert_eq!(parsed.filter.seeders, vec![(Comparison::Greater, 20)]);
+    assert_eq!(parsed.filter.include_sources, vec!["piratebay".to_string()]);
+    assert_eq!(parsed.filter.exclude_sources, vec!["1337x".to_string()]);
+    assert_eq!(parsed.filter.quality, vec!["1080p".to_string()]);
+    assert_eq!(parsed.filter.after, NaiveDate::from_ymd_opt(2024, 1, 1));
+}
+
+#[test]
+fn unknown_prefixes_stay_in_text() {
+    let parsed = parse_query("Re:Zero -extended").unwrap();
+    assert_eq!(parsed.text, "Re:Zero -extended");
+    assert!(parsed.filter.is_empty());
+}
+
+#[test]
+fn reports_parse_errors() {
+    assert_eq!(
+        parse_query("ubuntu size:<lots"),
+        Err(QueryError::InvalidValue { filter: "size".into(), value: "<lots".into() })
+    );
+    assert!(matches!(parse_query("ubuntu after:yesterday"), Err(QueryError::InvalidValue { .. })));
+    assert_eq!(parse_query("ubuntu -seeders:>5"), Err(QueryError::UnsupportedNegation("seeders".into())));
+    assert_eq!(parse_query("seeders:>5"), Err(QueryError::MissingSearchTerms));
+}
+
+#[test]
+fn filter_matches_results() {
+    let filter = parse_query("x size:<4GB seeders:>=20 -source:1337x quality:1080p before:2024-06-01")
+        .unwrap()
+        .filter;
+
+    assert!(filter.matches(&result("Movie 1080p", &["PirateBay"], 20, Some(2_000_000_000))));
+    assert!(!filter.matches(&result("Movie 720p", &["PirateBay"], 20, Some(2_000_000_000))));
+    assert!(!filter.matches(&result("Movie 1080p", &["PirateBay"], 19, Some(2_000_000_000))));
+    assert!(!filter.matches(&result("Movie 1080p", &["PirateBay"], 20, Some(5_000_000_000))));
+    assert!(!filter.matches(&result("Movie 1080p", &["PirateBay"], 20, None)));
+    assert!(!filter.matches(&result("Movie 1080p", &["1337x"], 20, Some(2_000_000_000))));
+    // Merged entries remain visible through their other sources
+    assert!(filter.matches(&result("Movie 1080p", &["1337x", "YTS"], 20, Some(2_000_000_000))));
+    assert!(filter.allows_source("PirateBay"));
+    assert!(!filter.allows_source("1337x"));
+}
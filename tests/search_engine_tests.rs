@@ -0,0 +1,188 @@
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tui_torrent::api::{SearchRequest, SourceCapabilities, SourceResult, TorrentSource};
+use tui_torrent::torrent_search::{SourceState, TorrentSearchEngine, TorrentSearchResult};
+use tui_torrent::types::Category;
+
+struct FakeSource {
+    name: &'static str,
+    seeders: u32,
+    paging: bool,
+    delay: Duration,
+}
+
+#[async_trait]
+impl TorrentSource for FakeSource {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn capabilities(&self) -> SourceCapabilities {
+        SourceCapabilities {
+            paging: self.paging,
+            ..SourceCapabilities::default()
+        }
+    }
+
+    async fn prepare(&self) -> SourceResult<()> {
+        tokio::time::sleep(self.delay).await;
+        Ok(())
+    }
+
+    async fn search(&self, request: &SearchRequest) -> SourceResult<Vec<TorrentSearchResult>> {
+        tokio::time::sleep(self.delay).await;
+        if self.name == "Broken" {
+            return Err("boom".into());
+        }
+        Ok(vec![TorrentSearchResult {
+            name: format!("{} page {}", request.query, request.page),
+            size: "1 GB".into(),
+            seeders: self.seeders,
+            leechers: 0,
+            magnet_link: "magnet:?xt=urn:btih:TEST".into(),
+            source: self.name.into(),
+            ..Default::default()
+        }])
+    }
+}
+
+fn fake(name: &'static str, seeders: u32, paging: bool) -> Box<dyn TorrentSource> {
+    Box::new(FakeSource { name, seeders, paging, delay: Duration::ZERO })
+}
+
+fn slow(name: &'static str, delay: Duration) -> Box<dyn TorrentSource> {
+    Box::new(FakeSource { name, seeders: 1, paging: false, delay })
+}
+
+#[test]
+fn default_engine_registers_builtin_sources() {
+    let engine = TorrentSearchEngine::new();
+    let names: Vec<&str> = engine.sources().map(|s| s.name()).collect();
+    assert_eq!(names, vec!["YTS", "PirateBay", "1337x"]);
+}
+
+#[tokio::test]
+async fn registered_sources_are_merged_by_seeders() {
+    let engine = TorrentSearchEngine::empty()
+        .with_source(fake("Low", 5, false))
+        .with_source(fake("High", 50, false));
+
+    let results = engine.search_torrents("ubuntu", None).await.unwrap();
+    let sources: Vec<&str> = results.iter().map(|r| r.source.as_str()).collect();
+    assert_eq!(sources, vec!["High", "Low"]);
+}
+
+#[tokio::test]
+async fn later_pages_skip_sources_without_paging() {
+    let engine = TorrentSearchEngine::empty()
+        .with_source(fake("Paged", 5, true))
+        .with_source(fake("Flat", 50, false));
+
+    let results = engine.search(&SearchRequest::new("ubuntu").with_page(2)).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].source, "Paged");
+    assert_eq!(results[0].name, "ubuntu page 2");
+}
+
+#[test]
+fn category_searches_skip_sources_that_cannot_serve_it() {
+    let engine = TorrentSearchEngine::new();
+
+    let request = SearchRequest::new("x").with_category(Some(Category::Movies));
+    let names: Vec<&str> = engine.sources_for(&request).map(|s| s.name()).collect();
+    assert_eq!(names, vec!["YTS", "PirateBay", "1337x"]);
+
+    let request = SearchRequest::new("x").with_category(Some(Category::Anime));
+    let names: Vec<&str> = engine.sources_for(&request).map(|s| s.name()).collect();
+    assert_eq!(names, vec!["1337x"]);
+
+    let request = SearchRequest::new("x").with_category(Some(Category::Books));
+    let names: Vec<&str> = engine.sources_for(&request).map(|s| s.name()).collect();
+    assert_eq!(names, vec!["PirateBay"]);
+}
+
+#[tokio::test]
+async fn stream_yields_fast_sources_before_slow_ones() {
+    let engine = TorrentSearchEngine::empty()
+        .with_source(slow("Slow", Duration::from_millis(300)))
+        .with_source(fake("Fast", 10, false))
+        .with_source(slow("Broken", Duration::ZERO));
+
+    let request = SearchRequest::new("ubuntu");
+    let updates: Vec<_> = engine.search_stream(&request).collect().await;
+    let order: Vec<&str> = updates.iter().map(|u| u.source.as_str()).collect();
+    assert_eq!(order.last(), Some(&"Slow"));
+
+    let broken = updates.iter().find(|u| u.source == "Broken").unwrap();
+    assert_eq!(broken.state(), SourceState::Failed("boom".into()));
+}
+
+/// Source that never answers and records when its in-flight request is dropped
+struct HangingSource {
+    dropped: Arc<AtomicBool>,
+}
+
+struct DropFlag(Arc<AtomicBool>);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl TorrentSource for HangingSource {
+    fn name(&self) -> &str {
+        "Hanging"
+    }
+
+    fn capabilities(&self) -> SourceCapabilities {
+        SourceCapabilities::default()
+    }
+
+    async fn search(&self, _request: &SearchRequest) -> SourceResult<Vec<TorrentSearchResult>> {
+        let _flag = DropFlag(Arc::clone(&self.dropped));
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        Ok(Vec::new())
+    }
+}
+
+#[tokio::test]
+async fn cancel_aborts_in_flight_requests() {
+    let dropped = Arc::new(AtomicBool::new(false));
+    let engine = Arc::new(
+        TorrentSearchEngine::empty()
+            .with_source(fake("Fast", 10, false))
+            .with_source(Box::new(HangingSource { dropped: Arc::clone(&dropped) })),
+    );
+
+    let mut search = engine.spawn_search(SearchRequest::new("ubuntu"));
+    let first = search.next().await.unwrap();
+    assert_eq!(first.source, "Fast");
+    assert!(!dropped.load(Ordering::SeqCst));
+
+    search.cancel();
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while !search.is_finished() {
+            tokio::task::yield_now().await;
+        }
+    })
+    .await
+    .expect("search task should stop right away");
+    assert!(dropped.load(Ordering::SeqCst));
+    assert!(search.next().await.is_none());
+}
+
+#[tokio::test]
+async fn prepare_gives_up_on_slow_sources() {
+    let engine = TorrentSearchEngine::empty()
+        .with_source_timeout(Duration::from_millis(50))
+        .with_source(slow("Stalled", Duration::from_secs(30)));
+
+    let started = std::time::Instant::now();
+    engine.prepare().await;
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
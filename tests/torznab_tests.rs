@@ -0,0 +1,119 @@
+mod common;
+
+use common::{Route, StubServer};
+use tui_torrent::api::{SearchRequest, TorrentSource, TorznabClient};
+use tui_torrent::types::Category;
+
+const CAPS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<caps>
+  <searching><search available="yes" supportedParams="q"/></searching>
+  <limits max="100" default="50"/>
+  <categories>
+    <category id="2000" name="Movies">
+      <subcat id="2040" name="Movies/HD"/>
+    </category>
+    <category id="5000" name="TV">
+      <subcat id="5070" name="TV/Anime"/>
+    </category>
+    <category id="100044" name="Ebooks"/>
+  </categories>
+</caps>"#;
+
+const RESULTS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:torznab="http://torznab.com/schemas/2015/feed">
+  <channel>
+    <title>Jackett</title>
+    <item>
+      <title>Ubuntu 24.04 Desktop</title>
+      <link>http://jackett.local/dl/1.torrent</link>
+      <pubDate>Mon, 01 Jan 2024 10:00:00 +0000</pubDate>
+      <size>6114656256</size>
+      <torznab:attr name="category" value="4000"/>
+      <torznab:attr name="seeders" value="120"/>
+      <torznab:attr name="peers" value="150"/>
+      <torznab:attr name="infohash" value="C9E15763F722F23E98A29DECDFAE341B98D53056"/>
+      <torznab:attr name="magneturl" value="magnet:?xt=urn:btih:C9E15763F722F23E98A29DECDFAE341B98D53056&amp;dn=ubuntu"/>
+      <torznab:attr name="files" value="1"/>
+    </item>
+    <item>
+      <title>Torrent file only</title>
+      <link>http://jackett.local/dl/2.torrent</link>
+      <size>1000</size>
+      <torznab:attr name="seeders" value="3"/>
+    </item>
+  </channel>
+</rss>"#;
+
+const AUTH_ERROR: &str = r#"<?xml version="1.0" encoding="UTF-8"?><error code="100" description="Invalid API Key"/>"#;
+
+#[tokio::test]
+async fn parses_torznab_rss() {
+    let server = StubServer::start(vec![
+        Route::new("t=caps", "application/xml", CAPS),
+        Route::new("t=search", "application/rss+xml", RESULTS),
+    ])
+    .await;
+    let client = TorznabClient::new(&format!("{}/api", server.base_url), Some("secret")).with_name("Jackett");
+
+    let results = TorrentSource::search(&client, &SearchRequest::new("ubuntu")).await.unwrap();
+    assert_eq!(results.len(), 2);
+
+    let ubuntu = &results[0];
+    assert_eq!(ubuntu.name, "Ubuntu 24.04 Desktop");
+    assert_eq!(ubuntu.source, "Jackett");
+    assert_eq!(ubuntu.seeders, 120);
+    assert_eq!(ubuntu.leechers, 30);
+    assert_eq!(ubuntu.size_bytes, Some(6114656256));
+    assert_eq!(ubuntu.info_hash.as_deref(), Some("c9e15763f722f23e98a29decdfae341b98d53056"));
+    assert!(ubuntu.magnet_link.starts_with("magnet:?xt=urn:btih:C9E1"));
+    assert_eq!(ubuntu.category, Some(Category::Software));
+    assert_eq!(ubuntu.file_count, Some(1));
+    assert!(ubuntu.uploaded_at.is_some());
+
+    // Without a magnet or hash the .torrent link is handed to aria2 as-is
+    assert_eq!(results[1].magnet_link, "http://jackett.local/dl/2.torrent");
+
+    let search = server.requests().into_iter().find(|r| r.contains("t=search")).unwrap();
+    assert!(search.contains("q=ubuntu"));
+    assert!(search.contains("apikey=secret"));
+}
+
+#[tokio::test]
+async fn caps_drive_category_mapping() {
+    let server = StubServer::start(vec![
+        Route::new("t=caps", "application/xml", CAPS),
+        Route::new("t=search", "application/rss+xml", RESULTS),
+    ])
+    .await;
+    let client = TorznabClient::new(&format!("{}/api", server.base_url), None);
+
+    // Everything is assumed available until the caps are known
+    assert!(client.supports_category(Category::Music));
+
+    let caps = client.fetch_caps().await.unwrap();
+    assert_eq!(caps.limit, Some(100));
+    assert_eq!(caps.categories[&Category::Movies], vec![2000, 2040]);
+    assert_eq!(caps.categories[&Category::Anime], vec![5070]);
+    assert_eq!(caps.categories[&Category::Books], vec![100044]);
+    assert!(!client.supports_category(Category::Music));
+
+    let request = SearchRequest::new("dune").with_category(Some(Category::Movies));
+    TorrentSource::search(&client, &request).await.unwrap();
+    let search = server.requests().into_iter().find(|r| r.contains("t=search")).unwrap();
+    assert!(search.contains("cat=2000%2C2040"), "{}", search);
+    assert!(search.contains("limit=50&offset=0"), "{}", search);
+
+    // Later pages keep the page size and step by it
+    TorrentSource::search(&client, &request.clone().with_page(3)).await.unwrap();
+    let search = server.requests().into_iter().rfind(|r| r.contains("t=search")).unwrap();
+    assert!(search.contains("limit=50&offset=100"), "{}", search);
+}
+
+#[tokio::test]
+async fn reports_torznab_errors() {
+    let server = StubServer::start(vec![Route::new("t=search", "application/xml", AUTH_ERROR)]).await;
+    let client = TorznabClient::new(&format!("{}/api", server.base_url), Some("wrong"));
+
+    let error = TorrentSource::search(&client, &SearchRequest::new("ubuntu")).await.unwrap_err();
+    assert!(error.to_string().contains("Invalid API Key"));
+}
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 use tempfile::tempdir;
-use tui_torrent::utils::{ensure_download_dir_exists, format_bytes, format_speed};
+use tui_torrent::utils::{delete_download_files, ensure_download_dir_exists, format_bytes, format_duration, format_speed, parse_size};
 
 #[test]
 fn ensure_download_dir_creates_path() {
@@ -32,3 +32,52 @@ fn test_format_speed() {
     assert_eq!(format_speed("1073741824"), "1.0 GB/s");
     assert_eq!(format_speed("invalid"), "invalidB/s");
 }
+
+#[test]
+fn test_parse_size() {
+    assert_eq!(parse_size("700 MiB"), Some(700 * 1024 * 1024));
+    assert_eq!(parse_size("1.4 GB"), Some(1_400_000_000));
+    assert_eq!(parse_size("1,2 GB"), Some(1_200_000_000));
+    assert_eq!(parse_size("1,024.5 MB"), Some(1_024_500_000));
+    assert_eq!(parse_size("1,200MB"), Some(1_200_000_000));
+    assert_eq!(parse_size("1,234,567 B"), Some(1_234_567));
+    assert_eq!(parse_size("1,25 GB"), Some(1_250_000_000));
+    assert_eq!(parse_size("2GiB"), Some(2 * 1024 * 1024 * 1024));
+    assert_eq!(parse_size("512 KB"), Some(512_000));
+    assert_eq!(parse_size("123"), Some(123));
+    assert_eq!(parse_size(" 1 tb "), Some(1_000_000_000_000));
+    assert_eq!(parse_size("unknown"), None);
+    assert_eq!(parse_size("5 parsecs"), None);
+}
+
+#[test]
+fn deletes_download_files_and_empty_folders() {
+    let dir = tempdir().expect("tempdir");
+    let season = dir.path().join("Show S01");
+    std::fs::create_dir_all(season.join("Extras")).unwrap();
+    let files = [season.join("e01.mkv"), season.join("e02.mkv"), season.join("Extras").join("notes.txt")];
+    for file in &files {
+        std::fs::write(file, b"data").unwrap();
+    }
+    std::fs::write(dir.path().join("Show S01.aria2"), b"control").unwrap();
+    std::fs::write(dir.path().join("unrelated.iso"), b"keep").unwrap();
+
+    let paths: Vec<String> = files.iter().map(|f| f.to_string_lossy().to_string()).collect();
+    let mut refs: Vec<&str> = paths.iter().map(String::as_str).collect();
+    refs.push("[METADATA]c9e15763f722f23e98a29decdfae341b98d53056");
+
+    let control_file = dir.path().join("Show S01.aria2");
+    assert_eq!(delete_download_files(&refs, Some(&control_file)).unwrap(), 3);
+    assert!(!season.exists());
+    assert!(!control_file.exists());
+    assert!(dir.path().join("unrelated.iso").exists());
+}
+
+#[test]
+fn test_format_duration() {
+    use std::time::Duration;
+    assert_eq!(format_duration(Duration::from_secs(45)), "45s");
+    assert_eq!(format_duration(Duration::from_secs(190)), "3m 10s");
+    assert_eq!(format_duration(Duration::from_secs(7500)), "2h 05m");
+    assert_eq!(format_duration(Duration::from_secs(273600)), "3d 4h");
+}